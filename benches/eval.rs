@@ -0,0 +1,79 @@
+//! Compares evaluating expression trees through `Node::eval` row by row against
+//! the column-major batch evaluation, both walking the tree (`Node::eval_batch`)
+//! and running the compiled `bytecode::Program`, over a population of random trees.
+//! Run with `cargo bench --bench eval`
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::column::Column;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+const N_SUBS: usize = 2000;
+const N_ROWS: usize = 256;
+
+fn check_same(expected: &[nb::Type], got: &[nb::Type]) {
+    for (e, g) in expected.iter().zip(got.iter()) {
+        match (e, g) {
+            (nb::Type::Float(e), nb::Type::Float(g)) => {
+                assert!(e.to_bits() == g.to_bits() || (e.is_nan() && g.is_nan()))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn report(name: &str, time: Duration, base: Duration) {
+    println!(
+        "    {:<36}: {:>12.3?} ({:.2}x)",
+        name,
+        time,
+        base.as_secs_f64() / time.as_secs_f64()
+    );
+}
+
+fn main() {
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new()
+        .max_depth(6)
+        .float_range(-1.0, 1.0);
+    let mut rng = rand::thread_rng();
+
+    let population: Vec<_> = (0..N_SUBS)
+        .map(|_| et::Expr::random(vec![nb::TypeV::Float], nb::TypeV::Float, &table, &mut params))
+        .collect();
+    let train_x: Vec<_> = (0..N_ROWS)
+        .map(|_| vec![nb::Type::float(rng.gen_range(-10.0..=10.0))])
+        .collect();
+    let train_x_cols = Column::from_rows(&train_x);
+
+    let start_time = Instant::now();
+    let mut tree_out = Vec::with_capacity(N_SUBS * N_ROWS);
+    for p in population.iter() {
+        for x in train_x.iter() {
+            tree_out.push(p.root.eval(x));
+        }
+    }
+    let tree_time = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let mut batch_out = Vec::with_capacity(N_SUBS * N_ROWS);
+    for p in population.iter() {
+        batch_out.extend(p.eval_batch(&train_x_cols, N_ROWS).iter());
+    }
+    let batch_time = start_time.elapsed();
+
+    let start_time = Instant::now();
+    let mut vm_out = Vec::with_capacity(N_SUBS * N_ROWS);
+    for p in population.iter() {
+        vm_out.extend(p.compile().eval_columns(&train_x_cols, N_ROWS).iter());
+    }
+    let vm_time = start_time.elapsed();
+
+    check_same(&tree_out, &batch_out);
+    check_same(&tree_out, &vm_out);
+    println!("{N_SUBS} trees x {N_ROWS} rows");
+    report("tree walk, per row (Node::eval)", tree_time, tree_time);
+    report("tree walk, batch (Node::eval_batch)", batch_time, tree_time);
+    report("compiled, batch (Program)", vm_time, tree_time);
+}
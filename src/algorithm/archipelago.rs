@@ -0,0 +1,197 @@
+#![allow(dead_code)]
+//! Island model; several populations evolve independently, and every
+//! `migration_period` generations the fittest subjects of each island are copied
+//! to its neighbours, as given by the topology.
+//!
+//! The islands may have their own `BuilderParams` and `BuilderTable`s; with
+//! different ones they explore different parts of the search space, and the
+//! migrants spread what is found
+use super::expr_tree as et;
+use super::population::{Population, TrainingArgs, TrainingState};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::thread;
+
+/// Which islands the migrants of an island go to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// island `i` sends to island `i + 1`, the last one to the first
+    Ring,
+    /// every island sends to every other island
+    FullyConnected,
+    /// every island sends to another island picked at random on each migration
+    Random,
+}
+
+pub struct Archipelago {
+    pub islands: Vec<Population>,
+    pub topology: Topology,
+    /// number of generations between migrations
+    pub migration_period: usize,
+    /// number of the fittest subjects of an island sent on each migration
+    pub n_migrants: usize,
+    /// evolves each island on a thread of its own
+    pub parallel: bool,
+    /// randomizer for the `Random` topology
+    pub randomizer: ChaCha8Rng,
+}
+
+/// Builder pattern for Archipelago
+impl Archipelago {
+    /// the islands are expected to have their `BuilderParams` and `BuilderTable` set
+    pub fn new(islands: Vec<Population>) -> Archipelago {
+        assert!(!islands.is_empty(), "Archipelago needs at least one island");
+        Archipelago {
+            islands,
+            topology: Topology::Ring,
+            migration_period: 10,
+            n_migrants: 2,
+            parallel: false,
+            randomizer: ChaCha8Rng::from_entropy(),
+        }
+    }
+
+    pub fn topology(mut self, val: Topology) -> Self {
+        self.topology = val;
+        self
+    }
+
+    pub fn migration_period(mut self, val: usize) -> Self {
+        assert!(val > 0, "Migration period must be at least 1");
+        self.migration_period = val;
+        self
+    }
+
+    pub fn n_migrants(mut self, val: usize) -> Self {
+        self.n_migrants = val;
+        self
+    }
+
+    /// runs the islands on threads of their own; the fitness evaluation of each island
+    /// still uses `TrainingArgs::n_threads` threads, so setting that to 1 is advisable
+    pub fn parallel(mut self, val: bool) -> Self {
+        self.parallel = val;
+        self
+    }
+
+    /// seeds the randomizer of the `Random` topology; along with seeding the
+    /// `BuilderParams` of the islands, makes the training reproducible,
+    /// whether or not it runs in parallel
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.randomizer = ChaCha8Rng::seed_from_u64(seed);
+        self
+    }
+
+    /// islands receiving the migrants of each island
+    fn destinations(&mut self) -> Vec<Vec<usize>> {
+        let n = self.islands.len();
+        (0..n)
+            .map(|i| match self.topology {
+                _ if n == 1 => vec![],
+                Topology::Ring => vec![(i + 1) % n],
+                Topology::FullyConnected => (0..n).filter(|j| *j != i).collect(),
+                Topology::Random => {
+                    let j = self.randomizer.gen_range(0..n - 1);
+                    vec![if j >= i { j + 1 } else { j }]
+                }
+            })
+            .collect()
+    }
+
+    /// copies the fittest `n_migrants` subjects of every island to its destinations;
+    /// the migrants keep their errors, and are ranked along with the rest
+    /// of the population in the next generation
+    pub fn migrate(&mut self) {
+        let migrants: Vec<Vec<et::Expr>> = self
+            .islands
+            .iter()
+            .map(|island| {
+                // the subjects before the uncalculated ones are in the order of fitness
+                island
+                    .p
+                    .iter()
+                    .take_while(|p| !matches!(p.error, et::Error::Uncalculated))
+                    .take(self.n_migrants)
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        for (src, dsts) in self.destinations().into_iter().enumerate() {
+            for dst in dsts {
+                self.islands[dst].p.extend(migrants[src].iter().cloned());
+            }
+        }
+    }
+
+    /// runs up to `n_gens` generations on each island;
+    /// returns true if any of the islands found a good enough solution
+    fn run_islands(&mut self, args: &TrainingArgs, n_gens: usize) -> bool {
+        let run = |island: &mut Population| {
+            let end = (island.state.iter + n_gens).min(args.n_iter);
+            while island.state.iter < end {
+                if island.step(args, None).is_some() {
+                    return true;
+                }
+            }
+            false
+        };
+        let islands = self.islands.iter_mut();
+        if self.parallel {
+            thread::scope(|s| {
+                let handles: Vec<_> = islands.map(|island| s.spawn(move || run(island))).collect();
+                // joins all of the threads before checking the results
+                let done: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+                done.into_iter().any(|d| d)
+            })
+        } else {
+            // every island runs its generations, even after one is done
+            islands.map(run).collect::<Vec<_>>().into_iter().any(|d| d)
+        }
+    }
+
+    /// Trains all of the islands with the same `args`, migrating every `migration_period`
+    /// generations, until `n_iter` generations or any island stops (see `Population::step`).
+    /// returns the expression tree with least error across the islands;
+    /// with validation data, the one with least validation error.
+    /// The metrics records carry the index of their island. `TrainingArgs::checkpoint`
+    /// isn't supported, as the migrations can't be resumed; it panics if set
+    pub fn train(&mut self, args: &TrainingArgs) -> et::Expr {
+        assert!(
+            args.checkpoint_path.is_none(),
+            "Archipelago doesn't support checkpoint; save the islands with \
+             Population::save_checkpoint instead"
+        );
+        for (k, island) in self.islands.iter_mut().enumerate() {
+            island.island = Some(k);
+            island.state = TrainingState::new();
+            island.init_population_with(args.n_subs, args.init_method);
+        }
+        loop {
+            let done = self.run_islands(args, self.migration_period);
+            let iter = self.islands[0].state.iter;
+            if done || iter >= args.n_iter {
+                break;
+            }
+            if args.log_en {
+                println!("Log: migration after n_iter {}", iter);
+            }
+            self.migrate();
+        }
+        let best_valid = self
+            .islands
+            .iter()
+            .filter_map(|island| island.state.best_valid.as_ref())
+            .min_by(|a, b| a.valid_error.cmp(&b.valid_error));
+        best_valid.unwrap_or_else(|| self.best()).clone()
+    }
+
+    /// the subject with least error among the tops of the islands
+    pub fn best(&self) -> &et::Expr {
+        self.islands
+            .iter()
+            .map(|island| &island.p[0])
+            .filter(|p| !matches!(p.error, et::Error::Uncalculated))
+            .min_by(|a, b| a.error.cmp(&b.error))
+            .expect("Islands aren't trained")
+    }
+}
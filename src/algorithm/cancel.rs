@@ -0,0 +1,28 @@
+#![allow(dead_code)]
+//! Token for cancelling a training from another thread.
+//!
+//! The token is given to `TrainingArgs::cancel_token`, and a clone of it kept
+//! elsewhere, e.g. by a UI or a Ctrl-C handler; once cancelled, the training
+//! stops at the end of the generation it's on, and returns as it normally does
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Clones share the same state; cancelling any of them cancels all
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
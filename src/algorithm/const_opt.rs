@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+//! Local optimisation of the constants of a tree (see `Expr::optimize_consts`).
+//!
+//! Random mutation of `Val` nodes rarely hits precise coefficients; tuning them
+//! against the training data does. Nelder–Mead is used, as it needs no derivatives
+//! and only compares the objective values, so the errors can be compared with
+//! `Error::cmp` as they are elsewhere
+use std::cmp::Ordering;
+
+/// size of the initial simplex along a coordinate, relative to the value
+const INIT_STEP: f32 = 0.1;
+
+fn lerp(a: &[f32], b: &[f32], t: f32) -> Vec<f32> {
+    a.iter().zip(b).map(|(a, b)| a + t * (b - a)).collect()
+}
+
+/// minimises `f` with `max_iter` iterations of the Nelder–Mead method, starting
+/// with a simplex around `x0`; `cmp` orders the values of `f`, smaller being better.
+/// Returns the best point found and its value; never worse than `x0`
+pub fn nelder_mead<E>(
+    mut f: impl FnMut(&[f32]) -> E,
+    cmp: impl Fn(&E, &E) -> Ordering,
+    x0: &[f32],
+    max_iter: usize,
+) -> (Vec<f32>, E) {
+    let n = x0.len();
+    let mut simplex: Vec<(Vec<f32>, E)> = Vec::with_capacity(n + 1);
+    simplex.push((x0.to_vec(), f(x0)));
+    for i in 0..n {
+        let mut x = x0.to_vec();
+        x[i] += INIT_STEP * x[i].abs().max(1.0);
+        let fx = f(&x);
+        simplex.push((x, fx));
+    }
+    for _ in 0..max_iter {
+        simplex.sort_by(|a, b| cmp(&a.1, &b.1));
+        let centroid: Vec<f32> = (0..n)
+            .map(|i| simplex[..n].iter().map(|(x, _)| x[i]).sum::<f32>() / n as f32)
+            .collect();
+        let worst = &simplex[n];
+        let reflected = lerp(&centroid, &worst.0, -1.0);
+        let f_reflected = f(&reflected);
+        if cmp(&f_reflected, &simplex[0].1).is_lt() {
+            let expanded = lerp(&centroid, &worst.0, -2.0);
+            let f_expanded = f(&expanded);
+            simplex[n] = if cmp(&f_expanded, &f_reflected).is_lt() {
+                (expanded, f_expanded)
+            } else {
+                (reflected, f_reflected)
+            };
+            continue;
+        }
+        if cmp(&f_reflected, &simplex[n - 1].1).is_lt() {
+            simplex[n] = (reflected, f_reflected);
+            continue;
+        }
+        // contract towards the better of the reflected and the worst point
+        let outside = cmp(&f_reflected, &worst.1).is_lt();
+        let contracted = if outside {
+            lerp(&centroid, &reflected, 0.5)
+        } else {
+            lerp(&centroid, &worst.0, 0.5)
+        };
+        let f_contracted = f(&contracted);
+        let bound = if outside { &f_reflected } else { &worst.1 };
+        if cmp(&f_contracted, bound).is_lt() {
+            simplex[n] = (contracted, f_contracted);
+            continue;
+        }
+        // shrink towards the best point
+        let best = simplex[0].0.clone();
+        for (x, fx) in simplex[1..].iter_mut() {
+            *x = lerp(&best, x, 0.5);
+            *fx = f(x);
+        }
+    }
+    simplex.into_iter().min_by(|a, b| cmp(&a.1, &b.1)).unwrap()
+}
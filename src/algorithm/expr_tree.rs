@@ -1,7 +1,17 @@
 #![allow(dead_code)]
 
 use super::super::node::base as nb;
+use super::super::node::bytecode::Program;
+use super::super::node::codegen::{self, Lang};
+use super::super::node::column::Column;
+use super::super::node::parser::{self, ParseErr};
+use super::super::node::registry::NodeRegistry;
+use super::super::node::serialize::{self as ser, SerdeErr};
+use super::const_opt;
+use super::loss::Loss;
+use serde_json::{json, Value};
 use std::cmp::Ordering;
+use std::path::Path;
 
 /// This type represents the Error calculated
 /// by default, its Uncalculated
@@ -17,6 +27,7 @@ pub enum Error {
 }
 
 impl Error {
+    #[allow(clippy::should_implement_trait)]
     pub fn cmp(&self, other: &Error) -> Ordering {
         match (&self, other) {
             (_, Error::Uncalculated) => {
@@ -43,34 +54,75 @@ impl Error {
             }
         }
     }
+
+    /// JSON form of the error; `null` if uncalculated
+    pub fn to_json(&self) -> Value {
+        match self {
+            Error::Uncalculated => Value::Null,
+            Error::Err { real, nan } => json!({
+                "real": ser::type_to_json(nb::Type::Float(*real)),
+                "nan": ser::type_to_json(nb::Type::Float(*nan)),
+            }),
+        }
+    }
+
+    pub fn from_json(val: &Value) -> Result<Error, SerdeErr> {
+        if val.is_null() {
+            return Ok(Error::Uncalculated);
+        }
+        let get = |key: &str| match val.get(key) {
+            Some(v) => match ser::type_from_json(nb::TypeV::Float, v)? {
+                nb::Type::Float(f) => Ok(f),
+                _ => unreachable!(),
+            },
+            None => Err(SerdeErr::new(format!("Missing field `error.{}`", key))),
+        };
+        Ok(Error::Err {
+            real: get("real")?,
+            nan: get("nan")?,
+        })
+    }
 }
 
 pub struct Expr {
     pub root: nb::NodeRef,
     pub error: Error,
+    /// error on each of the training cases, as of the last `calc_err`;
+    /// non-finite errors are stored as infinity. Empty if uncalculated
+    pub case_errors: Vec<f32>,
+    /// error on the validation data, as of the last `calc_valid_err`
+    pub valid_error: Error,
     arg_types: Vec<nb::TypeV>,
     rtype: nb::TypeV,
 }
 
-impl Expr {
-    pub fn new(root: nb::NodeRef) -> Expr {
+impl Clone for Expr {
+    fn clone(&self) -> Self {
+        let root = self.root.deep_copy();
         Expr {
-            arg_types: root.get_arg_types().to_vec(),
-            rtype: root.get_rtype(),
-            error: Error::Uncalculated,
             root,
+            error: self.error,
+            case_errors: self.case_errors.clone(),
+            valid_error: self.valid_error,
+            arg_types: self.arg_types.clone(),
+            rtype: self.rtype,
         }
     }
+}
 
-    pub fn clone(&self) -> Self {
-        let root = self.root.deep_copy();
+impl Expr {
+    /// expression with `root` as the tree, taking arguments of type `arg_types`
+    pub fn new(root: nb::NodeRef, arg_types: Vec<nb::TypeV>) -> Expr {
         Expr {
+            arg_types,
+            rtype: root.get_rtype(),
+            error: Error::Uncalculated,
+            case_errors: vec![],
+            valid_error: Error::Uncalculated,
             root,
-            error: self.error,
-            arg_types: self.arg_types.clone(),
-            rtype: self.rtype,
         }
     }
+
     pub fn random(
         arg_types: Vec<nb::TypeV>,
         rtype: nb::TypeV,
@@ -82,6 +134,8 @@ impl Expr {
                 .get_rand_node(0, rtype, params)
                 .build_random_node(builder_table, &arg_types, rtype, 0, params),
             error: Error::Uncalculated,
+            case_errors: vec![],
+            valid_error: Error::Uncalculated,
             arg_types,
             rtype,
         }
@@ -91,41 +145,244 @@ impl Expr {
         self.root.get_tree_str(0)
     }
 
+    /// returns the expression in the form of mathematical expression;
+    /// this can be read back with `Expr::parse`
+    pub fn to_equation_str(&self) -> String {
+        self.root.get_equation_str()
+    }
+
+    /// builds an expression from its textual form (see `Node::get_equation_str`)
+    /// taking arguments of type `arg_types`;
+    /// `registry` must contain every node kind used in the expression
+    pub fn parse(
+        equation: &str,
+        arg_types: Vec<nb::TypeV>,
+        registry: &NodeRegistry,
+    ) -> Result<Expr, ParseErr> {
+        let root = parser::parse_equation(equation, &arg_types, registry)?;
+        Ok(Expr {
+            rtype: root.get_rtype(),
+            error: Error::Uncalculated,
+            case_errors: vec![],
+            valid_error: Error::Uncalculated,
+            arg_types,
+            root,
+        })
+    }
+
+    /// returns the source code of a standalone function named `name` in `lang`,
+    /// computing the expression; the parameters `x0`, `x1`, ... are typed as per `arg_types`.
+    /// Custom nodes (see `custom_nodes`) are computed by closures, which have no source code;
+    /// they are emitted as calls of functions with the name of the node, such as
+    /// `sigmoid(x0)`, which have to be defined along with the generated code
+    pub fn to_code(&self, name: &str, lang: Lang) -> String {
+        codegen::function(
+            name,
+            &self.arg_types,
+            self.rtype,
+            &self.root.get_code_str(lang),
+            lang,
+        )
+    }
+
+    /// returns the tree computing the derivative of the expression with respect to `x[var_idx]`,
+    /// simplified with `Node::prune`; the expression and the argument must be of float type
+    pub fn derivative(&self, var_idx: usize) -> nb::NodeRef {
+        assert_eq!(self.rtype, nb::TypeV::Float, "Only float expressions can be differentiated!!");
+        assert_eq!(
+            self.arg_types[var_idx],
+            nb::TypeV::Float,
+            "Can only differentiate with respect to float arguments!!"
+        );
+        self.root.derivative(var_idx).prune()
+    }
+
     pub fn type_check(&self) -> Result<(), nb::TypeErr> {
         self.root.type_check()
     }
 
+    /// lowers the expression tree into a flat program,
+    /// for faster evaluation over many inputs
+    pub fn compile(&self) -> Program {
+        Program::compile(&self.root)
+    }
+
+    /// evaluates the expression over `n_rows` data points at once;
+    /// `args` holds a column of `n_rows` values for each argument
+    pub fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column {
+        self.root.eval_batch(args, n_rows)
+    }
+
     /// calculates the error of an expression tree.
     /// Takes input and expected outputs as argument,
     /// calculates the output from the given input;
-    /// sets the `error` field of the tree with average
-    /// error over all the given inputs.
+    /// sets the `error` field of the tree with the `loss`
+    /// over all the given inputs (see `loss`).
+    /// Any `Fn(actual, predicted) -> error` is a loss, averaged over the inputs
     pub fn calc_err(
         &mut self,
         train_x: &[Vec<nb::Type>],
         train_y: &[nb::Type],
-        err_fn: &dyn Fn(nb::Type, nb::Type)->f32
-        
+        loss: &dyn Loss,
+    ) {
+        self.calc_err_columns(&Column::from_rows(train_x), train_y, loss)
+    }
+
+    /// same as `calc_err`, with the input in column-major form (see `Column::from_rows`).
+    /// The tree is compiled once, and each instruction of the compiled program
+    /// is run once over all the inputs
+    pub fn calc_err_columns(
+        &mut self,
+        train_x: &[Column],
+        train_y: &[nb::Type],
+        loss: &dyn Loss,
     ) {
-        let mut err_real: f32 = 0.0;
         let mut err_nan: f32 = 0.0;
+        let pred_y = self.compile().eval_columns(train_x, train_y.len());
 
-        for i in 0..train_x.len() {
-           
-            let e = (err_fn)(train_y[i], self.root.eval(&train_x[i]));
+        self.case_errors.clear();
+        for (i, y) in train_y.iter().enumerate() {
+            let e = loss.case_err(*y, pred_y.get(i));
             if e.is_finite() {
-                err_real += e;
+                self.case_errors.push(e);
             } else {
                 err_nan += 1.0;
+                self.case_errors.push(f32::INFINITY);
             }
         }
+        let err_real = loss.aggregate(&self.case_errors, train_y);
         self.error = Error::Err {
-            real: err_real / train_y.len() as f32,
+            real: err_real,
             nan: err_nan / train_y.len() as f32,
         }
     }
 
+    /// calculates the error on held-out data, as `calc_err` does, into `valid_error`;
+    /// `error` and `case_errors` stay those of the training data
+    pub fn calc_valid_err(
+        &mut self,
+        valid_x: &[Vec<nb::Type>],
+        valid_y: &[nb::Type],
+        loss: &dyn Loss,
+    ) {
+        let error = self.error;
+        let case_errors = std::mem::take(&mut self.case_errors);
+        self.calc_err(valid_x, valid_y, loss);
+        self.valid_error = std::mem::replace(&mut self.error, error);
+        self.case_errors = case_errors;
+    }
+
+    /// tunes the float constants of the tree to minimise the error on the data,
+    /// with `max_iter` iterations of Nelder–Mead (see `const_opt`);
+    /// calculates the error as `calc_err_columns` does, and it only gets better.
+    /// Returns the number of times the error was calculated
+    pub fn optimize_consts(
+        &mut self,
+        train_x: &[Column],
+        train_y: &[nb::Type],
+        loss: &dyn Loss,
+        max_iter: usize,
+    ) -> usize {
+        let mut consts = vec![];
+        self.root.get_float_consts(&mut consts);
+        if consts.is_empty() {
+            if matches!(self.error, Error::Uncalculated) {
+                self.calc_err_columns(train_x, train_y, loss);
+                return 1;
+            }
+            return 0;
+        }
+        let mut n_evals = 0;
+        let (best, _) = const_opt::nelder_mead(
+            |x| {
+                n_evals += 1;
+                self.root.set_float_consts(&mut x.iter().copied());
+                self.calc_err_columns(train_x, train_y, loss);
+                self.error
+            },
+            Error::cmp,
+            &consts,
+            max_iter,
+        );
+        self.root.set_float_consts(&mut best.into_iter());
+        self.calc_err_columns(train_x, train_y, loss);
+        n_evals + 1
+    }
+
+    /// simplifies the tree (see `Node::prune`); if it changes, the errors are
+    /// reset, as a rewrite may change the result on some inputs (e.g. the sign of a zero)
     pub fn prune(&mut self) {
-        self.root = self.root.prune();
+        let pruned = self.root.prune();
+        if pruned.get_equation_str() != self.root.get_equation_str() {
+            self.error = Error::Uncalculated;
+            self.case_errors.clear();
+            self.valid_error = Error::Uncalculated;
+        }
+        self.root = pruned;
+    }
+
+    /// returns the expression in versioned JSON form,
+    /// along with the argument types, return type and the calculated error
+    pub fn to_json(&self) -> Value {
+        json!({
+            "version": ser::FORMAT_VERSION,
+            "arg_types": ser::typev_list_to_json(&self.arg_types),
+            "rtype": ser::typev_to_json(self.rtype),
+            "error": self.error.to_json(),
+            "root": self.root.to_json(),
+        })
+    }
+
+    /// rebuilds an expression from its JSON form;
+    /// `registry` must contain every node kind used in the expression
+    pub fn from_json(val: &Value, registry: &NodeRegistry) -> Result<Expr, SerdeErr> {
+        match val.get("version").and_then(|v| v.as_u64()) {
+            Some(ser::FORMAT_VERSION) => {}
+            version => {
+                return Err(SerdeErr::new(format!(
+                    "Unsupported format version {:?}; expected {}",
+                    version,
+                    ser::FORMAT_VERSION
+                )))
+            }
+        }
+        let field = |key: &str| {
+            val.get(key)
+                .ok_or_else(|| SerdeErr::new(format!("Missing field `{}`", key)))
+        };
+        let arg_types = ser::typev_list_from_json(field("arg_types")?)?;
+        let rtype = ser::typev_from_json(field("rtype")?)?;
+        let error = Error::from_json(field("error")?)?;
+        let root = ser::node_from_json(field("root")?, &arg_types, registry)?;
+        if root.get_rtype() != rtype {
+            return Err(SerdeErr::new(format!(
+                "Expression returns {:?}; Got root of type {:?}",
+                rtype,
+                root.get_rtype()
+            )));
+        }
+        root.type_check()?;
+        Ok(Expr {
+            root,
+            error,
+            case_errors: vec![],
+            valid_error: Error::Uncalculated,
+            arg_types,
+            rtype,
+        })
+    }
+
+    /// writes the expression to `path` in JSON form
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SerdeErr> {
+        let text = serde_json::to_string_pretty(&self.to_json())
+            .map_err(|e| SerdeErr::new(e.to_string()))?;
+        std::fs::write(path, text).map_err(|e| SerdeErr::new(e.to_string()))
+    }
+
+    /// reads an expression written by `save`
+    pub fn load<P: AsRef<Path>>(path: P, registry: &NodeRegistry) -> Result<Expr, SerdeErr> {
+        let text = std::fs::read_to_string(path).map_err(|e| SerdeErr::new(e.to_string()))?;
+        let val: Value = serde_json::from_str(&text).map_err(|e| SerdeErr::new(e.to_string()))?;
+        Self::from_json(&val, registry)
     }
 }
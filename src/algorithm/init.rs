@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+//! Strategies for building the random trees of the initial population and
+//! of the immigrants (see `TrainingArgs::init_method`).
+//!
+//! All of them build the trees with `BuilderTable::get_rand_node`, within depth
+//! limits set for each tree; branches end in terminals at random, with
+//! `termination_probability`, between the minimum and the maximum depth
+use super::super::node::base as nb;
+use super::expr_tree as et;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitMethod {
+    /// branches may end anywhere between the `min_depth` and the `max_depth`
+    /// of the `BuilderParams`
+    Grow,
+    /// every branch goes down to the `max_depth` of the `BuilderParams`
+    Full,
+    /// the maximum depth is ramped evenly over `min_depth..=max_depth` across the trees,
+    /// with half of the trees of each depth grown and the other half full
+    RampedHalfAndHalf { min_depth: usize, max_depth: usize },
+}
+
+impl InitMethod {
+    /// builds the `i`th of the trees built together, e.g. for the initial population
+    pub fn build(
+        &self,
+        i: usize,
+        arg_types: &[nb::TypeV],
+        rtype: nb::TypeV,
+        builder_table: &nb::BuilderTable,
+        params: &mut nb::BuilderParams,
+    ) -> et::Expr {
+        let (min_depth, max_depth) = match *self {
+            InitMethod::Grow => (params.min_depth, params.max_depth),
+            InitMethod::Full => (params.max_depth, params.max_depth),
+            InitMethod::RampedHalfAndHalf {
+                min_depth,
+                max_depth,
+            } => {
+                assert!(
+                    min_depth <= max_depth,
+                    "Invalid depth range of ramped half-and-half"
+                );
+                // consecutive trees share a depth, one grown and the other full
+                let depth = min_depth + (i / 2) % (max_depth - min_depth + 1);
+                if i.is_multiple_of(2) {
+                    (params.min_depth.min(depth), depth)
+                } else {
+                    (depth, depth)
+                }
+            }
+        };
+        let limits = (params.min_depth, params.max_depth);
+        params.min_depth = min_depth;
+        params.max_depth = max_depth;
+        let expr = et::Expr::random(arg_types.to_vec(), rtype, builder_table, params);
+        params.min_depth = limits.0;
+        params.max_depth = limits.1;
+        expr
+    }
+}
@@ -0,0 +1,230 @@
+#![allow(dead_code)]
+//! Loss functions scoring the output of an expression against the training data.
+//!
+//! A loss gives the error on each case (`Loss::case_err`), which the selection
+//! strategies working case by case use as is (see `selection::EpsilonLexicase`),
+//! and aggregates those over the data set into the error of the expression
+//! (`Loss::aggregate`). Cases with non-finite errors are counted in the `nan` part
+//! of `Error` instead, and left out of the aggregate.
+//! Values of any type are taken as numbers; `true` is 1 and `false` is 0
+use super::super::node::base::Type;
+
+pub trait Loss: Send + Sync {
+    /// error of the output on a single case
+    fn case_err(&self, actual: Type, predicted: Type) -> f32;
+
+    /// error over the data set, from the errors of all the cases and their actual values;
+    /// non-finite errors are given as infinity. The mean of the finite errors by default
+    fn aggregate(&self, case_errs: &[f32], actual: &[Type]) -> f32 {
+        mean(&finite(case_errs, actual).0)
+    }
+}
+
+/// any `Fn(actual, predicted) -> error` is a loss; the finite errors are summed
+/// and divided by the number of cases, as `TrainingArgs::err_fn` always did
+impl<F: Fn(Type, Type) -> f32 + Send + Sync> Loss for F {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        self(actual, predicted)
+    }
+
+    fn aggregate(&self, case_errs: &[f32], _actual: &[Type]) -> f32 {
+        finite_sum_per_case(case_errs)
+    }
+}
+
+/// mean of `v`; 0 if empty
+fn mean(v: &[f32]) -> f32 {
+    if v.is_empty() {
+        0.0
+    } else {
+        v.iter().sum::<f32>() / v.len() as f32
+    }
+}
+
+/// errors and actual values of the cases with finite errors
+fn finite(case_errs: &[f32], actual: &[Type]) -> (Vec<f32>, Vec<Type>) {
+    case_errs
+        .iter()
+        .zip(actual)
+        .filter(|(e, _)| e.is_finite())
+        .unzip()
+}
+
+/// sum of the finite errors over the number of all the cases; 0 if empty
+fn finite_sum_per_case(case_errs: &[f32]) -> f32 {
+    if case_errs.is_empty() {
+        0.0
+    } else {
+        case_errs.iter().filter(|e| e.is_finite()).sum::<f32>() / case_errs.len() as f32
+    }
+}
+
+fn sq_err(actual: Type, predicted: Type) -> f32 {
+    (actual.as_f32() - predicted.as_f32()).powi(2)
+}
+
+/// Relative error, `|predicted - actual| / |actual|`;
+/// the absolute error where `actual` is 0
+pub struct Relative;
+
+impl Loss for Relative {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        let (y, p) = (actual.as_f32(), predicted.as_f32());
+        if y != 0.0 {
+            ((p - y) / y).abs()
+        } else {
+            p.abs()
+        }
+    }
+}
+
+/// Error relative to the prediction, `|actual - predicted| / |predicted|`;
+/// `|actual|` where the prediction is 0. The default loss of training, aggregated
+/// as `TrainingArgs::err_fn` losses are (the finite errors over the number of cases)
+pub struct PredRelative;
+
+impl Loss for PredRelative {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        let (y, p) = (actual.as_f32(), predicted.as_f32());
+        if p != 0.0 {
+            ((y - p) / p).abs()
+        } else {
+            y.abs()
+        }
+    }
+
+    fn aggregate(&self, case_errs: &[f32], _actual: &[Type]) -> f32 {
+        finite_sum_per_case(case_errs)
+    }
+}
+
+/// Mean squared error
+pub struct Mse;
+
+impl Loss for Mse {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        sq_err(actual, predicted)
+    }
+}
+
+/// Root mean squared error; the cases are scored by the squared error
+pub struct Rmse;
+
+impl Loss for Rmse {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        sq_err(actual, predicted)
+    }
+
+    fn aggregate(&self, case_errs: &[f32], actual: &[Type]) -> f32 {
+        mean(&finite(case_errs, actual).0).sqrt()
+    }
+}
+
+/// Mean absolute error
+pub struct Mae;
+
+impl Loss for Mae {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        (actual.as_f32() - predicted.as_f32()).abs()
+    }
+}
+
+/// Mean Huber loss; squared for errors within `delta`, linear beyond,
+/// so outliers weigh less than with `Mse`
+pub struct Huber {
+    pub delta: f32,
+}
+
+impl Huber {
+    pub fn new(delta: f32) -> Huber {
+        assert!(delta > 0.0, "Delta of Huber loss must be positive");
+        Huber { delta }
+    }
+}
+
+impl Loss for Huber {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        let e = (actual.as_f32() - predicted.as_f32()).abs();
+        if e <= self.delta {
+            0.5 * e * e
+        } else {
+            self.delta * (e - 0.5 * self.delta)
+        }
+    }
+}
+
+/// Coefficient of determination, as `1 - R²` so that smaller is better;
+/// the squared errors relative to the variance of the actual values.
+/// The cases are scored by the squared error
+pub struct RSquared;
+
+impl Loss for RSquared {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        sq_err(actual, predicted)
+    }
+
+    fn aggregate(&self, case_errs: &[f32], actual: &[Type]) -> f32 {
+        let (case_errs, actual) = finite(case_errs, actual);
+        let ys: Vec<_> = actual.iter().map(|y| y.as_f32()).collect();
+        let y_mean = mean(&ys);
+        let ss_tot: f32 = ys.iter().map(|y| (y - y_mean).powi(2)).sum();
+        let ss_res: f32 = case_errs.iter().sum();
+        if ss_tot > 0.0 {
+            ss_res / ss_tot
+        } else if ss_res == 0.0 {
+            0.0
+        } else {
+            f32::INFINITY
+        }
+    }
+}
+
+/// Mean binary cross-entropy; the actual value is the class (0/1 or `Bool`),
+/// the output the probability of class 1, clipped to (0, 1)
+pub struct LogLoss;
+
+impl LogLoss {
+    const EPS: f32 = 1e-7;
+}
+
+impl Loss for LogLoss {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        let p = predicted.as_f32().clamp(Self::EPS, 1.0 - Self::EPS);
+        if actual.as_f32() > 0.5 {
+            -p.ln()
+        } else {
+            -(1.0 - p).ln()
+        }
+    }
+}
+
+/// Mean hinge loss, `max(0, 1 - y * output)`; the actual value is the class,
+/// `y` being 1 for positive values (or `true`) and -1 otherwise
+pub struct Hinge;
+
+impl Loss for Hinge {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        let y = if actual.as_f32() > 0.0 { 1.0 } else { -1.0 };
+        (1.0 - y * predicted.as_f32()).max(0.0)
+    }
+}
+
+/// Classification error, the fraction of cases where the output is not the actual class;
+/// for `Bool`, `Int` and `UInt` outputs
+pub struct ZeroOne;
+
+impl Loss for ZeroOne {
+    fn case_err(&self, actual: Type, predicted: Type) -> f32 {
+        let same = match (actual, predicted) {
+            (Type::Bool(a), Type::Bool(b)) => a == b,
+            (Type::Int(a), Type::Int(b)) => a == b,
+            (Type::UInt(a), Type::UInt(b)) => a == b,
+            (a, b) => a.as_f32() == b.as_f32(),
+        };
+        if same {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
@@ -0,0 +1,228 @@
+#![allow(dead_code)]
+//! Structured record of every generation of the training, written to a CSV
+//! or JSON Lines sink (see `TrainingArgs::metrics`) instead of the free-form
+//! text of `log_en`.
+//!
+//! The errors are those of the population as evaluated in the generation, before
+//! the unfit are purged and the new subjects added; the timings are those of the
+//! phases as logged with `exec_time_log_en`, 0 for the ones that didn't run
+use super::super::node::base as nb;
+use super::super::node::serialize as ser;
+use super::expr_tree::{self as et, Error};
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// the timed phases of a generation
+pub const PHASES: [&str; 10] = [
+    "prune_population",
+    "calc_err",
+    "sort_population",
+    "sort_pareto",
+    "optimize_consts",
+    "purge_unfit",
+    "init_population",
+    "cross_breed",
+    "generate_mutants",
+    "mass_extinction",
+];
+
+#[derive(Debug, Clone)]
+pub struct GenerationMetrics {
+    pub iter: usize,
+    /// island of an `Archipelago` the record is of; `None` for a single population
+    pub island: Option<usize>,
+    /// number of subjects evaluated
+    pub population: usize,
+    pub best_error: Error,
+    pub median_error: Error,
+    pub worst_error: Error,
+    pub mean_depth: f32,
+    pub mean_node_count: f32,
+    pub mutants_tried: usize,
+    pub mutants_added: usize,
+    pub crossovers_tried: usize,
+    pub crossovers_added: usize,
+    /// time spent in each of `PHASES`, in the same order
+    pub timings: [Duration; PHASES.len()],
+}
+
+impl GenerationMetrics {
+    pub fn new(iter: usize) -> GenerationMetrics {
+        GenerationMetrics {
+            iter,
+            island: None,
+            population: 0,
+            best_error: Error::Uncalculated,
+            median_error: Error::Uncalculated,
+            worst_error: Error::Uncalculated,
+            mean_depth: 0.0,
+            mean_node_count: 0.0,
+            mutants_tried: 0,
+            mutants_added: 0,
+            crossovers_tried: 0,
+            crossovers_added: 0,
+            timings: [Duration::ZERO; PHASES.len()],
+        }
+    }
+
+    /// adds `elapsed` to the time of `phase`, one of `PHASES`
+    pub fn time(&mut self, phase: &str, elapsed: Duration) {
+        let idx = PHASES
+            .iter()
+            .position(|p| *p == phase)
+            .unwrap_or_else(|| panic!("Unknown phase {}", phase));
+        self.timings[idx] += elapsed;
+    }
+
+    /// fills in the size, errors and tree sizes from the subjects `p`;
+    /// the uncalculated ones only count towards the tree sizes
+    pub fn measure(&mut self, p: &[et::Expr]) {
+        self.population = p.len();
+        let mut errs: Vec<Error> = p
+            .iter()
+            .map(|p| p.error)
+            .filter(|e| !matches!(e, Error::Uncalculated))
+            .collect();
+        errs.sort_by(|a, b| a.cmp(b));
+        if !errs.is_empty() {
+            self.best_error = errs[0];
+            self.median_error = errs[errs.len() / 2];
+            self.worst_error = errs[errs.len() - 1];
+        }
+        if !p.is_empty() {
+            let n = p.len() as f32;
+            self.mean_depth = p.iter().map(|p| p.root.get_max_depth() as f32).sum::<f32>() / n;
+            self.mean_node_count = p
+                .iter()
+                .map(|p| p.root.get_node_count() as f32)
+                .sum::<f32>()
+                / n;
+        }
+    }
+
+    fn csv_header() -> String {
+        let mut cols: Vec<String> = [
+            "iter",
+            "island",
+            "population",
+            "best_real",
+            "best_nan",
+            "median_real",
+            "median_nan",
+            "worst_real",
+            "worst_nan",
+            "mean_depth",
+            "mean_node_count",
+            "mutants_tried",
+            "mutants_added",
+            "crossovers_tried",
+            "crossovers_added",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+        cols.extend(PHASES.iter().map(|p| format!("{}_secs", p)));
+        cols.join(",")
+    }
+
+    fn to_csv(&self) -> String {
+        // uncalculated errors are left empty
+        let err = |e: Error| match e {
+            Error::Uncalculated => ",".to_string(),
+            Error::Err { real, nan } => format!("{},{}", real, nan),
+        };
+        let mut cols = vec![
+            self.iter.to_string(),
+            // empty for a single population
+            self.island.map_or(String::new(), |i| i.to_string()),
+            self.population.to_string(),
+            err(self.best_error),
+            err(self.median_error),
+            err(self.worst_error),
+            self.mean_depth.to_string(),
+            self.mean_node_count.to_string(),
+            self.mutants_tried.to_string(),
+            self.mutants_added.to_string(),
+            self.crossovers_tried.to_string(),
+            self.crossovers_added.to_string(),
+        ];
+        cols.extend(self.timings.iter().map(|t| t.as_secs_f64().to_string()));
+        cols.join(",")
+    }
+
+    pub fn to_json(&self) -> Value {
+        let timings: Map<String, Value> = PHASES
+            .iter()
+            .zip(self.timings.iter())
+            .map(|(p, t)| (p.to_string(), json!(t.as_secs_f64())))
+            .collect();
+        json!({
+            "iter": self.iter,
+            "island": self.island,
+            "population": self.population,
+            "best_error": self.best_error.to_json(),
+            "median_error": self.median_error.to_json(),
+            "worst_error": self.worst_error.to_json(),
+            "mean_depth": ser::type_to_json(nb::Type::Float(self.mean_depth)),
+            "mean_node_count": ser::type_to_json(nb::Type::Float(self.mean_node_count)),
+            "mutants_tried": self.mutants_tried,
+            "mutants_added": self.mutants_added,
+            "crossovers_tried": self.crossovers_tried,
+            "crossovers_added": self.crossovers_added,
+            "timings_secs": timings,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// a header line, then a line of comma separated values per generation
+    Csv,
+    /// a JSON object per line, per generation
+    JsonLines,
+}
+
+/// Destination of the per-generation records; shared by the islands of an
+/// `Archipelago`, whose records are interleaved and carry the island they are of
+pub struct MetricsSink {
+    format: MetricsFormat,
+    /// the writer, and whether anything has been written to it yet
+    out: Mutex<(Box<dyn Write + Send>, bool)>,
+}
+
+impl MetricsSink {
+    pub fn new(out: Box<dyn Write + Send>, format: MetricsFormat) -> MetricsSink {
+        MetricsSink {
+            format,
+            out: Mutex::new((out, false)),
+        }
+    }
+
+    /// writes to a new file at `path`, replacing any existing one
+    pub fn create<P: AsRef<Path>>(path: P, format: MetricsFormat) -> io::Result<MetricsSink> {
+        let file = File::create(path)?;
+        Ok(Self::new(Box::new(BufWriter::new(file)), format))
+    }
+
+    /// writes the record of a generation; flushed right away, so that the
+    /// progress can be followed while training
+    pub fn write(&self, metrics: &GenerationMetrics) -> io::Result<()> {
+        let mut guard = self.out.lock().unwrap();
+        let (out, started) = &mut *guard;
+        match self.format {
+            MetricsFormat::Csv => {
+                if !*started {
+                    writeln!(out, "{}", GenerationMetrics::csv_header())?;
+                }
+                writeln!(out, "{}", metrics.to_csv())?;
+            }
+            MetricsFormat::JsonLines => writeln!(out, "{}", metrics.to_json())?,
+        }
+        *started = true;
+        out.flush()
+    }
+}
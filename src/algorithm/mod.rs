@@ -1,2 +1,12 @@
+pub mod archipelago;
+pub mod cancel;
+pub mod const_opt;
 pub mod expr_tree;
+pub mod init;
+pub mod loss;
+pub mod metrics;
+pub mod observer;
+pub mod pareto;
 pub mod population;
+pub mod report;
+pub mod selection;
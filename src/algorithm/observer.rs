@@ -0,0 +1,28 @@
+#![allow(dead_code)]
+//! Hooks into the training loop, for following the progress of a run
+//! (streaming it, plotting the convergence) and stopping it from outside.
+//!
+//! Observers are added to `TrainingArgs`, and called by `Population::step` on every
+//! generation; with an `Archipelago` they're called for each of the islands,
+//! from the threads of the islands if it runs in parallel. The hooks take `&self`,
+//! so an observer keeping track of things needs interior mutability
+use super::expr_tree as et;
+use super::population::Population;
+
+pub trait Observer: Send + Sync {
+    /// called at the end of generation `iter` (counting from 0)
+    fn on_generation_end(&self, _iter: usize, _popln: &Population) {}
+
+    /// called when generation `iter` finds a subject with less error than any before
+    fn on_new_best(&self, _iter: usize, _best: &et::Expr) {}
+
+    /// called when generation `iter` triggers a mass extinction,
+    /// after the population is refilled
+    fn on_mass_extinction(&self, _iter: usize, _popln: &Population) {}
+
+    /// checked at the end of every generation; the training stops
+    /// as soon as it returns true
+    fn should_stop(&self) -> bool {
+        false
+    }
+}
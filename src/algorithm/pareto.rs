@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+//! NSGA-II style ranking of a population on two objectives;
+//! the error of a subject and the complexity of its tree, both minimised.
+//!
+//! A subject dominates another if it is no worse on both the objectives and better
+//! on at least one. The population is split into fronts; the first front is
+//! the subjects no one dominates (the Pareto front), the second front is the ones
+//! only dominated by the first front, and so on. Within a front, subjects in sparse
+//! regions of the front (larger crowding distance) are preferred, to keep it diverse
+use super::expr_tree::{self as et, Error};
+use std::cmp::Ordering;
+
+/// Measure of the complexity of an expression tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Complexity {
+    /// number of nodes in the tree, see `Node::get_node_count`
+    NodeCount,
+    /// depth of the tree, see `Node::get_max_depth`
+    Depth,
+}
+
+impl Complexity {
+    pub fn of(&self, expr: &et::Expr) -> usize {
+        match self {
+            Complexity::NodeCount => expr.root.get_node_count(),
+            Complexity::Depth => expr.root.get_max_depth(),
+        }
+    }
+}
+
+/// checks if subject `a` dominates subject `b`, given their (error, complexity);
+/// errors are compared with `Error::cmp`, so the errors must be calculated
+pub fn dominates(a: (&Error, usize), b: (&Error, usize)) -> bool {
+    let err = a.0.cmp(b.0);
+    err != Ordering::Greater && a.1 <= b.1 && (err == Ordering::Less || a.1 < b.1)
+}
+
+/// splits the subjects into fronts of non-domination; returns the indices
+/// of the subjects in each front, the Pareto front first.
+/// Each front is in the order of increasing error (and decreasing complexity)
+pub fn non_dominated_fronts(population: &[&et::Expr], complexity: Complexity) -> Vec<Vec<usize>> {
+    let cx: Vec<_> = population.iter().map(|p| complexity.of(p)).collect();
+    let mut order: Vec<usize> = (0..population.len()).collect();
+    order.sort_by(|a, b| {
+        population[*a]
+            .error
+            .cmp(&population[*b].error)
+            .then(cx[*a].cmp(&cx[*b]))
+    });
+    // with two objectives, a subject can only be dominated by the ones before it
+    // in `order`, and within a front it suffices to check the last subject added;
+    // it is the one with the least complexity
+    let mut fronts: Vec<Vec<usize>> = vec![];
+    for i in order {
+        let is_dominated = |f: &Vec<usize>| {
+            let last = *f.last().unwrap();
+            dominates(
+                (&population[last].error, cx[last]),
+                (&population[i].error, cx[i]),
+            )
+        };
+        match fronts.iter().position(|f| !is_dominated(f)) {
+            Some(k) => fronts[k].push(i),
+            None => fronts.push(vec![i]),
+        }
+    }
+    fronts
+}
+
+/// crowding distance of each subject of `front`, in the order of `front`
+/// (as returned by `non_dominated_fronts`); the sum over the objectives of the
+/// normalised distance between the neighbours of the subject on the front.
+/// The subjects at the ends of the front get infinity.
+/// The error objective uses the real part of the error; non-finite errors add nothing
+pub fn crowding_distance(
+    population: &[&et::Expr],
+    front: &[usize],
+    complexity: Complexity,
+) -> Vec<f32> {
+    let n = front.len();
+    let mut dist = vec![0.0f32; n];
+    if n <= 2 {
+        return vec![f32::INFINITY; n];
+    }
+    let real = |i: usize| match population[front[i]].error {
+        Error::Err { real, .. } => real,
+        Error::Uncalculated => f32::NAN,
+    };
+    let cx = |i: usize| complexity.of(population[front[i]]) as f32;
+    // the front is sorted on the error, and so in reverse on the complexity
+    let objectives: [Vec<f32>; 2] = [(0..n).map(real).collect(), (0..n).map(cx).collect()];
+    for obj in objectives.iter() {
+        let finite = obj.iter().filter(|v| v.is_finite());
+        let range = finite.clone().fold(f32::MIN, |a, b| a.max(*b))
+            - finite.fold(f32::MAX, |a, b| a.min(*b));
+        for i in 1..n - 1 {
+            let d = (obj[i + 1] - obj[i - 1]).abs() / range;
+            if d.is_finite() {
+                dist[i] += d;
+            }
+        }
+    }
+    dist[0] = f32::INFINITY;
+    dist[n - 1] = f32::INFINITY;
+    dist
+}
@@ -1,15 +1,42 @@
 use super::super::node::base as nb;
+use super::super::node::column::Column;
+use super::super::node::registry::NodeRegistry;
+use super::super::node::serialize::{self as ser, SerdeErr};
+use super::cancel::CancelToken;
 use super::expr_tree::{self as et, Error};
-use rand::distributions::weighted::WeightedIndex;
-use rand::distributions::Distribution;
-use rand::Rng;
-use std::time::Instant;
+use super::init::InitMethod;
+use super::loss::{self, Loss};
+use super::metrics::{GenerationMetrics, MetricsSink};
+use super::observer::Observer;
+use super::pareto::{self, Complexity};
+use super::report::{StopReason, TrainingReport};
+use super::selection::{self as sel, Selection};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub struct TrainingArgs<'a> {
     /// training data input
     pub train_x: Option<&'a [Vec<nb::Type>]>,
     ///train data output
     pub train_y: Option<&'a [nb::Type]>,
+    /// validation data input; the best subject of each iteration is scored on it,
+    /// and the one with the least validation error is returned
+    pub valid_x: Option<&'a [Vec<nb::Type>]>,
+    /// validation data output
+    pub valid_y: Option<&'a [nb::Type]>,
+    /// if the validation error doesn't improve for `patience` iterations,
+    /// training is stopped; 0 never stops
+    pub patience: usize,
+    /// training is stopped after the generation that exceeds this time
+    pub max_duration: Option<Duration>,
+    /// training is stopped after the generation that exceeds this many
+    /// calculations of errors (see `TrainingReport::n_evaluations`); 0 never stops
+    pub max_evaluations: usize,
+    /// training is stopped after the generation this is cancelled in
+    pub cancel_token: Option<CancelToken>,
     /// number of subjects in population at the end of iteration
     pub n_subs: usize,
     /// after `purge_period` iterations, unfit children are purged from population
@@ -22,6 +49,11 @@ pub struct TrainingArgs<'a> {
     pub breed_probability: f32,
     /// every `new_sub_intro_period` iterations, completely new random subjects are introduced into the population
     pub new_sub_intro_period: usize,
+    /// how the random subjects of the initial population, and of the refill
+    /// after a mass extinction, are built
+    pub init_method: InitMethod,
+    /// how the random subjects introduced every `new_sub_intro_period` iterations are built
+    pub immigrant_init_method: InitMethod,
     /// n_subs*new_sub_increase_ratio.0/*new_sub_increase_ratio.1 number of
     /// new subjects are added to population every `new_sub_intro_period` iterations
     pub new_sub_increase_ratio: (usize, usize),
@@ -40,14 +72,38 @@ pub struct TrainingArgs<'a> {
     pub max_allowed_err: f32,
     /// enables logging
     pub log_en: bool,
-    /// Error function to compare predicted output vs actual output,
-    /// of the form (actual_val, predicted_val)->error
-    /// The error should be normalized
-    pub err_fn: Box<dyn Fn(nb::Type, nb::Type) -> f32>,
+    /// Loss to compare predicted output vs actual output,
+    /// giving the error of a subject; see `loss` for the built-in ones
+    pub loss: Box<dyn Loss>,
     /// enables logging execution time
     pub exec_time_log_en: bool,
     /// maximum possible population at the end of an iteration
     pub max_population: usize,
+    /// number of threads the fitness evaluation is spread across;
+    /// 0 uses all the available cores
+    pub n_threads: usize,
+    /// strategy for picking the parents of mutants and the fathers of children
+    pub selection: Box<dyn Selection>,
+    /// strategy for picking the mothers of children, which get a subtree of the father
+    pub mother_selection: Box<dyn Selection>,
+    /// number of the top subjects whose float constants are tuned every iteration
+    /// (see `Expr::optimize_consts`); 0 disables the tuning
+    pub const_opt_n_subs: usize,
+    /// number of iterations of tuning the constants of a subject
+    pub const_opt_n_iter: usize,
+    /// number of the top subjects the weights of the build table are adapted to
+    /// every iteration (see `BuilderTable::adapt_weights`); 0 keeps the weights
+    pub adapt_n_best: usize,
+    /// rate the weights of the build table are adapted at
+    pub adapt_rate: f32,
+    /// hooks called as the training goes (see `Observer`)
+    pub observers: Vec<Box<dyn Observer>>,
+    /// sink the metrics of every generation are written to
+    pub metrics: Option<MetricsSink>,
+    /// file the population is checkpointed to (see `Population::save_checkpoint`)
+    pub checkpoint_path: Option<PathBuf>,
+    /// number of iterations between checkpoints
+    pub checkpoint_period: usize,
 }
 
 impl<'a> TrainingArgs<'a> {
@@ -55,10 +111,18 @@ impl<'a> TrainingArgs<'a> {
         Self {
             train_x: None,
             train_y: None,
+            valid_x: None,
+            valid_y: None,
+            patience: 0,
+            max_duration: None,
+            max_evaluations: 0,
+            cancel_token: None,
             n_subs: 128,
             n_iter: 1000,
             log_en: false,
             new_sub_intro_period: 5,
+            init_method: InitMethod::Grow,
+            immigrant_init_method: InitMethod::Grow,
             new_sub_increase_ratio: (1, 2),
             top_children_ratio: (1, 2),
             mut_probability: 0.1,
@@ -68,17 +132,19 @@ impl<'a> TrainingArgs<'a> {
             mass_extinction_th: 50,
             max_allowed_err: 0.0,
             exec_time_log_en: false,
-            err_fn: Box::new(|act_val, pred_val| match (act_val, pred_val) {
-                (nb::Type::Float(pred_y_dat), nb::Type::Float(train_y_dat)) => {
-                    if train_y_dat != 0.0 {
-                        ((pred_y_dat - train_y_dat) / train_y_dat).abs()
-                    } else {
-                        pred_y_dat.abs()
-                    }
-                }
-                (_, _) => unimplemented!(),
-            }),
+            loss: Box::new(loss::PredRelative),
             max_population: 10000,
+            n_threads: 1,
+            selection: Box::new(sel::LinearRank::default()),
+            mother_selection: Box::new(sel::Uniform),
+            const_opt_n_subs: 0,
+            const_opt_n_iter: 20,
+            adapt_n_best: 0,
+            adapt_rate: 0.1,
+            observers: vec![],
+            metrics: None,
+            checkpoint_path: None,
+            checkpoint_period: 10,
         }
     }
     #[allow(dead_code)]
@@ -94,6 +160,59 @@ impl<'a> TrainingArgs<'a> {
         self
     }
     #[allow(dead_code)]
+    /// validation data input; the best subject of each iteration is scored on it,
+    /// and the one with the least validation error is returned
+    pub fn valid_x(mut self, val: &'a [Vec<nb::Type>]) -> Self {
+        self.valid_x = Some(val);
+        self
+    }
+    #[allow(dead_code)]
+    /// validation data output
+    pub fn valid_y(mut self, val: &'a [nb::Type]) -> Self {
+        self.valid_y = Some(val);
+        self
+    }
+    #[allow(dead_code)]
+    /// stops training if the validation error doesn't improve for `val` iterations;
+    /// 0 never stops
+    pub fn patience(mut self, val: usize) -> Self {
+        self.patience = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// builds the random subjects of the initial population, and of the refill
+    /// after a mass extinction, with `val`
+    pub fn init_method(mut self, val: InitMethod) -> Self {
+        self.init_method = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// builds the random subjects introduced every `new_sub_intro_period` iterations with `val`
+    pub fn immigrant_init_method(mut self, val: InitMethod) -> Self {
+        self.immigrant_init_method = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// stops training after the generation that exceeds `val` of wall-clock time,
+    /// counting the time before resuming
+    pub fn max_duration(mut self, val: Duration) -> Self {
+        self.max_duration = Some(val);
+        self
+    }
+    #[allow(dead_code)]
+    /// stops training after the generation that exceeds `val` calculations of errors;
+    /// 0 never stops
+    pub fn max_evaluations(mut self, val: usize) -> Self {
+        self.max_evaluations = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// stops training at the end of the generation `val` (or a clone of it) is cancelled in
+    pub fn cancel_token(mut self, val: CancelToken) -> Self {
+        self.cancel_token = Some(val);
+        self
+    }
+    #[allow(dead_code)]
     /// Max allowed error, after an solution
     /// with error less than or equal to this,
     /// training is stopped
@@ -175,11 +294,18 @@ impl<'a> TrainingArgs<'a> {
         self
     }
     /// err function to compare expected data and predicted data;
-    /// of the form (actual_val, predicted_val)->error.
+    /// of the form (actual_val, predicted_val)->error, averaged over the data.
     /// The error should be normalised
     #[allow(dead_code)]
-    pub fn err_fn(mut self, val: Box<dyn Fn(nb::Type, nb::Type) -> f32>) -> Self {
-        self.err_fn = val;
+    pub fn err_fn(mut self, val: Box<dyn Fn(nb::Type, nb::Type) -> f32 + Send + Sync>) -> Self {
+        self.loss = Box::new(val);
+        self
+    }
+    #[allow(dead_code)]
+    /// loss to compare expected data and predicted data;
+    /// see `loss` for the built-in ones
+    pub fn loss(mut self, val: Box<dyn Loss>) -> Self {
+        self.loss = val;
         self
     }
 
@@ -196,6 +322,66 @@ impl<'a> TrainingArgs<'a> {
         self.max_population = val;
         self
     }
+    #[allow(dead_code)]
+    /// number of threads the fitness evaluation is spread across;
+    /// 0 uses all the available cores
+    pub fn n_threads(mut self, val: usize) -> Self {
+        self.n_threads = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// strategy for picking the parents of mutants and the fathers of children;
+    /// see `selection` for the available ones
+    pub fn selection(mut self, val: Box<dyn Selection>) -> Self {
+        self.selection = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// strategy for picking the mothers of children; uniform by default
+    pub fn mother_selection(mut self, val: Box<dyn Selection>) -> Self {
+        self.mother_selection = val;
+        self
+    }
+    #[allow(dead_code)]
+    /// tunes the float constants of the top `n_subs` subjects every iteration,
+    /// with `n_iter` iterations of Nelder–Mead against the training data
+    pub fn const_opt(mut self, n_subs: usize, n_iter: usize) -> Self {
+        self.const_opt_n_subs = n_subs;
+        self.const_opt_n_iter = n_iter;
+        self
+    }
+    #[allow(dead_code)]
+    /// adapts the weights of the build table every iteration to the composition of
+    /// the top `n_best` subjects, at `rate` (see `BuilderTable::adapt_weights`).
+    /// The build table isn't checkpointed, so this can't be used along with `checkpoint`
+    pub fn adapt_weights(mut self, n_best: usize, rate: f32) -> Self {
+        assert!((0.0..=1.0).contains(&rate), "Adaptation rate must be within [0, 1]");
+        self.adapt_n_best = n_best;
+        self.adapt_rate = rate;
+        self
+    }
+    #[allow(dead_code)]
+    /// adds an observer, called along with the ones added before
+    pub fn observer(mut self, val: Box<dyn Observer>) -> Self {
+        self.observers.push(val);
+        self
+    }
+    #[allow(dead_code)]
+    /// writes a record of every generation to `val` (see `metrics`)
+    pub fn metrics(mut self, val: MetricsSink) -> Self {
+        self.metrics = Some(val);
+        self
+    }
+    #[allow(dead_code)]
+    /// saves a checkpoint of the population to `path` every `period` iterations,
+    /// from which an interrupted training can be resumed (see `Population::resume`).
+    /// Only for training a single population; `Archipelago::train` rejects it
+    pub fn checkpoint<P: AsRef<Path>>(mut self, path: P, period: usize) -> Self {
+        assert!(period > 0, "Checkpoint period must be at least 1");
+        self.checkpoint_path = Some(path.as_ref().to_path_buf());
+        self.checkpoint_period = period;
+        self
+    }
     /// checks the argument for correctness
     pub fn compile(self) -> Self {
         if self.train_x.is_none() {
@@ -204,10 +390,58 @@ impl<'a> TrainingArgs<'a> {
         if self.train_y.is_none() {
             panic!("Required: train_y");
         }
+        match (self.valid_x, self.valid_y) {
+            (Some(x), Some(y)) if x.len() != y.len() => {
+                panic!("valid_x and valid_y must be of the same length")
+            }
+            (Some(_), None) | (None, Some(_)) => panic!("Required: both valid_x and valid_y"),
+            _ => {}
+        }
+        if self.patience > 0 && self.valid_x.is_none() {
+            panic!("patience requires validation data");
+        }
+        if self.adapt_n_best > 0 && self.checkpoint_path.is_some() {
+            panic!("adapt_weights can't be used with checkpoint; the adapted weights aren't saved");
+        }
         self
     }
 }
 
+impl<'a> Default for TrainingArgs<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// runs `f` on every subject, split across `n_threads` threads (0 for all the available cores);
+/// `f` must only depend on the subject, so that the result is the same for any number of threads
+fn for_each_subject(
+    mut subjects: Vec<&mut et::Expr>,
+    n_threads: usize,
+    f: impl Fn(&mut et::Expr) + Sync,
+) {
+    let n_threads = if n_threads == 0 {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        n_threads
+    };
+    if n_threads <= 1 || subjects.len() <= 1 {
+        subjects.into_iter().for_each(f);
+        return;
+    }
+    let chunk_size = subjects.len().div_ceil(n_threads);
+    let f = &f;
+    thread::scope(|s| {
+        for chunk in subjects.chunks_mut(chunk_size) {
+            s.spawn(move || {
+                for p in chunk.iter_mut() {
+                    f(p);
+                }
+            });
+        }
+    });
+}
+
 macro_rules! log_execution_time {
     ( $msg: expr, $arg: stmt, $log: expr) => {
         let start_time = Instant::now();
@@ -216,6 +450,154 @@ macro_rules! log_execution_time {
             println!("    >>> {} :: Execution time = {:#?}", $msg, start_time.elapsed());
         }
     };
+    // also adds the time to the `GenerationMetrics`
+    ( $msg: expr, $arg: stmt, $log: expr, $metrics: expr) => {
+        let start_time = Instant::now();
+        $arg
+        $metrics.time($msg, start_time.elapsed());
+        if($log) {
+            println!("    >>> {} :: Execution time = {:#?}", $msg, start_time.elapsed());
+        }
+    };
+}
+
+/// Progress of training, carried from one generation to the next
+#[derive(Clone)]
+pub struct TrainingState {
+    /// number of generations run
+    pub iter: usize,
+    /// the minimum error, as of the last time it changed by more than `delta_th`
+    pub minim_error: Error,
+    /// number of generations since `minim_error` last changed
+    pub stagnant_cycles: usize,
+    /// the least error so far, as reported to `Observer::on_new_best`
+    pub best_error: Error,
+    /// subjects at the start of the population that survive a mass extinction
+    pub n_elite: usize,
+    /// subject with the least validation error so far, if there's validation data
+    pub best_valid: Option<et::Expr>,
+    /// number of generations since `best_valid` last changed
+    pub valid_stagnant_cycles: usize,
+    /// the least error of every generation
+    pub error_history: Vec<Error>,
+    /// generations that triggered a mass extinction
+    pub extinctions: Vec<usize>,
+    /// number of times the error of a subject was calculated
+    pub n_evaluations: usize,
+    /// wall-clock time spent training
+    pub elapsed: Duration,
+}
+
+impl TrainingState {
+    pub fn new() -> Self {
+        TrainingState {
+            iter: 0,
+            minim_error: Error::Uncalculated,
+            stagnant_cycles: 0,
+            best_error: Error::Uncalculated,
+            n_elite: 1,
+            best_valid: None,
+            valid_stagnant_cycles: 0,
+            error_history: vec![],
+            extinctions: vec![],
+            n_evaluations: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "iter": self.iter,
+            "minim_error": self.minim_error.to_json(),
+            "stagnant_cycles": self.stagnant_cycles,
+            "best_error": self.best_error.to_json(),
+            "n_elite": self.n_elite,
+            "best_valid": self.best_valid.as_ref().map(|p| json!({
+                "expr": p.to_json(),
+                "valid_error": p.valid_error.to_json(),
+            })),
+            "valid_stagnant_cycles": self.valid_stagnant_cycles,
+            "error_history": self.error_history.iter().map(|e| e.to_json()).collect::<Vec<_>>(),
+            "extinctions": self.extinctions,
+            "n_evaluations": self.n_evaluations,
+            "elapsed_secs": self.elapsed.as_secs_f64(),
+        })
+    }
+
+    /// Fields added after the first version of the format take their default value
+    /// when missing, so that older checkpoints still load
+    fn from_json(val: &Value, registry: &NodeRegistry) -> Result<TrainingState, SerdeErr> {
+        let field = |key: &str| {
+            val.get(key)
+                .ok_or_else(|| SerdeErr::new(format!("Missing field `state.{}`", key)))
+        };
+        let count = |key: &str| {
+            field(key)?
+                .as_u64()
+                .map(|n| n as usize)
+                .ok_or_else(|| SerdeErr::new(format!("Invalid `state.{}`", key)))
+        };
+        let best_valid = match field("best_valid")? {
+            Value::Null => None,
+            best => {
+                let mut p = et::Expr::from_json(&best["expr"], registry)?;
+                p.valid_error = et::Error::from_json(&best["valid_error"])?;
+                Some(p)
+            }
+        };
+        // the history of the run was added with the report; older checkpoints
+        // resume with an empty history
+        let error_history = match val.get("error_history") {
+            Some(hist) => hist
+                .as_array()
+                .ok_or_else(|| SerdeErr::new("Invalid `state.error_history`".to_string()))?
+                .iter()
+                .map(et::Error::from_json)
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        let extinctions = match val.get("extinctions") {
+            Some(ext) => ext
+                .as_array()
+                .and_then(|v| v.iter().map(|i| i.as_u64().map(|i| i as usize)).collect())
+                .ok_or_else(|| SerdeErr::new("Invalid `state.extinctions`".to_string()))?,
+            None => vec![],
+        };
+        let n_evaluations = match val.get("n_evaluations") {
+            Some(_) => count("n_evaluations")?,
+            None => 0,
+        };
+        let elapsed = match val.get("elapsed_secs") {
+            Some(secs) => secs
+                .as_f64()
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                .ok_or_else(|| SerdeErr::new("Invalid `state.elapsed_secs`".to_string()))?,
+            None => Duration::ZERO,
+        };
+        Ok(TrainingState {
+            iter: count("iter")?,
+            minim_error: et::Error::from_json(field("minim_error")?)?,
+            stagnant_cycles: count("stagnant_cycles")?,
+            // added with the observers; `load_checkpoint` takes it from the population
+            best_error: match val.get("best_error") {
+                Some(err) => et::Error::from_json(err)?,
+                None => Error::Uncalculated,
+            },
+            n_elite: count("n_elite")?,
+            best_valid,
+            valid_stagnant_cycles: count("valid_stagnant_cycles")?,
+            error_history,
+            extinctions,
+            n_evaluations,
+            elapsed,
+        })
+    }
+}
+
+impl Default for TrainingState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[allow(dead_code)]
@@ -227,6 +609,11 @@ pub struct Population {
     pub builder_table: nb::BuilderTable,
     pub arg_types: Vec<nb::TypeV>,
     pub ret_type: nb::TypeV,
+    /// progress of the training; kept across `step`s and stored in checkpoints
+    pub state: TrainingState,
+    /// index of the population among the islands of an `Archipelago`,
+    /// recorded in the metrics; set by `Archipelago::train`
+    pub island: Option<usize>,
 }
 
 impl Population {
@@ -238,6 +625,8 @@ impl Population {
             arg_types,
             ret_type,
             builder_table: nb::BuilderTable::new(), //dummy, empty build table
+            state: TrainingState::new(),
+            island: None,
         }
     }
 
@@ -251,11 +640,104 @@ impl Population {
         self.params = params;
     }
 
+    /// writes everything needed to resume the training to `path` in JSON form:
+    /// the subjects with their errors, the `params` with the state of the randomizer,
+    /// and the `state`. The build table isn't stored
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<(), SerdeErr> {
+        let subjects: Vec<Value> = self
+            .p
+            .iter()
+            .map(|p| {
+                json!({
+                    "expr": p.to_json(),
+                    "case_errors": p.case_errors.iter()
+                        .map(|e| ser::type_to_json(nb::Type::Float(*e)))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let val = json!({
+            "version": ser::FORMAT_VERSION,
+            "arg_types": ser::typev_list_to_json(&self.arg_types),
+            "rtype": ser::typev_to_json(self.ret_type),
+            "params": ser::params_to_json(&self.params),
+            "state": self.state.to_json(),
+            "population": subjects,
+        });
+        let text = serde_json::to_string(&val).map_err(|e| SerdeErr::new(e.to_string()))?;
+        // written aside first, so that a crash while writing leaves the last checkpoint intact
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text).map_err(|e| SerdeErr::new(e.to_string()))?;
+        std::fs::rename(&tmp, path).map_err(|e| SerdeErr::new(e.to_string()))
+    }
+
+    /// reads a population written by `save_checkpoint`; `registry` must contain every
+    /// node kind used in it. The build table must be set again before resuming,
+    /// and the training continued with `resume` and the same `TrainingArgs`
+    pub fn load_checkpoint<P: AsRef<Path>>(
+        path: P,
+        registry: &NodeRegistry,
+    ) -> Result<Population, SerdeErr> {
+        let text = std::fs::read_to_string(path).map_err(|e| SerdeErr::new(e.to_string()))?;
+        let val: Value = serde_json::from_str(&text).map_err(|e| SerdeErr::new(e.to_string()))?;
+        match val.get("version").and_then(|v| v.as_u64()) {
+            Some(ser::FORMAT_VERSION) => {}
+            version => {
+                return Err(SerdeErr::new(format!(
+                    "Unsupported format version {:?}; expected {}",
+                    version,
+                    ser::FORMAT_VERSION
+                )))
+            }
+        }
+        let field = |key: &str| {
+            val.get(key)
+                .ok_or_else(|| SerdeErr::new(format!("Missing field `{}`", key)))
+        };
+        let mut popln = Population::new(
+            ser::typev_list_from_json(field("arg_types")?)?,
+            ser::typev_from_json(field("rtype")?)?,
+        );
+        popln.params = ser::params_from_json(field("params")?)?;
+        popln.state = TrainingState::from_json(field("state")?, registry)?;
+        let subjects = field("population")?
+            .as_array()
+            .ok_or_else(|| SerdeErr::new("Expected list of subjects".to_string()))?;
+        for sub in subjects {
+            let mut p = et::Expr::from_json(&sub["expr"], registry)?;
+            if let Some(errs) = sub["case_errors"].as_array() {
+                for e in errs {
+                    if let nb::Type::Float(e) = ser::type_from_json(nb::TypeV::Float, e)? {
+                        p.case_errors.push(e);
+                    }
+                }
+            }
+            popln.p.push(p);
+        }
+        if field("state")?.get("best_error").is_none() {
+            popln.state.best_error = popln
+                .p
+                .iter()
+                .map(|p| p.error)
+                .filter(|err| !matches!(err, Error::Uncalculated))
+                .min_by(|a, b| a.cmp(b))
+                .unwrap_or(Error::Uncalculated);
+        }
+        Ok(popln)
+    }
+
     #[allow(dead_code)]
     pub fn init_population(&mut self, num_subs: usize) {
-        for _ in 0..num_subs {
-            self.p.push(et::Expr::random(
-                self.arg_types.clone(),
+        self.init_population_with(num_subs, InitMethod::Grow);
+    }
+
+    /// adds `num_subs` random subjects, built with `method`
+    pub fn init_population_with(&mut self, num_subs: usize, method: InitMethod) {
+        for i in 0..num_subs {
+            self.p.push(method.build(
+                i,
+                &self.arg_types,
                 self.ret_type,
                 &self.builder_table,
                 &mut self.params,
@@ -263,15 +745,20 @@ impl Population {
         }
     }
 
+    /// adds mutants of `num_tries` subjects picked with `selection`;
+    /// the population is expected to be sorted. Returns the number of mutants added
     #[allow(dead_code)]
-    pub fn generate_mutants(&mut self, num_tries: usize, mut_prob: f32, log_en: bool) {
-        let initial_population = self.p.len();
+    pub fn generate_mutants(
+        &mut self,
+        num_tries: usize,
+        mut_prob: f32,
+        selection: &dyn Selection,
+        log_en: bool,
+    ) -> usize {
         let mut n_success = 0usize;
-        let weights: Vec<_> = (0..initial_population).rev().collect();
-        let weighted_dist = WeightedIndex::new(&weights).unwrap();
-        for _ in 0..num_tries {
-            //randomly do a weighted selection
-            let p = &self.p[weighted_dist.sample(&mut self.params.randomizer)];
+        let parents = selection.select(&self.p, num_tries, &mut self.params.randomizer);
+        for idx in parents {
+            let p = &self.p[idx];
             let mut_prob = if let Error::Err { real: r, nan: _n } = p.error {
                 r * mut_prob
             } else {
@@ -285,7 +772,7 @@ impl Population {
                 &mut self.params,
             );
             if let Some(s) = maybe_mutant {
-                self.p.push(et::Expr::new(s));
+                self.p.push(et::Expr::new(s, self.arg_types.clone()));
                 n_success += 1;
             }
         }
@@ -294,16 +781,26 @@ impl Population {
                 "    generate_mutants ::  num_tries={num_tries}, new_children_added={n_success}"
             );
         }
+        n_success
     }
 
-    pub fn cross_breed(&mut self, num_tries: usize, breeding_prob: f32, log_en: bool) {
+    /// adds children of `num_tries` pairs of subjects, the fathers picked with `selection`
+    /// and the mothers with `mother_selection`; the population is expected to be sorted.
+    /// Returns the number of children added
+    pub fn cross_breed(
+        &mut self,
+        num_tries: usize,
+        breeding_prob: f32,
+        selection: &dyn Selection,
+        mother_selection: &dyn Selection,
+        log_en: bool,
+    ) -> usize {
         let mut n_success = 0usize;
-        let initial_population = self.p.len();
-        let weights: Vec<_> = (0..initial_population).rev().collect();
-        let weighted_dist = WeightedIndex::new(&weights).unwrap();
-        for _ in 0..num_tries {
-            let father_tree = &self.p[weighted_dist.sample(&mut self.params.randomizer)];
-            let mother_tree = &self.p[self.params.randomizer.gen_range(0..initial_population)];
+        let fathers = selection.select(&self.p, num_tries, &mut self.params.randomizer);
+        let mothers = mother_selection.select(&self.p, num_tries, &mut self.params.randomizer);
+        for (father, mother) in fathers.into_iter().zip(mothers) {
+            let father_tree = &self.p[father];
+            let mother_tree = &self.p[mother];
             let adj_father_breed_prob = if let Error::Err { real: r, nan: _n } = father_tree.error {
                 r * breeding_prob
             } else {
@@ -326,7 +823,7 @@ impl Population {
                     &mut self.params,
                 );
                 if let Some(child) = maybe_child {
-                    self.p.push(et::Expr::new(child));
+                    self.p.push(et::Expr::new(child, self.arg_types.clone()));
                     n_success += 1;
                 }
             }
@@ -334,28 +831,68 @@ impl Population {
         if log_en {
             println!("    cross_breed ::  num_tries={num_tries}, new_children_added={n_success}");
         }
+        n_success
     }
 
+    /// simplifies every subject (see `Expr::prune`);
+    /// the errors of the subjects that changed are calculated again
     pub fn prune_population(&mut self) {
         for p in self.p.iter_mut() {
             p.prune()
         }
     }
 
+    /// calculates the error of every subject whose error isn't calculated yet.
+    /// The subjects are split across `n_threads` threads (0 for all the available cores);
+    /// each subject's error is independent of the others, so the result
+    /// is the same for any number of threads
     #[allow(dead_code)]
     pub fn calc_err(
         &mut self,
         train_x: &[Vec<nb::Type>],
         train_y: &[nb::Type],
-        err_fn: &dyn Fn(nb::Type, nb::Type) -> f32,
-    ) {
-        for p in self.p.iter_mut() {
-            //if error is already calculated for a subject,
-            //its not required to recalculate the error again
-            if let Error::Uncalculated = p.error {
-                p.calc_err(train_x, train_y, err_fn);
-            }
-        }
+        loss: &dyn Loss,
+        n_threads: usize,
+    ) -> usize {
+        // the data is transposed once, so that each expression is evaluated
+        // over the whole data set per node
+        let train_x = &Column::from_rows(train_x);
+        //if error is already calculated for a subject,
+        //its not required to recalculate the error again
+        let pending: Vec<_> = self
+            .p
+            .iter_mut()
+            .filter(|p| matches!(p.error, Error::Uncalculated))
+            .collect();
+        let n_evals = pending.len();
+        for_each_subject(pending, n_threads, |p| {
+            p.calc_err_columns(train_x, train_y, loss)
+        });
+        n_evals
+    }
+
+    /// tunes the float constants of the top `n_subs` subjects with `n_iter` iterations each
+    /// (see `Expr::optimize_consts`); the population is expected to be sorted.
+    /// Spread across `n_threads` threads like `calc_err`.
+    /// Returns the number of times an error was calculated
+    pub fn optimize_consts(
+        &mut self,
+        n_subs: usize,
+        n_iter: usize,
+        train_x: &[Vec<nb::Type>],
+        train_y: &[nb::Type],
+        loss: &dyn Loss,
+        n_threads: usize,
+    ) -> usize {
+        let train_x = &Column::from_rows(train_x);
+        let n_subs = n_subs.min(self.p.len());
+        let top: Vec<_> = self.p[..n_subs].iter_mut().collect();
+        let n_evals = AtomicUsize::new(0);
+        for_each_subject(top, n_threads, |p| {
+            let n = p.optimize_consts(train_x, train_y, loss, n_iter);
+            n_evals.fetch_add(n, Ordering::Relaxed);
+        });
+        n_evals.into_inner()
     }
     ///Sorts the population accordig to fitness,
     /// if the fitness is uncalculated, panics
@@ -369,6 +906,59 @@ impl Population {
         }
     }
 
+    /// Sorts the population NSGA-II style on error and `complexity`;
+    /// by front of non-domination, then by decreasing crowding distance within a front
+    /// (see `pareto`). The subject with the least error comes first.
+    /// Returns the size of the Pareto front, which is at the start of the population;
+    /// if the fitness is uncalculated, panics
+    pub fn sort_pareto(&mut self, complexity: Complexity, log_en: bool) -> usize {
+        let subjects: Vec<_> = self.p.iter().collect();
+        let fronts = pareto::non_dominated_fronts(&subjects, complexity);
+        let mut order = Vec::with_capacity(self.p.len());
+        for front in fronts.iter() {
+            let dist = pareto::crowding_distance(&subjects, front, complexity);
+            let mut by_dist: Vec<_> = front.iter().zip(dist).collect();
+            // stable, so the ends of the front (both infinitely far) stay in the order of error
+            by_dist.sort_by(|a, b| b.1.total_cmp(&a.1));
+            order.extend(by_dist.into_iter().map(|(i, _)| *i));
+        }
+        let mut old: Vec<_> = std::mem::take(&mut self.p).into_iter().map(Some).collect();
+        self.p = order.into_iter().map(|i| old[i].take().unwrap()).collect();
+        if log_en {
+            if let Error::Err { real, nan } = self.p[0].error {
+                println!(
+                    "    sort_pareto: fronts = {}, pareto front size = {}, minimum_error in population := real_err: {real}, nan: {nan}",
+                    fronts.len(),
+                    fronts[0].len()
+                );
+            }
+        }
+        fronts[0].len()
+    }
+
+    /// returns the subjects on the Pareto front of error vs `complexity`, one per distinct
+    /// (error, complexity), from the simplest to the most accurate;
+    /// subjects with uncalculated errors are left out
+    pub fn pareto_front(&self, complexity: Complexity) -> Vec<et::Expr> {
+        let evaluated: Vec<_> = self
+            .p
+            .iter()
+            .filter(|p| !matches!(p.error, Error::Uncalculated))
+            .collect();
+        let fronts = pareto::non_dominated_fronts(&evaluated, complexity);
+        let mut front: Vec<&et::Expr> = vec![];
+        for i in fronts.first().into_iter().flatten().rev() {
+            let p = evaluated[*i];
+            let is_dup = front.last().is_some_and(|last| {
+                complexity.of(last) == complexity.of(p) && last.error.cmp(&p.error).is_eq()
+            });
+            if !is_dup {
+                front.push(p);
+            }
+        }
+        front.into_iter().cloned().collect()
+    }
+
     ///Only keep the top expressions with least errors
     /// Keep final_n number of children only
     #[allow(dead_code)]
@@ -383,115 +973,373 @@ impl Population {
     }
 
     ///This is the actual train method
-    /// returns the expression tree with least error
-    pub fn train(&mut self, args: &TrainingArgs) -> et::Expr {
+    /// returns the report of the run, with the expression tree with least error;
+    /// with validation data, the one with least validation error among the best
+    /// of each iteration, with both its training and validation errors
+    pub fn train(&mut self, args: &TrainingArgs) -> TrainingReport {
+        self.state = TrainingState::new();
+        self.resume(args)
+    }
+
+    /// continues training from `state`, as it was left by `train` or `load_checkpoint`;
+    /// `args` are expected to be the same as when it started.
+    /// Returns the same as `train`, covering the run from its start
+    pub fn resume(&mut self, args: &TrainingArgs) -> TrainingReport {
+        let stop_reason = self.evolve(args, None);
+        let best = match &self.state.best_valid {
+            Some(best) => best.clone(),
+            None => self.p[0].clone(),
+        };
+        TrainingReport {
+            best,
+            stop_reason,
+            n_iter: self.state.iter,
+            error_history: self.state.error_history.clone(),
+            extinctions: self.state.extinctions.clone(),
+            n_evaluations: self.state.n_evaluations,
+            elapsed: self.state.elapsed,
+        }
+    }
+
+    /// Trains NSGA-II style, minimising both the error and the `complexity` of the trees;
+    /// survivors are picked by front of non-domination and crowding distance
+    /// (see `sort_pareto`), and mass extinctions keep the whole Pareto front.
+    /// Returns the Pareto front (see `pareto_front`), to pick the trade-off from;
+    /// with validation data, along with their validation errors
+    pub fn train_pareto(&mut self, args: &TrainingArgs, complexity: Complexity) -> Vec<et::Expr> {
+        self.state = TrainingState::new();
+        self.resume_pareto(args, complexity)
+    }
+
+    /// continues training NSGA-II style from `state`, like `resume`;
+    /// returns the same as `train_pareto`
+    pub fn resume_pareto(&mut self, args: &TrainingArgs, complexity: Complexity) -> Vec<et::Expr> {
+        self.evolve(args, Some(complexity));
+        let mut front = self.pareto_front(complexity);
+        if let (Some(valid_x), Some(valid_y)) = (args.valid_x, args.valid_y) {
+            for p in front.iter_mut() {
+                p.calc_valid_err(valid_x, valid_y, args.loss.as_ref());
+            }
+        }
+        front
+    }
+
+    /// the generational loop of `train` and `train_pareto`;
+    /// the population is ranked on error alone, or NSGA-II style if `pareto` is given.
+    /// Returns why it stopped
+    fn evolve(&mut self, args: &TrainingArgs, pareto: Option<Complexity>) -> StopReason {
+        if self.state.iter == 0 {
+            //Start with few kids in the beginning
+            self.init_population_with(args.n_subs, args.init_method);
+        }
+        while self.state.iter < args.n_iter {
+            let stop = self.step(args, pareto);
+            if let Some(path) = &args.checkpoint_path {
+                if self.state.iter.is_multiple_of(args.checkpoint_period) {
+                    // a lost checkpoint isn't worth stopping the training for
+                    if let Err(e) = self.save_checkpoint(path) {
+                        eprintln!("Failed to save checkpoint to {:?}: {}", path, e.msg);
+                    }
+                }
+            }
+            if let Some(reason) = stop {
+                return reason;
+            }
+        }
+        StopReason::MaxIter
+    }
+
+    /// scores the best subject on the validation data, and keeps it in `state`
+    /// if it beats the best so far; the population is expected to be sorted.
+    /// Returns true if the validation error didn't improve for `patience` iterations
+    fn track_validation(&mut self, args: &TrainingArgs) -> bool {
+        let state = &mut self.state;
+        let (valid_x, valid_y) = match (args.valid_x, args.valid_y) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return false,
+        };
+        let mut top = self.p[0].clone();
+        top.calc_valid_err(valid_x, valid_y, args.loss.as_ref());
+        let improved = match &state.best_valid {
+            Some(best) => top.valid_error.cmp(&best.valid_error).is_lt(),
+            None => true,
+        };
+        if improved {
+            if args.log_en {
+                if let Error::Err { real, nan } = top.valid_error {
+                    println!("    track_validation: new best validation error := real_err: {real}, nan: {nan}");
+                }
+            }
+            state.best_valid = Some(top);
+            state.valid_stagnant_cycles = 0;
+        } else {
+            state.valid_stagnant_cycles += 1;
+        }
+        args.patience > 0 && state.valid_stagnant_cycles >= args.patience
+    }
+
+    /// sorts the population by error, or NSGA-II style if `pareto` is given;
+    /// returns the number of subjects at the start that survive a mass extinction
+    fn rank(
+        &mut self,
+        args: &TrainingArgs,
+        pareto: Option<Complexity>,
+        metrics: &mut GenerationMetrics,
+    ) -> usize {
+        let mut n_elite = 1;
+        match pareto {
+            None => {
+                log_execution_time!(
+                    "sort_population",
+                    self.sort_population(args.log_en),
+                    args.exec_time_log_en,
+                    metrics
+                ); //sort the population by error
+            }
+            Some(complexity) => {
+                log_execution_time!(
+                    "sort_pareto",
+                    n_elite = self.sort_pareto(complexity, args.log_en),
+                    args.exec_time_log_en,
+                    metrics
+                );
+            }
+        }
+        n_elite
+    }
+
+    /// runs generation `state.iter` of the training (see `evolve`) and advances `state`;
+    /// the population is expected to be initialized.
+    /// Returns why the training is to stop, if it is; a subject with error within
+    /// `max_allowed_err` is found, the validation error ran out of `patience`,
+    /// the time or evaluations ran out, it was cancelled, or an observer asked to
+    pub fn step(
+        &mut self,
+        args: &TrainingArgs,
+        pareto: Option<Complexity>,
+    ) -> Option<StopReason> {
+        let start_time = Instant::now();
+        let i = self.state.iter;
+        self.state.iter += 1;
+        let mut metrics = GenerationMetrics::new(i);
+        metrics.island = self.island;
+        let stop = self.run_generation(args, pareto, i, &mut metrics);
+        self.state.elapsed += start_time.elapsed();
+        if let Some(sink) = &args.metrics {
+            // losing a record isn't worth stopping the training for
+            if let Err(e) = sink.write(&metrics) {
+                eprintln!("Failed to write metrics: {}", e);
+            }
+        }
+        for observer in args.observers.iter() {
+            observer.on_generation_end(i, self);
+        }
+        stop.or_else(|| self.check_budget(args))
+            .or_else(|| {
+                args.observers
+                    .iter()
+                    .any(|o| o.should_stop())
+                    .then_some(StopReason::Observer)
+            })
+    }
+
+    /// returns why the training is to stop, if it ran out of time or evaluations,
+    /// or was cancelled
+    fn check_budget(&self, args: &TrainingArgs) -> Option<StopReason> {
+        if args.cancel_token.as_ref().is_some_and(|t| t.is_cancelled()) {
+            Some(StopReason::Cancelled)
+        } else if args.max_duration.is_some_and(|d| self.state.elapsed >= d) {
+            Some(StopReason::MaxDuration)
+        } else if args.max_evaluations > 0 && self.state.n_evaluations >= args.max_evaluations {
+            Some(StopReason::MaxEvaluations)
+        } else {
+            None
+        }
+    }
+
+    /// notifies the observers if the fittest subject beats the best one so far;
+    /// the population is expected to be ranked
+    fn track_best(&mut self, args: &TrainingArgs, iter: usize) {
+        let n_elite = self.state.n_elite.clamp(1, self.p.len());
+        let best = self.p[..n_elite]
+            .iter()
+            .min_by(|a, b| a.error.cmp(&b.error))
+            .unwrap();
+        let improved = match self.state.best_error {
+            Error::Uncalculated => true,
+            best_error => best.error.cmp(&best_error).is_lt(),
+        };
+        self.state.error_history.push(best.error);
+        if improved {
+            self.state.best_error = best.error;
+            for observer in args.observers.iter() {
+                observer.on_new_best(iter, best);
+            }
+        }
+    }
+
+    /// body of `step`, for generation `i`
+    fn run_generation(
+        &mut self,
+        args: &TrainingArgs,
+        pareto: Option<Complexity>,
+        i: usize,
+        metrics: &mut GenerationMetrics,
+    ) -> Option<StopReason> {
         let num_subs = args.n_subs;
         let n_iter = args.n_iter;
         let train_x = args.train_x.unwrap();
         let train_y = args.train_y.unwrap();
         let breed_prob = args.breed_probability;
         let mut_prob = args.mut_probability;
-        let mut minim_error = Error::Uncalculated;
-        let mut stagnant_cycles = 0usize;
-        self.init_population(num_subs); //Start with few kids in the beginning
-        for i in 0..n_iter {
+        if args.log_en {
+            println!("Log: n_iter {i}; Present population {}", self.p.len());
+        }
+        log_execution_time!(
+            "prune_population",
+            self.prune_population(),
+            args.exec_time_log_en,
+            metrics
+        );
+        log_execution_time!(
+            "calc_err",
+            self.state.n_evaluations +=
+                self.calc_err(train_x, train_y, args.loss.as_ref(), args.n_threads),
+            args.exec_time_log_en,
+            metrics
+        ); //calculate the errors expression tree
+        self.state.n_elite = self.rank(args, pareto, metrics);
+        if args.const_opt_n_subs > 0 {
+            log_execution_time!(
+                "optimize_consts",
+                self.state.n_evaluations += self.optimize_consts(
+                    args.const_opt_n_subs,
+                    args.const_opt_n_iter,
+                    train_x,
+                    train_y,
+                    args.loss.as_ref(),
+                    args.n_threads
+                ),
+                args.exec_time_log_en,
+                metrics
+            );
+            // the tuned subjects may have overtaken others
+            self.state.n_elite = self.rank(args, pareto, metrics);
+        }
+        self.track_best(args, i);
+        if args.adapt_n_best > 0 {
+            let n_best = args.adapt_n_best.min(self.p.len());
+            let best: Vec<_> = self.p[..n_best].iter().map(|p| &p.root).collect();
+            self.builder_table.adapt_weights(&best, args.adapt_rate);
+        }
+        if args.metrics.is_some() {
+            metrics.measure(&self.p);
+        }
+        if self.track_validation(args) {
             if args.log_en {
-                println!("Log: n_iter {i}; Present population {}", self.p.len());
+                println!("   ### Validation error stagnant; stopping ###");
             }
+            return Some(StopReason::Patience);
+        }
+        if i.is_multiple_of(args.purge_period) {
             log_execution_time!(
-                "prune_population",
-                self.prune_population(),
-                args.exec_time_log_en
+                "purge_unfit",
+                self.purge_unfit(num_subs, args.log_en),
+                args.exec_time_log_en,
+                metrics
             );
+        }
+        let l = (self.p.len() * args.top_children_ratio.0) / args.top_children_ratio.1;
+        if i.is_multiple_of(args.new_sub_intro_period) {
             log_execution_time!(
-                "calc_err",
-                self.calc_err(train_x, train_y, &args.err_fn),
-                args.exec_time_log_en
-            ); //calculate the errors expression tree
+                "init_population",
+                self.init_population_with(
+                    (num_subs * args.new_sub_increase_ratio.0) / args.new_sub_increase_ratio.1,
+                    args.immigrant_init_method,
+                ),
+                args.exec_time_log_en,
+                metrics
+            );
+        }
+        if i != n_iter - 1 {
+            metrics.crossovers_tried = l;
+            metrics.mutants_tried = l;
             log_execution_time!(
-                "sort_population",
-                self.sort_population(args.log_en),
-                args.exec_time_log_en
-            ); //sort the population by error
-            if i % args.purge_period == 0 {
-                log_execution_time!(
-                    "purge_unfit",
-                    self.purge_unfit(num_subs, args.log_en),
-                    args.exec_time_log_en
-                );
-            }
-            let l = (self.p.len() * args.top_children_ratio.0) / args.top_children_ratio.1;
-            if i % args.new_sub_intro_period == 0 {
-                log_execution_time!(
-                    "init_population",
-                    self.init_population(
-                        (num_subs * args.new_sub_increase_ratio.0) / args.new_sub_increase_ratio.1,
-                    ),
-                    args.exec_time_log_en
-                );
-            }
-            if i != n_iter - 1 {
-                log_execution_time!(
-                    "cross_breed",
-                    self.cross_breed(l, breed_prob, args.log_en),
-                    args.exec_time_log_en
-                );
-                log_execution_time!(
-                    "generate_mutants",
-                    self.generate_mutants(l, mut_prob, args.log_en),
-                    args.exec_time_log_en
-                );
-            }
+                "cross_breed",
+                metrics.crossovers_added = self.cross_breed(
+                    l,
+                    breed_prob,
+                    args.selection.as_ref(),
+                    args.mother_selection.as_ref(),
+                    args.log_en
+                ),
+                args.exec_time_log_en,
+                metrics
+            );
+            log_execution_time!(
+                "generate_mutants",
+                metrics.mutants_added =
+                    self.generate_mutants(l, mut_prob, args.selection.as_ref(), args.log_en),
+                args.exec_time_log_en,
+                metrics
+            );
+        }
 
-            if args.log_en {
-                println!();
+        if args.log_en {
+            println!();
+        }
+        if let Error::Err { real, nan } = self.p[0].error {
+            if real <= args.max_allowed_err && nan == 0.0 {
+                return Some(StopReason::TargetError);
             }
-            if let Error::Err { real, nan } = self.p[0].error {
-                if real <= args.max_allowed_err && nan == 0.0 {
-                    break;
-                }
-                if let Error::Err {
-                    real: min_real,
-                    nan: min_nan,
-                } = minim_error
+            if let Error::Err {
+                real: min_real,
+                nan: min_nan,
+            } = self.state.minim_error
+            {
+                let denom_real_err = if real == 0.0 { 1.0 } else { real };
+                let denom_nan_err = if nan == 0.0 { 1.0 } else { nan };
+                if (min_real - real).abs() / denom_real_err <= args.delta_th
+                    && (min_nan - nan).abs() / denom_nan_err <= args.delta_th
                 {
-                    let denom_real_err = if real == 0.0 { 1.0 } else { real };
-                    let denom_nan_err = if nan == 0.0 { 1.0 } else { nan };
-                    if (min_real - real).abs() / denom_real_err <= args.delta_th
-                        && (min_nan - nan).abs() / denom_nan_err <= args.delta_th
-                    {
-                        stagnant_cycles += 1;
-                    } else {
-                        minim_error = Error::Err { real, nan };
-                        stagnant_cycles = 0;
-                    }
+                    self.state.stagnant_cycles += 1;
                 } else {
-                    minim_error = self.p[0].error;
+                    self.state.minim_error = Error::Err { real, nan };
+                    self.state.stagnant_cycles = 0;
                 }
+            } else {
+                self.state.minim_error = self.p[0].error;
             }
-            //if minimum error remains unchanged for long time,
-            //trigger a mass extinction. Purge all but the top child
-            //and fill the population with new random children
-            if stagnant_cycles >= args.mass_extinction_th {
-                if args.log_en {
-                    println!("   ### Triggering mass extinction ###");
-                }
-                log_execution_time!(
-                    "mass_extinction",
-                    {
-                        self.purge_unfit(1, args.log_en);
-                        self.init_population(num_subs - 1);
-                    },
-                    args.exec_time_log_en
-                );
-
-                stagnant_cycles = 0;
+        }
+        //if minimum error remains unchanged for long time,
+        //trigger a mass extinction. Purge all but the top child
+        //and fill the population with new random children
+        //(all but the Pareto front, when training NSGA-II style)
+        if self.state.stagnant_cycles >= args.mass_extinction_th {
+            if args.log_en {
+                println!("   ### Triggering mass extinction ###");
             }
-            if self.p.len() > args.max_population {
-                // clip the maximum population
-                self.purge_unfit(args.max_population, args.log_en);
+            log_execution_time!(
+                "mass_extinction",
+                {
+                    let n_elite = self.state.n_elite.min(self.p.len());
+                    self.purge_unfit(n_elite, args.log_en);
+                    self.init_population_with(num_subs.saturating_sub(n_elite), args.init_method);
+                },
+                args.exec_time_log_en,
+                metrics
+            );
+
+            self.state.stagnant_cycles = 0;
+            self.state.extinctions.push(i);
+            for observer in args.observers.iter() {
+                observer.on_mass_extinction(i, self);
             }
         }
-        self.p[0].clone()
+        if self.p.len() > args.max_population {
+            // clip the maximum population
+            self.purge_unfit(args.max_population, args.log_en);
+        }
+        None
     }
 }
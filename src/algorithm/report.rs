@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+//! Summary of a training run, returned by `Population::train`
+use super::expr_tree::{self as et, Error};
+use std::time::Duration;
+
+/// Why the training stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// a subject with error within `max_allowed_err` was found
+    TargetError,
+    /// all of the `n_iter` generations ran
+    MaxIter,
+    /// the validation error didn't improve for `patience` generations
+    Patience,
+    /// an observer asked to stop (see `Observer::should_stop`)
+    Observer,
+    /// the training ran for `max_duration`
+    MaxDuration,
+    /// the errors were calculated `max_evaluations` times
+    MaxEvaluations,
+    /// the `CancelToken` was cancelled
+    Cancelled,
+}
+
+#[derive(Clone)]
+pub struct TrainingReport {
+    /// the subject `train` picked; see there
+    pub best: et::Expr,
+    pub stop_reason: StopReason,
+    /// number of generations run
+    pub n_iter: usize,
+    /// the least error of every generation
+    pub error_history: Vec<Error>,
+    /// generations that triggered a mass extinction
+    pub extinctions: Vec<usize>,
+    /// number of times the error of a subject was calculated on the training data,
+    /// including while tuning constants
+    pub n_evaluations: usize,
+    /// wall-clock time spent training, across resumes
+    pub elapsed: Duration,
+}
@@ -0,0 +1,289 @@
+#![allow(dead_code)]
+//! Strategies for picking the parents of mutants and children.
+//!
+//! Selection happens on a population sorted from the fittest to the least fit
+//! (see `Population::sort_population`); subjects introduced after sorting sit at
+//! the end with their error uncalculated, and are treated as the least fit
+use super::expr_tree::{self as et, Error};
+use rand::distributions::weighted::WeightedIndex;
+use rand::distributions::Distribution;
+use rand::{Rng, RngCore};
+use std::collections::HashMap;
+
+/// Strategy for picking subjects from the population for breeding
+pub trait Selection: Send + Sync {
+    /// returns the indices of `n` subjects picked from `population`;
+    /// the same subject may be picked more than once
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize>;
+}
+
+/// picks `n` indices from `0..weights.len()` with probability proportional to the weights;
+/// uniformly if all of the weights are zero
+fn weighted(weights: &[f32], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+    match WeightedIndex::new(weights) {
+        Ok(dist) => (0..n).map(|_| dist.sample(rng)).collect(),
+        Err(_) => (0..n).map(|_| rng.gen_range(0..weights.len())).collect(),
+    }
+}
+
+/// Picks every subject with the same probability; no selection pressure.
+/// The default for the mothers of children (see `TrainingArgs::mother_selection`)
+pub struct Uniform;
+
+impl Selection for Uniform {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        (0..n).map(|_| rng.gen_range(0..population.len())).collect()
+    }
+}
+
+/// Picks the fittest out of `size` subjects chosen uniformly;
+/// larger tournaments mean higher selection pressure
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Tournament {
+    pub fn new(size: usize) -> Tournament {
+        assert!(size > 0, "Tournament size must be at least 1");
+        Tournament { size }
+    }
+}
+
+impl Selection for Tournament {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        (0..n)
+            .map(|_| {
+                (0..self.size)
+                    .map(|_| rng.gen_range(0..population.len()))
+                    .min()
+                    .unwrap()
+            })
+            .collect()
+    }
+}
+
+/// Probability decreases linearly with the rank.
+/// `pressure` is in [1, 2]; the fittest subject is `pressure` times as likely to be picked
+/// as the average one, and the least fit `2 - pressure` times.
+/// 1 is uniform selection and 2 (default) never picks the least fit
+pub struct LinearRank {
+    pub pressure: f32,
+}
+
+impl LinearRank {
+    pub fn new(pressure: f32) -> LinearRank {
+        assert!(
+            (1.0..=2.0).contains(&pressure),
+            "Selection pressure of linear rank must be in [1, 2]"
+        );
+        LinearRank { pressure }
+    }
+}
+
+impl Default for LinearRank {
+    fn default() -> Self {
+        Self::new(2.0)
+    }
+}
+
+impl Selection for LinearRank {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let len = population.len();
+        let last = len.saturating_sub(1).max(1) as f32;
+        let weights: Vec<_> = (0..len)
+            .map(|i| {
+                let rank_from_last = (len - 1 - i) as f32;
+                (2.0 - self.pressure) + 2.0 * (self.pressure - 1.0) * rank_from_last / last
+            })
+            .collect();
+        weighted(&weights, n, rng)
+    }
+}
+
+/// Probability decreases geometrically with the rank, by `base` (in (0, 1]) per rank;
+/// smaller `base` means higher selection pressure
+pub struct ExponentialRank {
+    pub base: f32,
+}
+
+impl ExponentialRank {
+    pub fn new(base: f32) -> ExponentialRank {
+        assert!(
+            base > 0.0 && base <= 1.0,
+            "Base of exponential rank must be in (0, 1]"
+        );
+        ExponentialRank { base }
+    }
+}
+
+impl Selection for ExponentialRank {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let weights: Vec<_> = (0..population.len())
+            .scan(1.0f32, |w, _| {
+                let cur = *w;
+                *w *= self.base;
+                Some(cur)
+            })
+            .collect();
+        weighted(&weights, n, rng)
+    }
+}
+
+/// Roulette wheel; probability is proportional to the fitness
+/// `(1 - nan) / (1 + real)` of the subject's error.
+/// Subjects with uncalculated errors are never picked
+pub struct FitnessProportional;
+
+impl FitnessProportional {
+    pub fn fitness(error: &Error) -> f32 {
+        match error {
+            Error::Err { real, nan } if real.is_finite() => (1.0 - nan) / (1.0 + real),
+            _ => 0.0,
+        }
+    }
+}
+
+impl Selection for FitnessProportional {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let weights: Vec<_> = population
+            .iter()
+            .map(|p| Self::fitness(&p.error).max(0.0))
+            .collect();
+        weighted(&weights, n, rng)
+    }
+}
+
+/// Picks uniformly among the fittest `fraction` (in (0, 1]) of the population
+pub struct Truncation {
+    pub fraction: f32,
+}
+
+impl Truncation {
+    pub fn new(fraction: f32) -> Truncation {
+        assert!(
+            fraction > 0.0 && fraction <= 1.0,
+            "Fraction of truncation must be in (0, 1]"
+        );
+        Truncation { fraction }
+    }
+}
+
+impl Selection for Truncation {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let top = ((population.len() as f32 * self.fraction).ceil() as usize)
+            .clamp(1, population.len());
+        (0..n).map(|_| rng.gen_range(0..top)).collect()
+    }
+}
+
+/// Epsilon-lexicase selection; uses the errors on the individual training cases
+/// (`Expr::case_errors`) instead of the averaged error.
+/// For every pick the cases are shuffled, and the candidates are filtered case by case,
+/// keeping those within epsilon of the best error on the case, until a single candidate
+/// or no case is left; one of the remaining candidates is picked.
+/// Epsilon of a case is the median absolute deviation of the population's errors on it.
+/// Subjects with uncalculated errors are never picked
+pub struct EpsilonLexicase;
+
+/// median of non-empty `v`; reorders `v`
+fn median(v: &mut [f32]) -> f32 {
+    let mid = v.len() / 2;
+    *v.select_nth_unstable_by(mid, f32::total_cmp).1
+}
+
+impl EpsilonLexicase {
+    /// epsilon for each of the `n_cases` cases; the median absolute deviation
+    /// of the finite errors of the subjects on the case, 0 if there are none
+    pub fn epsilons(population: &[&et::Expr], n_cases: usize) -> Vec<f32> {
+        let mut errs = Vec::with_capacity(population.len());
+        (0..n_cases)
+            .map(|c| {
+                errs.clear();
+                errs.extend(
+                    population
+                        .iter()
+                        .map(|p| p.case_errors[c])
+                        .filter(|e| e.is_finite()),
+                );
+                if errs.is_empty() {
+                    return 0.0;
+                }
+                let med = median(&mut errs);
+                errs.iter_mut().for_each(|e| *e = (*e - med).abs());
+                median(&mut errs)
+            })
+            .collect()
+    }
+}
+
+impl Selection for EpsilonLexicase {
+    fn select(&self, population: &[et::Expr], n: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let n_cases = population
+            .iter()
+            .map(|p| p.case_errors.len())
+            .max()
+            .unwrap_or(0);
+        let eval_idx: Vec<usize> = (0..population.len())
+            .filter(|i| n_cases > 0 && population[*i].case_errors.len() == n_cases)
+            .collect();
+        if eval_idx.is_empty() {
+            return (0..n).map(|_| rng.gen_range(0..population.len())).collect();
+        }
+        let evaluated: Vec<_> = eval_idx.iter().map(|i| &population[*i]).collect();
+        let epsilons = Self::epsilons(&evaluated, n_cases);
+
+        // subjects with the same errors on every case always survive the filtering together,
+        // so the filtering is done on the distinct errors, and one of the subjects
+        // sharing the surviving errors is picked at the end
+        let mut groups: HashMap<Vec<u32>, Vec<usize>> = HashMap::new();
+        for (p, i) in evaluated.iter().zip(eval_idx.iter()) {
+            let key = p.case_errors.iter().map(|e| e.to_bits()).collect();
+            groups.entry(key).or_default().push(*i);
+        }
+        // in population order, so that the picks only depend on the state of `rng`
+        let mut groups: Vec<Vec<usize>> = groups.into_values().collect();
+        groups.sort_unstable_by_key(|members| members[0]);
+        let n_groups = groups.len();
+        // errors laid out case by case, so that filtering on a case reads a single row
+        let mut errs = vec![0.0; n_cases * n_groups];
+        for (g, members) in groups.iter().enumerate() {
+            for (c, e) in population[members[0]].case_errors.iter().enumerate() {
+                errs[c * n_groups + g] = *e;
+            }
+        }
+
+        let mut cases: Vec<usize> = (0..n_cases).collect();
+        let mut candidates = Vec::with_capacity(n_groups);
+        (0..n)
+            .map(|_| {
+                candidates.clear();
+                candidates.extend(0..n_groups);
+                // cases are shuffled lazily, as filtering usually stops after a few
+                for k in 0..n_cases {
+                    if candidates.len() <= 1 {
+                        break;
+                    }
+                    cases.swap(k, rng.gen_range(k..n_cases));
+                    let c = cases[k];
+                    let row = &errs[c * n_groups..(c + 1) * n_groups];
+                    let best = candidates
+                        .iter()
+                        .map(|g| row[*g])
+                        .fold(f32::INFINITY, f32::min);
+                    let th = best + epsilons[c];
+                    candidates.retain(|g| row[*g] <= th);
+                }
+                // uniform over the surviving subjects
+                let n_survivors: usize = candidates.iter().map(|g| groups[*g].len()).sum();
+                let mut k = rng.gen_range(0..n_survivors);
+                for g in candidates.iter() {
+                    if k < groups[*g].len() {
+                        return groups[*g][k];
+                    }
+                    k -= groups[*g].len();
+                }
+                unreachable!()
+            })
+            .collect()
+    }
+}
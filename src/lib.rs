@@ -0,0 +1,2 @@
+pub mod algorithm;
+pub mod node;
@@ -1,9 +1,6 @@
-mod algorithm;
-mod node;
-
-use algorithm::population as ap;
-use node::base as nb;
-use node::btables::FloatFnTable;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
 use rand::Rng;
 use std::f32::consts::PI;
 
@@ -64,7 +61,8 @@ fn main() {
             .max_allowed_err(1e-6)
             .max_population(2000)
             .compile(),
-    );
+    )
+    .best;
 
     // print the top 10 expressions
     for (i, p) in popln.p.iter().enumerate() {
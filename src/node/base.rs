@@ -1,7 +1,16 @@
 #![allow(dead_code)]
 use rand;
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use super::bytecode::{Instr, Program};
+use super::codegen::{self, Lang};
+use super::column::Column;
+use super::diff;
+use super::serialize as ser;
+use serde_json::json;
 
 /// Type value
 #[derive(std::fmt::Debug, Clone, Copy, std::cmp::PartialEq, std::cmp::Eq, std::hash::Hash)]
@@ -39,37 +48,47 @@ impl Type {
     }
 
     #[allow(dead_code)]
-    pub fn rand(&self) -> Type {
-        match self {
-            Type::Int(_) => Type::Int(rand::random()),
-            Type::Float(_) => Type::Float(rand::random()),
-            Type::UInt(_) => Type::UInt(rand::random()),
-            Type::Bool(_) => Type::Bool(rand::random()),
-        }
+    pub fn rand<R: Rng>(&self, rng: &mut R) -> Type {
+        Type::random(self.get_type(), rng)
     }
 
-    pub fn int_range(a: i32, b: i32) -> Type {
-        let mut rng = thread_rng();
+    pub fn int_range<R: Rng>(a: i32, b: i32, rng: &mut R) -> Type {
         Type::Int(rng.gen_range(a..=b))
     }
-    pub fn float_range(a: f32, b: f32) -> Type {
-        let mut rng = thread_rng();
+    pub fn float_range<R: Rng>(a: f32, b: f32, rng: &mut R) -> Type {
         Type::Float(rng.gen_range(a..=b))
     }
 
-    pub fn uint_range(a: u32, b: u32) -> Type {
-        let mut rng = thread_rng();
+    pub fn uint_range<R: Rng>(a: u32, b: u32, rng: &mut R) -> Type {
         Type::UInt(rng.gen_range(a..=b))
     }
-    pub fn bool_rand() -> Type {
-        Type::Bool(rand::random())
+    pub fn bool_rand<R: Rng>(rng: &mut R) -> Type {
+        Type::Bool(rng.gen())
+    }
+    /// returns the type of the value
+    pub fn get_type(&self) -> TypeV {
+        match self {
+            Type::Int(_) => TypeV::Int,
+            Type::Float(_) => TypeV::Float,
+            Type::UInt(_) => TypeV::UInt,
+            Type::Bool(_) => TypeV::Bool,
+        }
     }
-    fn random(rtype: TypeV) -> Self {
+    /// returns the value as a float; `true` is 1 and `false` is 0
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Type::Int(i) => *i as f32,
+            Type::Float(f) => *f,
+            Type::UInt(u) => *u as f32,
+            Type::Bool(b) => *b as u8 as f32,
+        }
+    }
+    fn random<R: Rng>(rtype: TypeV, rng: &mut R) -> Self {
         match rtype {
-            TypeV::Int => Type::Int(rand::random()),
-            TypeV::Float => Type::Float(rand::random()),
-            TypeV::UInt => Type::UInt(rand::random()),
-            TypeV::Bool => Type::Bool(rand::random()),
+            TypeV::Int => Type::Int(rng.gen()),
+            TypeV::Float => Type::Float(rng.gen()),
+            TypeV::UInt => Type::UInt(rng.gen()),
+            TypeV::Bool => Type::Bool(rng.gen()),
         }
     }
 }
@@ -94,8 +113,9 @@ pub type NodeRef = Box<dyn Node>;
 
 /// This is the top level Node trait
 /// `Node` is not to be initiliazed directly, but
-/// rather `NodeRef` that is dyn object of trait is to be used
-pub trait Node {
+/// rather `NodeRef` that is dyn object of trait is to be used.
+/// Nodes are `Send + Sync`, so that trees can be evaluated across threads
+pub trait Node: Send + Sync {
     /// each node is evaluated and value is passed up the tree
     fn eval(&self, args: &[Type]) -> Type;
     /// returns string representation of the node in the form of tree
@@ -174,14 +194,45 @@ pub trait Node {
     ) -> Option<NodeRef>;
 
     /// These is to remove wasteful nodes from the tree
-    /// By wasteful I mean stuff like Abs(Abs(...)) === Abs(...)
-    /// or Sin(Asin(x)) == x and so on
+    /// By wasteful I mean stuff like Abs(Abs(...)) === Abs(...),
+    /// (x * 1) == x, constant subtrees and so on;
+    /// returns the simplified copy of the tree, see `simplify` for the rules
     fn prune(&self) -> NodeRef;
 
-    fn get_name(&self) -> &'static str;
+    fn get_name(&self) -> &str;
 
     /// recursively calculates the depth of the deepest branch of a node
     fn get_max_depth(&self) -> usize;
+
+    /// recursively counts the nodes in the tree, including this one
+    fn get_node_count(&self) -> usize;
+
+    /// appends the values of the float constants in the tree to `consts`, depth first
+    fn get_float_consts(&self, consts: &mut Vec<f32>);
+
+    /// replaces the float constants in the tree with values taken from `consts`,
+    /// in the order of `get_float_consts`
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>);
+
+    /// returns the node and its children in JSON form;
+    /// see `serialize::node_from_json` for the reverse
+    fn to_json(&self) -> serde_json::Value;
+
+    /// appends the postfix instructions for evaluating the node to `program`;
+    /// see `bytecode::Program`
+    fn compile(&self, program: &mut Program);
+
+    /// evaluates the node over `n_rows` data points at once;
+    /// `args` holds a column of `n_rows` values for each argument
+    fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column;
+
+    /// returns the node as an expression in the source code of `lang`;
+    /// arguments are named as per `codegen::var_name`
+    fn get_code_str(&self, lang: Lang) -> String;
+
+    /// returns the tree computing the derivative of the node with respect to `x[var_idx]`;
+    /// only float nodes can be differentiated. The tree is not simplified
+    fn derivative(&self, var_idx: usize) -> NodeRef;
 }
 
 /// A NUll node. This node does nothing
@@ -286,6 +337,34 @@ impl Node for Null {
     fn get_max_depth(&self) -> usize {
         0
     }
+
+    fn get_node_count(&self) -> usize {
+        0
+    }
+
+    fn get_float_consts(&self, _consts: &mut Vec<f32>) {}
+
+    fn set_float_consts(&mut self, _consts: &mut dyn Iterator<Item = f32>) {}
+
+    fn to_json(&self) -> serde_json::Value {
+        unreachable!()
+    }
+
+    fn compile(&self, _program: &mut Program) {
+        panic!("Cannot compile a Null block!!");
+    }
+
+    fn eval_batch(&self, _args: &[Column], _n_rows: usize) -> Column {
+        panic!("Cannot evaluate a Null block!!");
+    }
+
+    fn get_code_str(&self, _lang: Lang) -> String {
+        unreachable!()
+    }
+
+    fn derivative(&self, _var_idx: usize) -> NodeRef {
+        unreachable!()
+    }
 }
 
 /// Val node for storing constant values
@@ -297,12 +376,7 @@ pub struct Val {
 
 impl Val {
     pub fn make(val: Type) -> NodeRef {
-        let rtype = match val {
-            Type::Bool(_) => TypeV::Bool,
-            Type::UInt(_) => TypeV::UInt,
-            Type::Int(_) => TypeV::Int,
-            Type::Float(_) => TypeV::Float,
-        };
+        let rtype = val.get_type();
         Box::new(Val {
             v: val,
             rtype,
@@ -359,9 +433,9 @@ impl Node for Val {
         _arg_types: &[TypeV],
         node_rtype: TypeV,
         _depth: usize,
-        _params: &'a mut BuilderParams,
+        params: &'a mut BuilderParams,
     ) -> NodeRef {
-        let val = Type::random(node_rtype);
+        let val = Type::random(node_rtype, &mut params.randomizer);
         Val::make(val)
     }
     fn type_check(&self) -> Result<(), TypeErr> {
@@ -385,7 +459,17 @@ impl Node for Val {
                         .randomizer
                         .gen_range(params.float_range.0..=params.float_range.1),
                 )),
-                _ => unimplemented!(),
+                Type::Int(_) => Self::make(Type::int_range(
+                    params.int_range.0,
+                    params.int_range.1,
+                    &mut params.randomizer,
+                )),
+                Type::UInt(_) => Self::make(Type::uint_range(
+                    params.uint_range.0,
+                    params.uint_range.1,
+                    &mut params.randomizer,
+                )),
+                Type::Bool(_) => Self::make(Type::bool_rand(&mut params.randomizer)),
             })
         } else {
             None
@@ -412,7 +496,9 @@ impl Node for Val {
         depth: usize,
         params: &'_ mut BuilderParams,
     ) -> Option<NodeRef> {
-        if params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth) {
+        if new_node.get_rtype() == self.rtype
+            && params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth)
+        {
             Some(new_node)
         } else {
             None
@@ -430,6 +516,46 @@ impl Node for Val {
     fn get_max_depth(&self) -> usize {
         1
     }
+
+    fn get_node_count(&self) -> usize {
+        1
+    }
+
+    fn get_float_consts(&self, consts: &mut Vec<f32>) {
+        if let Type::Float(f) = self.v {
+            consts.push(f);
+        }
+    }
+
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>) {
+        if let Type::Float(_) = self.v {
+            self.v = Type::Float(consts.next().unwrap());
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "kind": "Val",
+            "rtype": ser::typev_to_json(self.rtype),
+            "value": ser::type_to_json(self.v),
+        })
+    }
+
+    fn compile(&self, program: &mut Program) {
+        program.push(Instr::Const(self.v));
+    }
+
+    fn eval_batch(&self, _args: &[Column], n_rows: usize) -> Column {
+        Column::splat(self.v, n_rows)
+    }
+
+    fn get_code_str(&self, lang: Lang) -> String {
+        codegen::literal(self.v, lang)
+    }
+
+    fn derivative(&self, _var_idx: usize) -> NodeRef {
+        diff::num(0.0)
+    }
 }
 
 pub struct Var {
@@ -485,8 +611,12 @@ impl Node for Var {
         let valid_indices: Vec<_> = (0..arg_types.len())
             .filter(|x| arg_types[*x] == node_rtype) //Only arguments with same type as rtype are to be chosen
             .collect();
-        let vindex = *valid_indices.choose(&mut params.randomizer).unwrap();
-        Var::make(vindex, node_rtype)
+        match valid_indices.choose(&mut params.randomizer) {
+            Some(vindex) => Var::make(*vindex, node_rtype),
+            // no argument of the type, e.g. for a custom primitive taking an integer
+            // in a float-only problem; a constant stands in for it
+            None => Val::make(Type::random(node_rtype, &mut params.randomizer)),
+        }
     }
     fn type_check(&self) -> Result<(), TypeErr> {
         Ok(())
@@ -536,7 +666,9 @@ impl Node for Var {
         depth: usize,
         params: &'_ mut BuilderParams,
     ) -> Option<NodeRef> {
-        if params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth) {
+        if new_node.get_rtype() == self.rtype
+            && params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth)
+        {
             Some(new_node)
         } else {
             None
@@ -553,82 +685,260 @@ impl Node for Var {
     fn get_max_depth(&self) -> usize {
         1
     }
+
+    fn get_node_count(&self) -> usize {
+        1
+    }
+
+    fn get_float_consts(&self, _consts: &mut Vec<f32>) {}
+
+    fn set_float_consts(&mut self, _consts: &mut dyn Iterator<Item = f32>) {}
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "kind": "Var",
+            "rtype": ser::typev_to_json(self.rtype),
+            "idx": self.idx,
+        })
+    }
+
+    fn compile(&self, program: &mut Program) {
+        program.push(Instr::Var(self.idx));
+    }
+
+    fn eval_batch(&self, args: &[Column], _n_rows: usize) -> Column {
+        args[self.idx].clone()
+    }
+
+    fn get_code_str(&self, _lang: Lang) -> String {
+        codegen::var_name(self.idx)
+    }
+
+    fn derivative(&self, var_idx: usize) -> NodeRef {
+        diff::num(if self.idx == var_idx { 1.0 } else { 0.0 })
+    }
 }
 
 pub struct BuilderTable {
-    rtype_bool: Vec<NodeRef>,
-    rtype_int: Vec<NodeRef>,
-    rtype_uint: Vec<NodeRef>,
-    rtype_float: Vec<NodeRef>,
+    rtype_bool: Primitives,
+    rtype_int: Primitives,
+    rtype_uint: Primitives,
+    rtype_float: Primitives,
     val_node: NodeRef,
     var_node: NodeRef,
+    /// weights of picking a `Val` or a `Var` for a terminal
+    terminal_weights: (f32, f32),
+}
+
+/// Nodes of the same return type, with the weights they're picked by
+#[derive(Default)]
+struct Primitives {
+    nodes: Vec<NodeRef>,
+    weights: Vec<f32>,
+}
+
+impl Primitives {
+    fn choose(&self, randomizer: &mut ChaCha8Rng) -> Option<&NodeRef> {
+        if self.weights.iter().sum::<f32>() <= 0.0 {
+            None
+        } else if self.weights.iter().all(|w| *w == self.weights[0]) {
+            // picked the way it was before weights, so that seeded runs stay the same
+            self.nodes.choose(randomizer)
+        } else {
+            let dist = WeightedIndex::new(&self.weights).unwrap();
+            Some(&self.nodes[dist.sample(randomizer)])
+        }
+    }
+}
+
+fn check_weight(weight: f32) {
+    assert!(
+        weight.is_finite() && weight >= 0.0,
+        "Weight must be finite and non-negative; Got {}",
+        weight
+    );
+}
+
+/// counts the nodes of every kind and return type in the tree
+fn count_nodes<'a>(node: &'a NodeRef, counts: &mut HashMap<(&'a str, TypeV), usize>) {
+    *counts.entry((node.get_name(), node.get_rtype())).or_insert(0) += 1;
+    for i in 0..node.get_arg_types().len() {
+        count_nodes(node.get_child(i), counts);
+    }
+}
+
+/// moves `weights` towards the share of each of the `counts` by `rate`;
+/// the shares are smoothed, so that none of the positive weights goes down to 0.
+/// Zero weights stay at 0, as they are of nodes that are never to be picked
+fn adapt(weights: &mut [f32], counts: &[usize], rate: f32) {
+    let live: Vec<_> = (0..weights.len()).filter(|i| weights[*i] > 0.0).collect();
+    if live.is_empty() {
+        return;
+    }
+    let n = live.len() as f32;
+    let total = live.iter().map(|i| counts[*i]).sum::<usize>() as f32 + n;
+    let mean = live.iter().map(|i| weights[*i]).sum::<f32>() / n;
+    for i in live {
+        let target = mean * n * (counts[i] as f32 + 1.0) / total;
+        weights[i] += rate * (target - weights[i]);
+    }
 }
 
 pub struct BuilderParams {
     pub max_depth: usize,
-    pub randomizer: rand::prelude::ThreadRng,
+    /// depth up to which branches don't end in terminals, where there are
+    /// operations of the required type
+    pub min_depth: usize,
+    /// every random decision in building, mutating, breeding and selecting
+    /// the trees is drawn from this; seed it with `seed` for reproducible runs
+    pub randomizer: ChaCha8Rng,
     pub termination_probability: f32,
     pub float_range: (f32, f32),
     pub int_range: (i32, i32),
     pub uint_range: (u32, u32),
+    /// most operands of randomly built variadic nodes, like `Sum`
+    pub max_arity: usize,
 }
 
 impl BuilderTable {
     pub fn new() -> BuilderTable {
         BuilderTable {
-            rtype_bool: vec![],
-            rtype_int: vec![],
-            rtype_uint: vec![],
-            rtype_float: vec![],
+            rtype_bool: Primitives::default(),
+            rtype_int: Primitives::default(),
+            rtype_uint: Primitives::default(),
+            rtype_float: Primitives::default(),
             val_node: Val::zero(TypeV::Bool),
             var_node: Var::make(0, TypeV::Bool),
+            terminal_weights: (1.0, 1.0),
         }
     }
 
-    pub fn push(&mut self, ty: TypeV, node: NodeRef) {
+    fn primitives(&self, ty: TypeV) -> &Primitives {
+        match ty {
+            TypeV::Bool => &self.rtype_bool,
+            TypeV::Int => &self.rtype_int,
+            TypeV::UInt => &self.rtype_uint,
+            TypeV::Float => &self.rtype_float,
+        }
+    }
+
+    fn primitives_mut(&mut self, ty: TypeV) -> &mut Primitives {
         match ty {
-            TypeV::Bool => self.rtype_bool.push(node),
-            TypeV::Int => self.rtype_int.push(node),
-            TypeV::UInt => self.rtype_uint.push(node),
-            TypeV::Float => self.rtype_float.push(node),
+            TypeV::Bool => &mut self.rtype_bool,
+            TypeV::Int => &mut self.rtype_int,
+            TypeV::UInt => &mut self.rtype_uint,
+            TypeV::Float => &mut self.rtype_float,
         }
     }
 
+    pub fn push(&mut self, ty: TypeV, node: NodeRef) {
+        self.push_weighted(ty, node, 1.0);
+    }
+
+    /// adds `node`, picked with probability proportional to `weight` among the nodes
+    /// of type `ty`, when building and mutating trees; `push` gives a weight of 1
+    pub fn push_weighted(&mut self, ty: TypeV, node: NodeRef, weight: f32) {
+        check_weight(weight);
+        let primitives = self.primitives_mut(ty);
+        primitives.nodes.push(node);
+        primitives.weights.push(weight);
+    }
+
+    /// weights of the nodes of type `ty`, in the order they were pushed
+    pub fn get_weights(&self, ty: TypeV) -> &[f32] {
+        &self.primitives(ty).weights
+    }
+
+    /// sets the weights of picking a constant (`Val`) and an argument (`Var`)
+    /// for a terminal; 1 each by default
+    pub fn set_terminal_weights(&mut self, val: f32, var: f32) {
+        check_weight(val);
+        check_weight(var);
+        assert!(val + var > 0.0, "Either of the terminal weights must be positive");
+        self.terminal_weights = (val, var);
+    }
+
+    pub fn get_terminal_weights(&self) -> (f32, f32) {
+        self.terminal_weights
+    }
+
+    /// moves the weights of the nodes, and of the terminals, towards how often they
+    /// appear in the `trees`, by `rate` (between 0 and 1); positive weights stay positive,
+    /// and zero weights stay at 0.
+    /// Nodes are told apart by their name and return type
+    pub fn adapt_weights(&mut self, trees: &[&NodeRef], rate: f32) {
+        let mut counts = HashMap::new();
+        for tree in trees {
+            count_nodes(tree, &mut counts);
+        }
+        let types = [TypeV::Bool, TypeV::Int, TypeV::UInt, TypeV::Float];
+        fn count<'a>(counts: &HashMap<(&'a str, TypeV), usize>, name: &'a str, ty: TypeV) -> usize {
+            *counts.get(&(name, ty)).unwrap_or(&0)
+        }
+        for ty in types {
+            let primitives = self.primitives_mut(ty);
+            if primitives.nodes.is_empty() {
+                continue;
+            }
+            let node_counts: Vec<usize> = primitives
+                .nodes
+                .iter()
+                .map(|n| count(&counts, n.get_name(), ty))
+                .collect();
+            adapt(&mut primitives.weights, &node_counts, rate);
+        }
+        // terminals of any type
+        let terminal_counts =
+            ["Val", "Var"].map(|name| types.iter().map(|ty| count(&counts, name, *ty)).sum());
+        let mut weights = [self.terminal_weights.0, self.terminal_weights.1];
+        adapt(&mut weights, &terminal_counts, rate);
+        self.terminal_weights = (weights[0], weights[1]);
+    }
+
     pub fn get_rand_node(
         &self,
         depth: usize,
         rtype: TypeV,
         params: &mut BuilderParams,
     ) -> &NodeRef {
-        if (params.randomizer.gen::<f32>() <= params.termination_probability)
-            || (depth >= params.max_depth)
-        {
-            if params.randomizer.gen::<f32>() >= 0.5 {
-                &self.val_node
-            } else {
-                &self.var_node
-            }
+        let terminate = params.randomizer.gen::<f32>() <= params.termination_probability;
+        let op = if (terminate && depth >= params.min_depth) || (depth >= params.max_depth) {
+            None
         } else {
-            return match rtype {
-                TypeV::Bool => self.rtype_bool.choose(&mut params.randomizer).unwrap(),
-                TypeV::Int => self.rtype_int.choose(&mut params.randomizer).unwrap(),
-                TypeV::UInt => self.rtype_uint.choose(&mut params.randomizer).unwrap(),
-                TypeV::Float => self.rtype_float.choose(&mut params.randomizer).unwrap(),
-            };
+            self.primitives(rtype).choose(&mut params.randomizer)
+        };
+        match op {
+            Some(op) => op,
+            None => {
+                let (val, var) = self.terminal_weights;
+                if params.randomizer.gen::<f32>() >= var / (val + var) {
+                    &self.val_node
+                } else {
+                    &self.var_node
+                }
+            }
         }
     }
 }
 
+impl Default for BuilderTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Builder pattern for BuilderParams
 impl BuilderParams {
     pub fn new() -> BuilderParams {
         BuilderParams {
             max_depth: 10,                 //Set this value as default
+            min_depth: 0,
             termination_probability: 0.05, //set early termination probability as 5% in the beginning
             float_range: (0.0, 1.0),
             int_range: (-100, 100),
             uint_range: (0, 100),
-            randomizer: thread_rng(),
+            max_arity: 4,
+            randomizer: ChaCha8Rng::from_entropy(),
         }
     }
 
@@ -637,6 +947,11 @@ impl BuilderParams {
         self
     }
 
+    pub fn min_depth(mut self, val: usize) -> Self {
+        self.min_depth = val;
+        self
+    }
+
     pub fn termination_probability(mut self, val: f32) -> Self {
         self.termination_probability = val;
         self
@@ -657,9 +972,24 @@ impl BuilderParams {
         self
     }
 
+    pub fn max_arity(mut self, val: usize) -> Self {
+        self.set_max_arity(val);
+        self
+    }
+
+    /// seeds the randomizer; the same seed with same data and arguments
+    /// will produce the same results
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.set_seed(seed);
+        self
+    }
+
     pub fn set_max_depth(&mut self, val: usize) {
         self.max_depth = val;
     }
+    pub fn set_min_depth(&mut self, val: usize) {
+        self.min_depth = val;
+    }
     pub fn set_float_range(&mut self, a: f32, b: f32) {
         self.float_range = (a, b);
     }
@@ -670,12 +1000,15 @@ impl BuilderParams {
     pub fn set_uint_range(&mut self, a: u32, b: u32) {
         self.uint_range = (a, b);
     }
+    pub fn set_max_arity(&mut self, val: usize) {
+        assert!(val >= 2, "Variadic nodes take at least 2 operands");
+        self.max_arity = val;
+    }
     pub fn set_termination_probability(&mut self, val: f32) {
         self.termination_probability = val;
     }
     pub fn set_seed(&mut self, seed: u64) {
-        let mut array = [seed];
-        self.randomizer.fill(&mut array);
+        self.randomizer = ChaCha8Rng::seed_from_u64(seed);
     }
     pub fn get_mut_prob(&self, base_prob: f32, depth: usize) -> f32 {
         let s = (usize::pow(2, depth as u32) as f32) * base_prob;
@@ -686,3 +1019,9 @@ impl BuilderParams {
         } //FIXME: Maybe this is not a good function for probability growth
     }
 }
+
+impl Default for BuilderParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
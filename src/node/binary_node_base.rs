@@ -1,6 +1,11 @@
 use std::marker::PhantomData;
 
 use super::base::*;
+use super::bytecode::{Instr, Program};
+use super::codegen::Lang;
+use super::column::Column;
+use super::serialize as ser;
+use super::simplify;
 use rand::Rng;
 
 /// For now I am not using this macro because it is a little hard to use
@@ -231,6 +236,47 @@ pub trait BinOpKind {
 
     /// eval function is defined for the operation
     fn eval(lhs: Type, rhs: Type) -> Type;
+
+    /// returns the operation applied on `lhs` and `rhs`, both of type `arg_type`,
+    /// in the source code of `lang`
+    fn get_code_str(lang: Lang, arg_type: TypeV, lhs: &str, rhs: &str) -> String;
+
+    /// set if `lhs OP rhs == rhs OP lhs`; operands of commutative
+    /// operations are put in a canonical order by `Node::prune`
+    const COMMUTATIVE: bool = false;
+
+    /// returns the simpler equivalent of `lhs OP rhs` if there is one;
+    /// called from `Node::prune` on already pruned operands
+    fn simplify(_lhs: &NodeRef, _rhs: &NodeRef) -> Option<NodeRef> {
+        None
+    }
+
+    /// returns the derivative of `lhs OP rhs`, given the derivatives `dlhs` and `drhs`
+    /// of the operands; panics if the operation can't be differentiated
+    fn derivative(_lhs: &NodeRef, _rhs: &NodeRef, _dlhs: NodeRef, _drhs: NodeRef) -> NodeRef {
+        panic!("{} cannot be differentiated!!", Self::NAME)
+    }
+
+    /// return type of the operation for the given argument type.
+    /// Same as the argument type unless overridden (Ex: comparison operators)
+    fn rtype(arg_type: TypeV) -> TypeV {
+        arg_type
+    }
+
+    /// checks if the operation takes operands of type `ty`;
+    /// by default it takes the numeric types (Ex: arithmetic operators)
+    fn accepts(ty: TypeV) -> bool {
+        ty != TypeV::Bool
+    }
+
+    /// evaluates the operation over whole columns;
+    /// by default `eval` is applied on every pair of values
+    fn eval_columns(lhs: Column, rhs: Column) -> Column {
+        Column::collect(
+            Self::rtype(lhs.get_type()),
+            lhs.iter().zip(rhs.iter()).map(|(l, r)| Self::eval(l, r)),
+        )
+    }
 }
 
 /// BinOpBase<KIND> is used to specialize for operation
@@ -240,28 +286,32 @@ pub struct BinOpBase<T: BinOpKind> {
     pub arg_types: Vec<TypeV>,
     pub rhs: NodeRef,
     pub lhs: NodeRef,
-    phantom: PhantomData<T>,
+    phantom: PhantomData<fn() -> T>,
 }
 
 impl<T: 'static + BinOpKind> BinOpBase<T> {
     pub fn make(rhs: NodeRef, lhs: NodeRef) -> NodeRef {
-        let rtype = rhs.get_rtype();
+        let rtype = T::rtype(rhs.get_rtype());
         assert_eq!(rhs.get_rtype(), lhs.get_rtype());
+        let ty = rhs.get_rtype();
+        assert!(T::accepts(ty), "{} can't take operands of type {:?}", T::NAME, ty);
         Box::new(Self {
             rtype,
             arg_types: vec![lhs.get_rtype(), rhs.get_rtype()],
             rhs,
             lhs,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         })
     }
     pub fn zero(rtype: TypeV, arg_types: Vec<TypeV>) -> NodeRef {
+        let ty = arg_types[0];
+        assert!(T::accepts(ty), "{} can't take operands of type {:?}", T::NAME, ty);
         Box::new(Self {
             rtype,
             arg_types,
             rhs: Null::zero(rtype),
             lhs: Null::zero(rtype),
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         })
     }
 }
@@ -330,7 +380,11 @@ impl<T: 'static + BinOpKind> Node for BinOpBase<T> {
         node
     }
     fn type_check(&self) -> Result<(), TypeErr> {
-        if (self.lhs.get_rtype() == self.arg_types[0])
+        if !T::accepts(self.arg_types[0]) {
+            Err(TypeErr {
+                msg: format!("{} can't take arguments of type {:?}!!", T::NAME, self.arg_types[0]),
+            })
+        } else if (self.lhs.get_rtype() == self.arg_types[0])
             && (self.rhs.get_rtype() == self.arg_types[1])
         {
             self.lhs.type_check()?;
@@ -459,9 +513,14 @@ impl<T: 'static + BinOpKind> Node for BinOpBase<T> {
     }
 
     fn prune(&self) -> NodeRef {
-        //This does nothing because even in case where there are wasteful slots in
-        //binary nodes, those will require more complex analysis with specific cases
-        Self::make(self.rhs.prune(), self.lhs.prune())
+        let (mut lhs, mut rhs) = (self.lhs.prune(), self.rhs.prune());
+        if let (Some(l), Some(r)) = (simplify::const_val(&lhs), simplify::const_val(&rhs)) {
+            return Val::make(T::eval(l, r));
+        }
+        if T::COMMUTATIVE && simplify::out_of_order(&lhs, &rhs) {
+            std::mem::swap(&mut lhs, &mut rhs);
+        }
+        T::simplify(&lhs, &rhs).unwrap_or_else(|| Self::make(rhs, lhs))
     }
 
     fn get_name(&self) -> &'static str {
@@ -471,4 +530,52 @@ impl<T: 'static + BinOpKind> Node for BinOpBase<T> {
     fn get_max_depth(&self) -> usize {
         usize::max(self.lhs.get_max_depth(), self.rhs.get_max_depth()) + 1
     }
+
+    fn get_node_count(&self) -> usize {
+        self.lhs.get_node_count() + self.rhs.get_node_count() + 1
+    }
+
+    fn get_float_consts(&self, consts: &mut Vec<f32>) {
+        self.lhs.get_float_consts(consts);
+        self.rhs.get_float_consts(consts);
+    }
+
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>) {
+        self.lhs.set_float_consts(consts);
+        self.rhs.set_float_consts(consts);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        ser::op_node_to_json(T::NAME, self.rtype, &self.arg_types, &[&self.lhs, &self.rhs])
+    }
+
+    fn compile(&self, program: &mut Program) {
+        self.lhs.compile(program);
+        self.rhs.compile(program);
+        program.push(Instr::Binary(T::eval_columns));
+    }
+
+    fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column {
+        let lhs = self.lhs.eval_batch(args, n_rows);
+        let rhs = self.rhs.eval_batch(args, n_rows);
+        T::eval_columns(lhs, rhs)
+    }
+
+    fn get_code_str(&self, lang: Lang) -> String {
+        T::get_code_str(
+            lang,
+            self.arg_types[0],
+            &self.lhs.get_code_str(lang),
+            &self.rhs.get_code_str(lang),
+        )
+    }
+
+    fn derivative(&self, var_idx: usize) -> NodeRef {
+        T::derivative(
+            &self.lhs,
+            &self.rhs,
+            self.lhs.derivative(var_idx),
+            self.rhs.derivative(var_idx),
+        )
+    }
 }
@@ -53,3 +53,9 @@ impl FloatFnTable {
         float_fn_table
     }
 }
+
+impl Default for FloatFnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
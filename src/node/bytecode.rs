@@ -0,0 +1,124 @@
+#![allow(dead_code)]
+//! Flat postfix form of expression trees.
+//!
+//! Evaluating a tree through `Node::eval` goes through a virtual call for every node;
+//! for evaluating the same tree over many inputs, the tree is lowered once with
+//! `Node::compile` into a `Program`, which is then run on a small stack machine.
+//! Every slot of the stack is a `Column` holding a value for each data point,
+//! so each instruction is dispatched once for the whole data set
+use super::base::*;
+use super::column::Column;
+use std::sync::Arc;
+
+/// Operation on the columns of its arguments, for nodes that aren't unary or binary
+pub type NaryFn = Arc<dyn Fn(&[Column]) -> Column + Send + Sync>;
+
+/// Single instruction of the stack machine
+#[derive(Clone)]
+pub enum Instr {
+    /// push the constant
+    Const(Type),
+    /// push the argument at index
+    Var(usize),
+    /// pop one value, push the result
+    Unary(fn(Column) -> Column),
+    /// pop rhs then lhs, push the result
+    Binary(fn(Column, Column) -> Column),
+    /// pop iffalse, iftrue and cond; push the selected branch
+    Cond,
+    /// pop the given number of arguments, push the result
+    Nary(NaryFn, usize),
+}
+
+/// Expression tree compiled into postfix instructions
+pub struct Program {
+    instrs: Vec<Instr>,
+    /// stack depth at the current end of the program
+    depth: usize,
+    /// maximum stack depth required for running the program
+    max_depth: usize,
+}
+
+impl Program {
+    fn new() -> Program {
+        Program {
+            instrs: vec![],
+            depth: 0,
+            max_depth: 0,
+        }
+    }
+
+    /// compiles the tree rooted at `root`
+    pub fn compile(root: &NodeRef) -> Program {
+        let mut program = Program::new();
+        root.compile(&mut program);
+        program
+    }
+
+    /// appends an instruction; used by `Node::compile`
+    pub fn push(&mut self, instr: Instr) {
+        match instr {
+            Instr::Const(_) | Instr::Var(_) => self.depth += 1,
+            Instr::Unary(_) => {}
+            Instr::Binary(_) => self.depth -= 1,
+            Instr::Cond => self.depth -= 2,
+            Instr::Nary(_, arity) => self.depth = self.depth + 1 - arity,
+        }
+        self.max_depth = usize::max(self.max_depth, self.depth);
+        self.instrs.push(instr);
+    }
+
+    pub fn len(&self) -> usize {
+        self.instrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instrs.is_empty()
+    }
+
+    /// evaluates the program for a single set of arguments
+    pub fn eval(&self, args: &[Type]) -> Type {
+        self.eval_rows(&[args])[0]
+    }
+
+    /// evaluates the program for every row of arguments
+    pub fn eval_rows<R: AsRef<[Type]>>(&self, rows: &[R]) -> Vec<Type> {
+        if rows.is_empty() {
+            return vec![];
+        }
+        self.eval_columns(&Column::from_rows(rows), rows.len()).to_vec()
+    }
+
+    /// evaluates the program over `n_rows` data points;
+    /// `args` holds a column of `n_rows` values for each argument
+    pub fn eval_columns(&self, args: &[Column], n_rows: usize) -> Column {
+        let mut stack: Vec<Column> = Vec::with_capacity(self.max_depth);
+        for instr in self.instrs.iter() {
+            match instr {
+                Instr::Const(v) => stack.push(Column::splat(*v, n_rows)),
+                Instr::Var(idx) => stack.push(args[*idx].clone()),
+                Instr::Unary(f) => {
+                    let arg = stack.pop().unwrap();
+                    stack.push(f(arg));
+                }
+                Instr::Binary(f) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(f(lhs, rhs));
+                }
+                Instr::Cond => {
+                    let iffalse = stack.pop().unwrap();
+                    let iftrue = stack.pop().unwrap();
+                    let cond = stack.pop().unwrap();
+                    stack.push(Column::select(cond, iftrue, iffalse));
+                }
+                Instr::Nary(f, arity) => {
+                    let args = stack.split_off(stack.len() - arity);
+                    stack.push(f(&args));
+                }
+            }
+        }
+        assert_eq!(stack.len(), 1, "Cannot evaluate an incomplete program!!");
+        stack.pop().unwrap()
+    }
+}
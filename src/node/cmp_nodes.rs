@@ -2,11 +2,35 @@
 /// all nodes assumes arguments are of same kind
 use super::base::*;
 use super::binary_node_base as bb;
+use super::codegen::Lang;
+use super::simplify;
+
+/// `x OP x` is `result` for every `x`, except float NaNs
+fn reflexive(lhs: &NodeRef, rhs: &NodeRef, result: bool) -> Option<NodeRef> {
+    if lhs.get_rtype() != TypeV::Float && simplify::same(lhs, rhs) {
+        Some(Val::make(Type::Bool(result)))
+    } else {
+        None
+    }
+}
 
 pub struct EqEval;
 
 impl bb::BinOpKind for EqEval {
     const NAME: &'static str = "==";
+    const COMMUTATIVE: bool = true;
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        reflexive(lhs, rhs, true)
+    }
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} == {})", lhs, rhs)
+    }
+    fn rtype(_arg_type: TypeV) -> TypeV {
+        TypeV::Bool
+    }
+    fn accepts(_ty: TypeV) -> bool {
+        true
+    }
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li == ri),
@@ -25,7 +49,20 @@ pub struct NEqEval;
 
 impl bb::BinOpKind for NEqEval {
     const NAME: &'static str = "!=";
+    const COMMUTATIVE: bool = true;
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        reflexive(lhs, rhs, false)
+    }
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} != {})", lhs, rhs)
+    }
 
+    fn rtype(_arg_type: TypeV) -> TypeV {
+        TypeV::Bool
+    }
+    fn accepts(_ty: TypeV) -> bool {
+        true
+    }
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li != ri),
@@ -44,12 +81,24 @@ pub struct GtEval;
 
 impl bb::BinOpKind for GtEval {
     const NAME: &'static str = ">";
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        reflexive(lhs, rhs, false)
+    }
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} > {})", lhs, rhs)
+    }
+    fn rtype(_arg_type: TypeV) -> TypeV {
+        TypeV::Bool
+    }
+    fn accepts(_ty: TypeV) -> bool {
+        true
+    }
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li & !ri),
-            (Type::Int(ri), Type::Int(li)) => Type::Bool(ri > li),
-            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(ri > li),
-            (Type::Float(ri), Type::Float(li)) => Type::Bool(ri > li),
+            (Type::Int(ri), Type::Int(li)) => Type::Bool(li > ri),
+            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(li > ri),
+            (Type::Float(ri), Type::Float(li)) => Type::Bool(li > ri),
             _ => unreachable!(),
         }
     }
@@ -62,12 +111,24 @@ pub struct GteEval;
 
 impl bb::BinOpKind for GteEval {
     const NAME: &'static str = ">=";
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        reflexive(lhs, rhs, true)
+    }
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} >= {})", lhs, rhs)
+    }
+    fn rtype(_arg_type: TypeV) -> TypeV {
+        TypeV::Bool
+    }
+    fn accepts(_ty: TypeV) -> bool {
+        true
+    }
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li >= ri),
-            (Type::Int(ri), Type::Int(li)) => Type::Bool(ri >= li),
-            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(ri >= li),
-            (Type::Float(ri), Type::Float(li)) => Type::Bool(ri >= li),
+            (Type::Int(ri), Type::Int(li)) => Type::Bool(li >= ri),
+            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(li >= ri),
+            (Type::Float(ri), Type::Float(li)) => Type::Bool(li >= ri),
             _ => unreachable!(),
         }
     }
@@ -80,12 +141,24 @@ pub struct LtEval;
 
 impl bb::BinOpKind for LtEval {
     const NAME: &'static str = "<";
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        reflexive(lhs, rhs, false)
+    }
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} < {})", lhs, rhs)
+    }
+    fn rtype(_arg_type: TypeV) -> TypeV {
+        TypeV::Bool
+    }
+    fn accepts(_ty: TypeV) -> bool {
+        true
+    }
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Bool(ri), Type::Bool(li)) => Type::Bool(!li & ri),
-            (Type::Int(ri), Type::Int(li)) => Type::Bool(ri < li),
-            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(ri < li),
-            (Type::Float(ri), Type::Float(li)) => Type::Bool(ri < li),
+            (Type::Int(ri), Type::Int(li)) => Type::Bool(li < ri),
+            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(li < ri),
+            (Type::Float(ri), Type::Float(li)) => Type::Bool(li < ri),
             _ => unreachable!(),
         }
     }
@@ -98,12 +171,24 @@ pub struct LteEval;
 
 impl bb::BinOpKind for LteEval {
     const NAME: &'static str = "<=";
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        reflexive(lhs, rhs, true)
+    }
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} <= {})", lhs, rhs)
+    }
+    fn rtype(_arg_type: TypeV) -> TypeV {
+        TypeV::Bool
+    }
+    fn accepts(_ty: TypeV) -> bool {
+        true
+    }
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li <= ri),
-            (Type::Int(ri), Type::Int(li)) => Type::Bool(ri <= li),
-            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(ri <= li),
-            (Type::Float(ri), Type::Float(li)) => Type::Bool(ri <= li),
+            (Type::Int(ri), Type::Int(li)) => Type::Bool(li <= ri),
+            (Type::UInt(ri), Type::UInt(li)) => Type::Bool(li <= ri),
+            (Type::Float(ri), Type::Float(li)) => Type::Bool(li <= ri),
             _ => unreachable!(),
         }
     }
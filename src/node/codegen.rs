@@ -0,0 +1,127 @@
+#![allow(dead_code)]
+//! Source code generation from expression trees.
+//!
+//! Every node writes itself as an expression of the target language
+//! through `Node::get_code_str`; the helpers here take care of the parts
+//! that are common to all nodes, like literals, type names and function signatures
+use super::base::*;
+
+/// Target language of the generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Rust,
+    C,
+}
+
+/// name of the `idx`th parameter of the generated function
+pub fn var_name(idx: usize) -> String {
+    format!("x{}", idx)
+}
+
+pub fn type_name(ty: TypeV, lang: Lang) -> &'static str {
+    match (lang, ty) {
+        (Lang::Rust, TypeV::Int) => "i32",
+        (Lang::Rust, TypeV::Float) => "f32",
+        (Lang::Rust, TypeV::UInt) => "u32",
+        (Lang::Rust, TypeV::Bool) => "bool",
+        (Lang::C, TypeV::Int) => "int32_t",
+        (Lang::C, TypeV::Float) => "float",
+        (Lang::C, TypeV::UInt) => "uint32_t",
+        (Lang::C, TypeV::Bool) => "bool",
+    }
+}
+
+/// literal for the value; negative values are parenthesised
+pub fn literal(val: Type, lang: Lang) -> String {
+    match (lang, val) {
+        (Lang::Rust, Type::Float(f)) if f.is_nan() => "f32::NAN".to_string(),
+        (Lang::Rust, Type::Float(f)) if f.is_infinite() && f > 0.0 => "f32::INFINITY".to_string(),
+        (Lang::Rust, Type::Float(f)) if f.is_infinite() => "f32::NEG_INFINITY".to_string(),
+        (Lang::Rust, Type::Float(f)) if f.is_sign_negative() => format!("({:?}_f32)", f),
+        (Lang::Rust, Type::Float(f)) => format!("{:?}_f32", f),
+        (Lang::Rust, Type::Int(i)) if i < 0 => format!("({}_i32)", i),
+        (Lang::Rust, Type::Int(i)) => format!("{}_i32", i),
+        (Lang::Rust, Type::UInt(u)) => format!("{}_u32", u),
+        (Lang::C, Type::Float(f)) if f.is_nan() => "NAN".to_string(),
+        (Lang::C, Type::Float(f)) if f.is_infinite() && f > 0.0 => "INFINITY".to_string(),
+        (Lang::C, Type::Float(f)) if f.is_infinite() => "(-INFINITY)".to_string(),
+        (Lang::C, Type::Float(f)) if f.is_sign_negative() => format!("({:?}f)", f),
+        (Lang::C, Type::Float(f)) => format!("{:?}f", f),
+        // -2147483648 is not a valid literal in C
+        (Lang::C, Type::Int(i)) if i == i32::MIN => "(-2147483647 - 1)".to_string(),
+        (Lang::C, Type::Int(i)) if i < 0 => format!("({})", i),
+        (Lang::C, Type::Int(i)) => format!("{}", i),
+        (Lang::C, Type::UInt(u)) => format!("{}u", u),
+        (_, Type::Bool(b)) => format!("{}", b),
+    }
+}
+
+/// `if cond { iftrue } else { iffalse }` as an expression
+pub fn select(cond: &str, iftrue: &str, iffalse: &str, lang: Lang) -> String {
+    match lang {
+        Lang::Rust => format!("(if {} {{ {} }} else {{ {} }})", cond, iftrue, iffalse),
+        Lang::C => format!("({} ? {} : {})", cond, iftrue, iffalse),
+    }
+}
+
+/// call of a function taking float arguments;
+/// `rust_fn` is an associated function of `f32` and `c_fn` is from `math.h`
+pub fn float_fn(rust_fn: &str, c_fn: &str, args: &[&str], lang: Lang) -> String {
+    match lang {
+        Lang::Rust => format!("f32::{}({})", rust_fn, args.join(", ")),
+        Lang::C => format!("{}({})", c_fn, args.join(", ")),
+    }
+}
+
+/// C has no integer power, minimum or maximum; generated C code uses these helpers instead.
+/// Being functions, their operands are evaluated once, however deep they're nested
+const C_HELPERS: &str = "static inline uint32_t ga_upow(uint32_t base, uint32_t exp) {
+    uint32_t ret = 1;
+    while (exp-- > 0) {
+        ret *= base;
+    }
+    return ret;
+}
+static inline int32_t ga_imin(int32_t a, int32_t b) { return a < b ? a : b; }
+static inline int32_t ga_imax(int32_t a, int32_t b) { return a > b ? a : b; }
+static inline uint32_t ga_umin(uint32_t a, uint32_t b) { return a < b ? a : b; }
+static inline uint32_t ga_umax(uint32_t a, uint32_t b) { return a > b ? a : b; }
+";
+
+/// wraps the expression `body` into a standalone function named `name`
+/// taking arguments of `arg_types` (named `x0`, `x1`, ...) and returning `rtype`
+pub fn function(name: &str, arg_types: &[TypeV], rtype: TypeV, body: &str, lang: Lang) -> String {
+    let params: Vec<_> = arg_types
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| match lang {
+            Lang::Rust => format!("{}: {}", var_name(i), type_name(*ty, lang)),
+            Lang::C => format!("{} {}", type_name(*ty, lang), var_name(i)),
+        })
+        .collect();
+    match lang {
+        Lang::Rust => format!(
+            "#[allow(unused_variables, unused_parens)]\npub fn {}({}) -> {} {{\n    {}\n}}\n",
+            name,
+            params.join(", "),
+            type_name(rtype, lang),
+            body
+        ),
+        Lang::C => format!(
+            "#include <math.h>\n#include <stdbool.h>\n#include <stdint.h>\n\n{}\n{} {}({}) {{\n{}    return {};\n}}\n",
+            C_HELPERS,
+            type_name(rtype, lang),
+            name,
+            if params.is_empty() {
+                "void".to_string()
+            } else {
+                params.join(", ")
+            },
+            // not every argument need be used by the expression
+            (0..arg_types.len())
+                .map(|i| format!("    (void){};\n", var_name(i)))
+                .collect::<String>(),
+            body
+        ),
+    }
+}
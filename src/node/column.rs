@@ -0,0 +1,134 @@
+#![allow(dead_code)]
+//! Column-major data for batch evaluation.
+//!
+//! A `Column` holds one value per row of a data set, all of the same type.
+//! Node kinds evaluate whole columns at a time (see `UnaryOpKind::eval_column`
+//! and `BinOpKind::eval_columns`), so the operation is dispatched once per column
+//! and the loop over the rows is left to the compiler to vectorise
+use super::base::*;
+
+#[derive(Debug, Clone)]
+pub enum Column {
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    UInt(Vec<u32>),
+    Bool(Vec<bool>),
+}
+
+impl Column {
+    /// column of `n` copies of `val`
+    pub fn splat(val: Type, n: usize) -> Column {
+        match val {
+            Type::Int(i) => Column::Int(vec![i; n]),
+            Type::Float(f) => Column::Float(vec![f; n]),
+            Type::UInt(u) => Column::UInt(vec![u; n]),
+            Type::Bool(b) => Column::Bool(vec![b; n]),
+        }
+    }
+
+    /// collects values of type `rtype` into a column; panics on values of any other type
+    pub fn collect<I: Iterator<Item = Type>>(rtype: TypeV, iter: I) -> Column {
+        let bad = |v: Type| -> ! { panic!("Column of {:?} cannot hold {:?}!!", rtype, v) };
+        match rtype {
+            TypeV::Int => Column::Int(
+                iter.map(|v| if let Type::Int(i) = v { i } else { bad(v) })
+                    .collect(),
+            ),
+            TypeV::Float => Column::Float(
+                iter.map(|v| if let Type::Float(f) = v { f } else { bad(v) })
+                    .collect(),
+            ),
+            TypeV::UInt => Column::UInt(
+                iter.map(|v| if let Type::UInt(u) = v { u } else { bad(v) })
+                    .collect(),
+            ),
+            TypeV::Bool => Column::Bool(
+                iter.map(|v| if let Type::Bool(b) = v { b } else { bad(v) })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// transposes row-major data (one `Vec<Type>` per data point)
+    /// into one column per argument
+    pub fn from_rows<R: AsRef<[Type]>>(rows: &[R]) -> Vec<Column> {
+        match rows.first() {
+            None => vec![],
+            Some(first) => (0..first.as_ref().len())
+                .map(|idx| {
+                    let rtype = first.as_ref()[idx].get_type();
+                    Column::collect(rtype, rows.iter().map(|row| row.as_ref()[idx]))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get_type(&self) -> TypeV {
+        match self {
+            Column::Int(_) => TypeV::Int,
+            Column::Float(_) => TypeV::Float,
+            Column::UInt(_) => TypeV::UInt,
+            Column::Bool(_) => TypeV::Bool,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Int(v) => v.len(),
+            Column::Float(v) => v.len(),
+            Column::UInt(v) => v.len(),
+            Column::Bool(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Type {
+        match self {
+            Column::Int(v) => Type::Int(v[i]),
+            Column::Float(v) => Type::Float(v[i]),
+            Column::UInt(v) => Type::UInt(v[i]),
+            Column::Bool(v) => Type::Bool(v[i]),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Type> + '_ {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+
+    pub fn to_vec(&self) -> Vec<Type> {
+        self.iter().collect()
+    }
+
+    /// elementwise `if cond { iftrue } else { iffalse }`
+    pub fn select(cond: Column, iftrue: Column, iffalse: Column) -> Column {
+        let cond = match cond {
+            Column::Bool(c) => c,
+            c => panic!("Invalid: Cond must be bool (Got {:?})", c.get_type()),
+        };
+        macro_rules! select {
+            ($t: expr, $f: expr) => {{
+                let mut t = $t;
+                for ((t, f), c) in t.iter_mut().zip($f).zip(cond) {
+                    if !c {
+                        *t = f;
+                    }
+                }
+                t
+            }};
+        }
+        match (iftrue, iffalse) {
+            (Column::Int(t), Column::Int(f)) => Column::Int(select!(t, f)),
+            (Column::Float(t), Column::Float(f)) => Column::Float(select!(t, f)),
+            (Column::UInt(t), Column::UInt(f)) => Column::UInt(select!(t, f)),
+            (Column::Bool(t), Column::Bool(f)) => Column::Bool(select!(t, f)),
+            (t, f) => panic!(
+                "Invalid: Branches must be of same type (Got {:?} and {:?})",
+                t.get_type(),
+                f.get_type()
+            ),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+#![allow(dead_code)]
+//! Primitives defined at runtime from closures.
+//!
+//! The predefined nodes are types implementing `UnaryOpKind`/`BinOpKind`, so adding
+//! one means compiling it in; a `CustomNode` instead carries its name, signature and
+//! the closure computing it, and can be made from configuration. Custom nodes are
+//! used like any other: pushed to a `BuilderTable` for building and mutation, and
+//! registered with `NodeRegistry::register_node` for parsing and loading trees.
+//!
+//! ```text
+//! let sigmoid = CustomNode::unary("sigmoid", TypeV::Float, TypeV::Float, |x| {
+//!     Type::Float(1.0 / (1.0 + (-x.as_f32()).exp()))
+//! });
+//! table.push(TypeV::Float, sigmoid.deep_copy());
+//! registry.register_node(sigmoid);
+//! ```
+use super::base::*;
+use super::bytecode::{Instr, Program};
+use super::codegen::Lang;
+use super::column::Column;
+use super::nary_node_base as nb;
+use super::serialize as ser;
+use super::simplify;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Closure computing a custom node from the values of its children
+pub type CustomFn = Arc<dyn Fn(&[Type]) -> Type + Send + Sync>;
+
+/// Name, signature and closure of a custom node, shared by all of its copies
+struct CustomDef {
+    name: Arc<str>,
+    arg_types: Vec<TypeV>,
+    rtype: TypeV,
+    f: CustomFn,
+}
+
+impl CustomDef {
+    /// applies the closure on every row of the columns
+    fn eval_columns(&self, args: &[Column]) -> Column {
+        let mut row = Vec::with_capacity(args.len());
+        Column::collect(
+            self.rtype,
+            (0..args[0].len()).map(|i| {
+                row.clear();
+                row.extend(args.iter().map(|c| c.get(i)));
+                (self.f)(&row)
+            }),
+        )
+    }
+}
+
+pub struct CustomNode {
+    def: Arc<CustomDef>,
+    pub children: Vec<NodeRef>,
+}
+
+impl CustomNode {
+    /// returns the zero node of a custom primitive named `name`, taking arguments of
+    /// `arg_types` and computing a value of `rtype` with `f`.
+    /// The name must be an identifier, so that the equation of the node can be parsed
+    pub fn nary<F>(name: &str, arg_types: Vec<TypeV>, rtype: TypeV, f: F) -> NodeRef
+    where
+        F: Fn(&[Type]) -> Type + Send + Sync + 'static,
+    {
+        assert!(
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "Name of custom node must be an identifier; Got `{}`",
+            name
+        );
+        assert!(
+            !["Val", "Var", "NULL", "true", "false"].contains(&name),
+            "`{}` is reserved",
+            name
+        );
+        assert!(!arg_types.is_empty(), "Custom node `{}` needs at least one argument", name);
+        let def = CustomDef {
+            name: name.into(),
+            arg_types,
+            rtype,
+            f: Arc::new(f),
+        };
+        Self::zero(Arc::new(def))
+    }
+
+    /// custom primitive with a single argument
+    pub fn unary<F>(name: &str, arg_type: TypeV, rtype: TypeV, f: F) -> NodeRef
+    where
+        F: Fn(Type) -> Type + Send + Sync + 'static,
+    {
+        Self::nary(name, vec![arg_type], rtype, move |args| f(args[0]))
+    }
+
+    /// custom primitive with two arguments
+    pub fn binary<F>(name: &str, arg_types: (TypeV, TypeV), rtype: TypeV, f: F) -> NodeRef
+    where
+        F: Fn(Type, Type) -> Type + Send + Sync + 'static,
+    {
+        Self::nary(name, vec![arg_types.0, arg_types.1], rtype, move |args| {
+            f(args[0], args[1])
+        })
+    }
+
+    fn zero(def: Arc<CustomDef>) -> NodeRef {
+        let children = def.arg_types.iter().map(|ty| Null::zero(*ty)).collect();
+        Box::new(CustomNode { def, children })
+    }
+
+    fn make(&self, children: Vec<NodeRef>) -> NodeRef {
+        Box::new(CustomNode {
+            def: Arc::clone(&self.def),
+            children,
+        })
+    }
+}
+
+impl Node for CustomNode {
+    fn eval(&self, args: &[Type]) -> Type {
+        let vals: Vec<_> = self.children.iter().map(|c| c.eval(args)).collect();
+        (self.def.f)(&vals)
+    }
+
+    fn get_rtype(&self) -> TypeV {
+        self.def.rtype
+    }
+
+    fn get_tree_str(&self, indent: usize) -> String {
+        let children: Vec<_> = self
+            .children
+            .iter()
+            .map(|c| c.get_tree_str(indent + 1))
+            .collect();
+        ".".repeat(indent) + &self.def.name + "\n" + &children.join("\n")
+    }
+
+    fn get_equation_str(&self) -> String {
+        let children: Vec<_> = self.children.iter().map(|c| c.get_equation_str()).collect();
+        format!("{}({})", self.def.name, children.join(", "))
+    }
+
+    fn get_arg_types(&self) -> &[TypeV] {
+        &self.def.arg_types
+    }
+
+    fn set_child(&mut self, child_index: usize, child: NodeRef) {
+        self.children[child_index] = child;
+    }
+
+    fn get_child(&self, child_index: usize) -> &NodeRef {
+        &self.children[child_index]
+    }
+
+    fn get_zero_node(&self) -> NodeRef {
+        Self::zero(Arc::clone(&self.def))
+    }
+
+    fn build_random_node<'a>(
+        &self,
+        build_table: &'a BuilderTable,
+        arg_types: &[TypeV],
+        _node_rtype: TypeV,
+        depth: usize,
+        params: &'a mut BuilderParams,
+    ) -> NodeRef {
+        let children = self
+            .def
+            .arg_types
+            .iter()
+            .map(|ty| {
+                build_table
+                    .get_rand_node(depth + 1, *ty, params)
+                    .build_random_node(build_table, arg_types, *ty, depth + 1, params)
+            })
+            .collect();
+        self.make(children)
+    }
+
+    fn deep_copy(&self) -> NodeRef {
+        self.make(self.children.iter().map(|c| c.deep_copy()).collect())
+    }
+
+    fn mutant_copy<'a>(
+        &self,
+        probability: f32,
+        node_depth: usize,
+        arg_types: &[TypeV],
+        build_table: &'a BuilderTable,
+        params: &'a mut BuilderParams,
+    ) -> Option<NodeRef> {
+        if params.randomizer.gen::<f32>() < params.get_mut_prob(probability, node_depth) {
+            Some(self.build_random_node(
+                build_table,
+                arg_types,
+                self.get_rtype(),
+                node_depth,
+                params,
+            ))
+        } else {
+            let children = nb::mutant_children(
+                &self.children,
+                probability,
+                node_depth,
+                arg_types,
+                build_table,
+                params,
+            )?;
+            Some(self.make(children))
+        }
+    }
+
+    fn type_check(&self) -> Result<(), TypeErr> {
+        let child_types: Vec<_> = self.children.iter().map(|c| c.get_rtype()).collect();
+        if child_types == self.def.arg_types {
+            self.children.iter().try_for_each(|c| c.type_check())
+        } else {
+            Err(TypeErr {
+                msg: format!(
+                    "{} required argument of type {:?}; Got {:?}!!",
+                    self.def.name, self.def.arg_types, child_types
+                ),
+            })
+        }
+    }
+
+    fn get_random_child(
+        &self,
+        probability: f32,
+        depth: usize,
+        params: &mut BuilderParams,
+    ) -> Option<NodeRef> {
+        if params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth) {
+            Some(self.deep_copy())
+        } else {
+            nb::random_child(&self.children, probability, depth, params)
+        }
+    }
+
+    fn set_random_child(
+        &self,
+        new_node: NodeRef,
+        probability: f32,
+        depth: usize,
+        params: &mut BuilderParams,
+    ) -> Option<NodeRef> {
+        if depth + new_node.get_max_depth() > params.max_depth {
+            None
+        } else if new_node.get_rtype() == self.get_rtype()
+            && params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth)
+        {
+            Some(new_node)
+        } else {
+            let children =
+                nb::set_random_operand(&self.children, new_node, probability, depth, params)?;
+            Some(self.make(children))
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        &self.def.name
+    }
+
+    fn prune(&self) -> NodeRef {
+        let children: Vec<_> = self.children.iter().map(|c| c.prune()).collect();
+        let consts: Option<Vec<_>> = children.iter().map(simplify::const_val).collect();
+        match consts {
+            Some(vals) => Val::make((self.def.f)(&vals)),
+            None => self.make(children),
+        }
+    }
+
+    fn get_max_depth(&self) -> usize {
+        self.children.iter().map(|c| c.get_max_depth()).max().unwrap_or(0) + 1
+    }
+
+    fn get_node_count(&self) -> usize {
+        self.children.iter().map(|c| c.get_node_count()).sum::<usize>() + 1
+    }
+
+    fn get_float_consts(&self, consts: &mut Vec<f32>) {
+        for c in self.children.iter() {
+            c.get_float_consts(consts);
+        }
+    }
+
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>) {
+        for c in self.children.iter_mut() {
+            c.set_float_consts(consts);
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let children: Vec<_> = self.children.iter().collect();
+        ser::op_node_to_json(&self.def.name, self.def.rtype, &self.def.arg_types, &children)
+    }
+
+    fn compile(&self, program: &mut Program) {
+        for c in self.children.iter() {
+            c.compile(program);
+        }
+        let def = Arc::clone(&self.def);
+        program.push(Instr::Nary(
+            Arc::new(move |args| def.eval_columns(args)),
+            self.children.len(),
+        ));
+    }
+
+    fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column {
+        let cols: Vec<_> = self
+            .children
+            .iter()
+            .map(|c| c.eval_batch(args, n_rows))
+            .collect();
+        self.def.eval_columns(&cols)
+    }
+
+    /// a call of a function with the name of the node, taking the arguments in order;
+    /// the function isn't generated, and is to be defined along with the generated code
+    fn get_code_str(&self, lang: Lang) -> String {
+        let children: Vec<_> = self.children.iter().map(|c| c.get_code_str(lang)).collect();
+        format!("{}({})", self.def.name, children.join(", "))
+    }
+
+    fn derivative(&self, _var_idx: usize) -> NodeRef {
+        panic!("{} cannot be differentiated!!", self.def.name)
+    }
+}
@@ -0,0 +1,112 @@
+#![allow(dead_code)]
+//! Helpers for building derivative trees (see `Node::derivative`).
+//!
+//! The constructors drop terms that are multiplied by a constant zero; unlike
+//! `Node::prune` they may do this for floats too, as a zero derivative is exactly zero
+//! and not the result of `inf - inf` and such
+use super::base::*;
+use super::cmp_nodes::Gte;
+use super::math_nodes as math;
+use super::misc_nodes::Cond;
+use super::op_nodes as ops;
+use super::simplify::is_num;
+
+/// float constant
+pub fn num(val: f32) -> NodeRef {
+    Val::make(Type::Float(val))
+}
+
+pub fn add(a: NodeRef, b: NodeRef) -> NodeRef {
+    if is_num(&a, 0) {
+        b
+    } else if is_num(&b, 0) {
+        a
+    } else {
+        ops::Add::make(b, a)
+    }
+}
+
+pub fn sub(a: NodeRef, b: NodeRef) -> NodeRef {
+    if is_num(&b, 0) {
+        a
+    } else if is_num(&a, 0) {
+        neg(b)
+    } else {
+        ops::Sub::make(b, a)
+    }
+}
+
+pub fn mul(a: NodeRef, b: NodeRef) -> NodeRef {
+    if is_num(&a, 0) || is_num(&b, 0) {
+        num(0.0)
+    } else if is_num(&a, 1) {
+        b
+    } else if is_num(&b, 1) {
+        a
+    } else {
+        ops::Mul::make(b, a)
+    }
+}
+
+pub fn div(a: NodeRef, b: NodeRef) -> NodeRef {
+    if is_num(&a, 0) {
+        num(0.0)
+    } else {
+        ops::Div::make(b, a)
+    }
+}
+
+pub fn pow(a: NodeRef, b: NodeRef) -> NodeRef {
+    ops::Pow::make(b, a)
+}
+
+pub fn neg(a: NodeRef) -> NodeRef {
+    mul(a, num(-1.0))
+}
+
+pub fn sqrt(a: NodeRef) -> NodeRef {
+    pow(a, num(0.5))
+}
+
+/// `c ? iftrue : iffalse`
+pub fn cond(c: NodeRef, iftrue: NodeRef, iffalse: NodeRef) -> NodeRef {
+    if is_num(&iftrue, 0) && is_num(&iffalse, 0) {
+        num(0.0)
+    } else {
+        Cond::make(c, iftrue, iffalse)
+    }
+}
+
+/// `a >= 0 ? iftrue : iffalse`
+pub fn if_non_neg(a: NodeRef, iftrue: NodeRef, iffalse: NodeRef) -> NodeRef {
+    cond(Gte::make(num(0.0), a), iftrue, iffalse)
+}
+
+/// chain rule for unary functions; `darg` multiplied by `f'(arg)`,
+/// where `f` builds the derivative of the function
+pub fn chain(darg: NodeRef, arg: &NodeRef, f: fn(NodeRef) -> NodeRef) -> NodeRef {
+    if is_num(&darg, 0) {
+        darg
+    } else {
+        mul(f(arg.deep_copy()), darg)
+    }
+}
+
+pub fn cos(a: NodeRef) -> NodeRef {
+    math::Cos::make(a)
+}
+pub fn sin(a: NodeRef) -> NodeRef {
+    math::Sin::make(a)
+}
+pub fn cosh(a: NodeRef) -> NodeRef {
+    math::Cosh::make(a)
+}
+pub fn sinh(a: NodeRef) -> NodeRef {
+    math::Sinh::make(a)
+}
+pub fn exp(a: NodeRef) -> NodeRef {
+    math::Exp::make(a)
+}
+pub fn ln(a: NodeRef) -> NodeRef {
+    math::Log::make(a)
+}
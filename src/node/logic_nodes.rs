@@ -1,12 +1,38 @@
 #![allow(dead_code)]
 use super::base::*;
 use super::binary_node_base as bb;
+use super::codegen::Lang;
+use super::simplify::*;
 use super::unary_node_base as ub;
 
 pub struct AndEval;
 
 impl bb::BinOpKind for AndEval {
     const NAME: &'static str = "&";
+    const COMMUTATIVE: bool = true;
+
+    /// x & x = x; x & true = x; x & false = false;
+    /// constants are on the right after reordering
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if same(lhs, rhs) || is_bool(rhs, true) {
+            Some(lhs.deep_copy())
+        } else if is_bool(rhs, false) {
+            Some(rhs.deep_copy())
+        } else {
+            None
+        }
+    }
+
+    fn accepts(ty: TypeV) -> bool {
+        ty != TypeV::Float
+    }
+
+    fn get_code_str(_lang: Lang, arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        match arg_type {
+            TypeV::Bool => format!("({} && {})", lhs, rhs),
+            _ => format!("({} & {})", lhs, rhs),
+        }
+    }
 
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
@@ -24,12 +50,36 @@ pub struct OrEval;
 
 impl bb::BinOpKind for OrEval {
     const NAME: &'static str = "|";
+    const COMMUTATIVE: bool = true;
+
+    /// x | x = x; x | false = x; x | true = true;
+    /// constants are on the right after reordering
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if same(lhs, rhs) || is_bool(rhs, false) {
+            Some(lhs.deep_copy())
+        } else if is_bool(rhs, true) {
+            Some(rhs.deep_copy())
+        } else {
+            None
+        }
+    }
+
+    fn accepts(ty: TypeV) -> bool {
+        ty != TypeV::Float
+    }
+
+    fn get_code_str(_lang: Lang, arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        match arg_type {
+            TypeV::Bool => format!("({} || {})", lhs, rhs),
+            _ => format!("({} | {})", lhs, rhs),
+        }
+    }
 
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
-            (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li && ri),
-            (Type::Int(ri), Type::Int(li)) => Type::Int(ri & li),
-            (Type::UInt(ri), Type::UInt(li)) => Type::UInt(ri & li),
+            (Type::Bool(ri), Type::Bool(li)) => Type::Bool(li || ri),
+            (Type::Int(ri), Type::Int(li)) => Type::Int(ri | li),
+            (Type::UInt(ri), Type::UInt(li)) => Type::UInt(ri | li),
             _ => unreachable!(),
         }
     }
@@ -44,6 +94,19 @@ impl ub::UnaryOpKind for NotEval {
     const RTYPE: TypeV = TypeV::Bool;
     const NAME: &'static str = "~";
 
+    fn get_code_str(_lang: Lang, arg: &str) -> String {
+        format!("(!{})", arg)
+    }
+
+    /// ~~x = x
+    fn simplify(arg: &NodeRef) -> Option<NodeRef> {
+        if arg.get_name() == Self::NAME {
+            Some(arg.get_child(0).deep_copy())
+        } else {
+            None
+        }
+    }
+
     fn eval(input: Type) -> Type {
         if let Type::Bool(b) = input {
             Type::Bool(!b)
@@ -1,8 +1,11 @@
 use super::base::*;
+use super::codegen::{self, Lang};
+use super::column::Column;
+use super::diff::*;
 use super::unary_node_base as ub;
 
 macro_rules! single_arg_fn_node_def {
-    ($type_name: ident, $temp_type_name: ident, $expr_fn: expr) => {
+    ($type_name: ident, $temp_type_name: ident, $expr_fn: expr, $rust_fn: expr, $c_fn: expr, $idempotent: expr, $deriv: expr) => {
         pub struct $temp_type_name {}
 
         impl ub::UnaryOpKind for $temp_type_name {
@@ -17,27 +20,72 @@ macro_rules! single_arg_fn_node_def {
                     unreachable!();
                 }
             }
+
+            fn get_code_str(lang: Lang, arg: &str) -> String {
+                codegen::float_fn($rust_fn, $c_fn, &[arg], lang)
+            }
+
+            /// chain rule, with `$deriv` building the derivative of the function
+            fn derivative(arg: &NodeRef, darg: NodeRef) -> NodeRef {
+                chain(darg, arg, $deriv)
+            }
+
+            /// f(f(x)) = f(x) if the function is idempotent
+            fn simplify(arg: &NodeRef) -> Option<NodeRef> {
+                if $idempotent && arg.get_name() == Self::NAME {
+                    Some(arg.deep_copy())
+                } else {
+                    None
+                }
+            }
+
+            fn eval_column(input: Column) -> Column {
+                if let Column::Float(mut v) = input {
+                    v.iter_mut().for_each(|x| *x = $expr_fn(*x));
+                    Column::Float(v)
+                } else {
+                    unreachable!();
+                }
+            }
         }
 
         pub type $type_name = ub::UnaryOpBase<$temp_type_name>;
     };
 }
 
-single_arg_fn_node_def!(Sin, __Sin, f32::sin);
-single_arg_fn_node_def!(Cos, __Cos, f32::cos);
-single_arg_fn_node_def!(Tan, __Tan, f32::tan);
-single_arg_fn_node_def!(ASin, __ASin, f32::asin);
-single_arg_fn_node_def!(ACos, __ACos, f32::acos);
-single_arg_fn_node_def!(ATan, __ATan, f32::atan);
-single_arg_fn_node_def!(Sinh, __Sinh, f32::sinh);
-single_arg_fn_node_def!(Cosh, __Cosh, f32::cosh);
-single_arg_fn_node_def!(Tanh, __Tanh, f32::tanh);
-single_arg_fn_node_def!(ASinh, __ASinh, f32::asinh);
-single_arg_fn_node_def!(ACosh, __ACosh, f32::acosh);
-single_arg_fn_node_def!(ATanh, __ATanh, f32::atanh);
-single_arg_fn_node_def!(Exp, __Exp, f32::exp);
-single_arg_fn_node_def!(Log, __Log, f32::ln);
-single_arg_fn_node_def!(Abs, __Abs, f32::abs);
+single_arg_fn_node_def!(Sin, __Sin, f32::sin, "sin", "sinf", false, cos);
+single_arg_fn_node_def!(Cos, __Cos, f32::cos, "cos", "cosf", false, |u| neg(sin(u)));
+single_arg_fn_node_def!(Tan, __Tan, f32::tan, "tan", "tanf", false, |u| {
+    div(num(1.0), mul(cos(u.deep_copy()), cos(u)))
+});
+single_arg_fn_node_def!(ASin, __ASin, f32::asin, "asin", "asinf", false, |u| {
+    div(num(1.0), sqrt(sub(num(1.0), mul(u.deep_copy(), u))))
+});
+single_arg_fn_node_def!(ACos, __ACos, f32::acos, "acos", "acosf", false, |u| {
+    div(num(-1.0), sqrt(sub(num(1.0), mul(u.deep_copy(), u))))
+});
+single_arg_fn_node_def!(ATan, __ATan, f32::atan, "atan", "atanf", false, |u| {
+    div(num(1.0), add(num(1.0), mul(u.deep_copy(), u)))
+});
+single_arg_fn_node_def!(Sinh, __Sinh, f32::sinh, "sinh", "sinhf", false, cosh);
+single_arg_fn_node_def!(Cosh, __Cosh, f32::cosh, "cosh", "coshf", false, sinh);
+single_arg_fn_node_def!(Tanh, __Tanh, f32::tanh, "tanh", "tanhf", false, |u| {
+    div(num(1.0), mul(cosh(u.deep_copy()), cosh(u)))
+});
+single_arg_fn_node_def!(ASinh, __ASinh, f32::asinh, "asinh", "asinhf", false, |u| {
+    div(num(1.0), sqrt(add(mul(u.deep_copy(), u), num(1.0))))
+});
+single_arg_fn_node_def!(ACosh, __ACosh, f32::acosh, "acosh", "acoshf", false, |u| {
+    div(num(1.0), sqrt(sub(mul(u.deep_copy(), u), num(1.0))))
+});
+single_arg_fn_node_def!(ATanh, __ATanh, f32::atanh, "atanh", "atanhf", false, |u| {
+    div(num(1.0), sub(num(1.0), mul(u.deep_copy(), u)))
+});
+single_arg_fn_node_def!(Exp, __Exp, f32::exp, "exp", "expf", false, exp);
+single_arg_fn_node_def!(Log, __Log, f32::ln, "ln", "logf", false, |u| div(num(1.0), u));
+single_arg_fn_node_def!(Abs, __Abs, f32::abs, "abs", "fabsf", true, |u| {
+    if_non_neg(u, num(1.0), num(-1.0))
+});
 
 pub struct __Heaviside {}
 
@@ -54,6 +102,30 @@ impl ub::UnaryOpKind for __Heaviside {
             _ => unreachable!()
         }
     }
+
+    /// zero everywhere other than at 0, where it doesn't exist
+    fn derivative(_arg: &NodeRef, _darg: NodeRef) -> NodeRef {
+        num(0.0)
+    }
+
+    fn get_code_str(lang: Lang, arg: &str) -> String {
+        codegen::select(
+            &format!("({} >= 0.0)", arg),
+            &codegen::literal(Type::Float(1.0), lang),
+            &codegen::literal(Type::Float(0.0), lang),
+            lang,
+        )
+    }
+
+    fn eval_column(input: Column) -> Column {
+        match input {
+            Column::Float(mut v) => {
+                v.iter_mut().for_each(|x| *x = if *x>=0.0 {1.0} else {0.0});
+                Column::Float(v)
+            }
+            _ => unreachable!()
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -76,6 +148,39 @@ impl ub::UnaryOpKind for __ReLu {
             _ => unreachable!()
         }
     }
+
+    /// ReLu'(x) = Heaviside(x)
+    fn derivative(arg: &NodeRef, darg: NodeRef) -> NodeRef {
+        chain(darg, arg, Heaviside::make)
+    }
+
+    /// ReLu(ReLu(x)) = ReLu(x)
+    fn simplify(arg: &NodeRef) -> Option<NodeRef> {
+        if arg.get_name() == Self::NAME {
+            Some(arg.deep_copy())
+        } else {
+            None
+        }
+    }
+
+    fn get_code_str(lang: Lang, arg: &str) -> String {
+        codegen::select(
+            &format!("({} >= 0.0)", arg),
+            arg,
+            &codegen::literal(Type::Float(0.0), lang),
+            lang,
+        )
+    }
+
+    fn eval_column(input: Column) -> Column {
+        match input {
+            Column::Float(mut v) => {
+                v.iter_mut().for_each(|x| *x = if *x>=0.0 {*x} else {0.0});
+                Column::Float(v)
+            }
+            _ => unreachable!()
+        }
+    }
 }
 
 #[allow(dead_code)]
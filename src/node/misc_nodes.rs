@@ -1,4 +1,10 @@
 use super::base::*;
+use super::bytecode::{Instr, Program};
+use super::codegen::{self, Lang};
+use super::diff;
+use super::simplify;
+use super::column::Column;
+use super::serialize as ser;
 use rand::seq::SliceRandom;
 use rand::Rng;
 
@@ -32,6 +38,16 @@ impl Cond {
             iffalse: Null::zero(rtype),
         })
     }
+    /// returns `t` if `c` is true, else `f`
+    pub fn select(c: Type, t: Type, f: Type) -> Type {
+        match (c, t, f) {
+            (Type::Bool(ci), Type::Float(ti), Type::Float(fi)) => if ci { Type::Float(ti) } else {Type::Float(fi)},
+            (Type::Bool(ci), Type::Int(ti), Type::Int(fi)) => if ci { Type::Int(ti) } else {Type::Int(fi)},
+            (Type::Bool(ci), Type::UInt(ti), Type::UInt(fi)) => if ci { Type::UInt(ti) } else {Type::UInt(fi)},
+            (Type::Bool(ci), Type::Bool(ti), Type::Bool(fi)) => if ci { Type::Bool(ti) } else {Type::Bool(fi)},
+            _ => panic!("Invalid: Cond must be bool (Got {:?}) Branches must be of same type (Got {:?} and {:?})", c, t, f)
+        }
+    }
 }
 impl Node for Cond {
     fn get_tree_str(&self, indent: usize) -> String {
@@ -50,12 +66,7 @@ impl Node for Cond {
         let c = self.cond.eval(args);
         let t = self.iftrue.eval(args);
         let f = self.iffalse.eval(args);
-        match (c, t, f) {
-            (Type::Bool(ci), Type::Float(ti), Type::Float(fi)) => if ci { Type::Float(ti) } else {Type::Float(fi)},
-            (Type::Bool(ci), Type::Int(ti), Type::Int(fi)) => if ci { Type::Int(ti) } else {Type::Int(fi)},
-            (Type::Bool(ci), Type::UInt(ti), Type::UInt(fi)) => if ci { Type::UInt(ti) } else {Type::UInt(fi)},
-            _ => panic!("Invalid: Cond must be bool (Got {:?}) Branches must be of same type (Got {:?} and {:?})", c, t, f)
-        }
+        Self::select(c, t, f)
     }
     fn get_rtype(&self) -> TypeV {
         self.rtype
@@ -104,7 +115,7 @@ impl Node for Cond {
             .build_random_node(build_table, arg_types, node_rtype, depth + 1, params);
         node.set_child(0, cond);
         node.set_child(1, iftrue);
-        node.set_child(1, iffalse);
+        node.set_child(2, iffalse);
         node
     }
     fn type_check(&self) -> Result<(), TypeErr> {
@@ -253,7 +264,19 @@ impl Node for Cond {
     }
 
     fn prune(&self) -> NodeRef {
-        Self::make(self.cond.prune(), self.iftrue.prune(), self.iffalse.prune())
+        let cond = self.cond.prune();
+        match simplify::const_val(&cond) {
+            Some(Type::Bool(true)) => self.iftrue.prune(),
+            Some(Type::Bool(false)) => self.iffalse.prune(),
+            _ => {
+                let (iftrue, iffalse) = (self.iftrue.prune(), self.iffalse.prune());
+                if simplify::same(&iftrue, &iffalse) {
+                    iftrue
+                } else {
+                    Self::make(cond, iftrue, iffalse)
+                }
+            }
+        }
     }
 
     fn get_max_depth(&self) -> usize {
@@ -263,5 +286,66 @@ impl Node for Cond {
         let branch_max_depth = usize::max(iffalse_depth, iftrue_depth);
         usize::max(cond_depth, branch_max_depth) + 1
     }
+
+    fn get_node_count(&self) -> usize {
+        self.cond.get_node_count()
+            + self.iftrue.get_node_count()
+            + self.iffalse.get_node_count()
+            + 1
+    }
+
+    fn get_float_consts(&self, consts: &mut Vec<f32>) {
+        self.cond.get_float_consts(consts);
+        self.iftrue.get_float_consts(consts);
+        self.iffalse.get_float_consts(consts);
+    }
+
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>) {
+        self.cond.set_float_consts(consts);
+        self.iftrue.set_float_consts(consts);
+        self.iffalse.set_float_consts(consts);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        ser::op_node_to_json(
+            "Cond",
+            self.rtype,
+            &self.arg_types,
+            &[&self.cond, &self.iftrue, &self.iffalse],
+        )
+    }
+
+    fn compile(&self, program: &mut Program) {
+        self.cond.compile(program);
+        self.iftrue.compile(program);
+        self.iffalse.compile(program);
+        program.push(Instr::Cond);
+    }
+
+    fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column {
+        Column::select(
+            self.cond.eval_batch(args, n_rows),
+            self.iftrue.eval_batch(args, n_rows),
+            self.iffalse.eval_batch(args, n_rows),
+        )
+    }
+
+    fn get_code_str(&self, lang: Lang) -> String {
+        codegen::select(
+            &self.cond.get_code_str(lang),
+            &self.iftrue.get_code_str(lang),
+            &self.iffalse.get_code_str(lang),
+            lang,
+        )
+    }
+
+    /// piecewise; the derivative of the branch selected by the condition
+    fn derivative(&self, var_idx: usize) -> NodeRef {
+        diff::cond(
+            self.cond.deep_copy(),
+            self.iftrue.derivative(var_idx),
+            self.iffalse.derivative(var_idx),
+        )
+    }
 }
 
@@ -1,9 +1,20 @@
 pub mod base;
 mod binary_node_base;
 pub mod btables;
+pub mod bytecode;
 pub mod cmp_nodes;
+pub mod codegen;
+pub mod column;
+pub mod custom_nodes;
+pub mod diff;
 pub mod logic_nodes;
 pub mod math_nodes;
 pub mod misc_nodes;
+mod nary_node_base;
+pub mod nary_nodes;
 pub mod op_nodes;
+pub mod parser;
+pub mod registry;
+pub mod serialize;
+pub mod simplify;
 mod unary_node_base;
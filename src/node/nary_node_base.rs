@@ -0,0 +1,414 @@
+use super::base::*;
+use super::bytecode::{Instr, Program};
+use super::codegen::Lang;
+use super::column::Column;
+use super::serialize as ser;
+use super::simplify;
+use rand::Rng;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Operations on any number of operands, like `Clamp(x, lo, hi)` or `Sum(a, b, ...)`;
+/// the operands and the result are all of the same type
+pub trait NaryOpKind {
+    const NAME: &'static str;
+
+    /// number of operands; the fewest for variadic operations
+    const ARITY: usize;
+
+    /// set if the operation takes any number of operands from `ARITY` on;
+    /// randomly built nodes have up to `BuilderParams::max_arity`, and mutation
+    /// and crossover may add or remove operands
+    const VARIADIC: bool = false;
+
+    /// checks if the operation takes operands of type `ty`;
+    /// by default it takes the numeric types
+    fn accepts(ty: TypeV) -> bool {
+        ty != TypeV::Bool
+    }
+
+    fn eval(args: &[Type]) -> Type;
+
+    /// returns the operation applied on `args`, of type `arg_type`, in the source code of `lang`
+    fn get_code_str(lang: Lang, arg_type: TypeV, args: &[String]) -> String;
+
+    /// returns the derivative of the operation on `args`, given the derivatives `dargs`
+    /// of the operands; panics if the operation can't be differentiated
+    fn derivative(_args: &[NodeRef], _dargs: Vec<NodeRef>) -> NodeRef {
+        panic!("{} cannot be differentiated!!", Self::NAME)
+    }
+
+    /// evaluates the operation over whole columns;
+    /// by default `eval` is applied on every row
+    fn eval_columns(args: &[Column]) -> Column {
+        let mut row = Vec::with_capacity(args.len());
+        Column::collect(
+            args[0].get_type(),
+            (0..args[0].len()).map(|i| {
+                row.clear();
+                row.extend(args.iter().map(|c| c.get(i)));
+                Self::eval(&row)
+            }),
+        )
+    }
+}
+
+/// NaryOpBase<KIND> is used to specialize for operation
+/// Ex: type Sum = NaryOpBase<SumEval>;
+pub struct NaryOpBase<T: NaryOpKind> {
+    pub rtype: TypeV,
+    pub arg_types: Vec<TypeV>,
+    pub args: Vec<NodeRef>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static + NaryOpKind> NaryOpBase<T> {
+    pub fn make(args: Vec<NodeRef>) -> NodeRef {
+        assert!(Self::takes(args.len()), "{} can't take {} operands", T::NAME, args.len());
+        let rtype = args[0].get_rtype();
+        assert!(args.iter().all(|a| a.get_rtype() == rtype));
+        assert!(T::accepts(rtype), "{} can't take operands of type {:?}", T::NAME, rtype);
+        Box::new(Self {
+            rtype,
+            arg_types: vec![rtype; args.len()],
+            args,
+            phantom: PhantomData,
+        })
+    }
+
+    /// the number of `arg_types` is the number of operands
+    pub fn zero(rtype: TypeV, arg_types: Vec<TypeV>) -> NodeRef {
+        let n = arg_types.len();
+        assert!(Self::takes(n), "{} can't take {} operands", T::NAME, n);
+        assert!(T::accepts(rtype), "{} can't take operands of type {:?}", T::NAME, rtype);
+        Box::new(Self {
+            rtype,
+            args: arg_types.iter().map(|ty| Null::zero(*ty)).collect(),
+            arg_types,
+            phantom: PhantomData,
+        })
+    }
+
+    /// checks if the operation takes `n` operands
+    pub fn takes(n: usize) -> bool {
+        n == T::ARITY || (T::VARIADIC && n > T::ARITY)
+    }
+
+    /// builds a random operand
+    fn build_operand<'a>(
+        &self,
+        build_table: &'a BuilderTable,
+        arg_types: &[TypeV],
+        depth: usize,
+        params: &'a mut BuilderParams,
+    ) -> NodeRef {
+        build_table
+            .get_rand_node(depth + 1, self.arg_types[0], params)
+            .build_random_node(build_table, arg_types, self.arg_types[0], depth + 1, params)
+    }
+
+    /// copy with an operand added or removed, as allowed by the arity of the operation
+    /// and `BuilderParams::max_arity`; `None` if neither is
+    fn change_arity<'a>(
+        &self,
+        arg_types: &[TypeV],
+        depth: usize,
+        build_table: &'a BuilderTable,
+        params: &'a mut BuilderParams,
+    ) -> Option<NodeRef> {
+        let n = self.args.len();
+        let can_add = n < params.max_arity;
+        let can_remove = n > T::ARITY;
+        let mut args: Vec<_> = self.args.iter().map(|a| a.deep_copy()).collect();
+        if can_add && (!can_remove || params.randomizer.gen::<f32>() < 0.5) {
+            let idx = params.randomizer.gen_range(0..=n);
+            args.insert(idx, self.build_operand(build_table, arg_types, depth, params));
+        } else if can_remove {
+            args.remove(params.randomizer.gen_range(0..n));
+        } else {
+            return None;
+        }
+        Some(Self::make(args))
+    }
+}
+
+impl<T: 'static + NaryOpKind> Node for NaryOpBase<T> {
+    fn eval(&self, args: &[Type]) -> Type {
+        let vals: Vec<_> = self.args.iter().map(|a| a.eval(args)).collect();
+        T::eval(&vals)
+    }
+
+    fn get_rtype(&self) -> TypeV {
+        self.rtype
+    }
+
+    fn get_tree_str(&self, indent: usize) -> String {
+        let args: Vec<_> = self.args.iter().map(|a| a.get_tree_str(indent + 1)).collect();
+        ".".repeat(indent) + T::NAME + "\n" + &args.join("\n")
+    }
+
+    fn get_equation_str(&self) -> String {
+        let args: Vec<_> = self.args.iter().map(|a| a.get_equation_str()).collect();
+        format!("{}({})", T::NAME, args.join(", "))
+    }
+
+    fn get_arg_types(&self) -> &[TypeV] {
+        &self.arg_types
+    }
+
+    fn set_child(&mut self, child_index: usize, child: NodeRef) {
+        self.args[child_index] = child;
+    }
+
+    fn get_child(&self, child_index: usize) -> &NodeRef {
+        &self.args[child_index]
+    }
+
+    fn get_zero_node(&self) -> NodeRef {
+        Self::zero(self.rtype, self.arg_types.clone())
+    }
+
+    fn build_random_node<'a>(
+        &self,
+        build_table: &'a BuilderTable,
+        arg_types: &[TypeV],
+        _node_rtype: TypeV,
+        depth: usize,
+        params: &'a mut BuilderParams,
+    ) -> NodeRef {
+        let n = if T::VARIADIC && params.max_arity > T::ARITY {
+            params.randomizer.gen_range(T::ARITY..=params.max_arity)
+        } else {
+            T::ARITY
+        };
+        let args = (0..n)
+            .map(|_| self.build_operand(build_table, arg_types, depth, params))
+            .collect();
+        Self::make(args)
+    }
+
+    fn deep_copy(&self) -> NodeRef {
+        Self::make(self.args.iter().map(|a| a.deep_copy()).collect())
+    }
+
+    /// besides replacing the node or mutating the operands,
+    /// variadic nodes may get an operand added or removed
+    fn mutant_copy<'a>(
+        &self,
+        probability: f32,
+        node_depth: usize,
+        arg_types: &[TypeV],
+        build_table: &'a BuilderTable,
+        params: &'a mut BuilderParams,
+    ) -> Option<NodeRef> {
+        if params.randomizer.gen::<f32>() < params.get_mut_prob(probability, node_depth) {
+            return Some(self.build_random_node(
+                build_table,
+                arg_types,
+                self.get_rtype(),
+                node_depth,
+                params,
+            ));
+        }
+        let resize = params.get_mut_prob(probability, node_depth);
+        if T::VARIADIC && params.randomizer.gen::<f32>() < resize {
+            if let Some(node) = self.change_arity(arg_types, node_depth, build_table, params) {
+                return Some(node);
+            }
+        }
+        let args =
+            mutant_children(&self.args, probability, node_depth, arg_types, build_table, params)?;
+        Some(Self::make(args))
+    }
+
+    fn type_check(&self) -> Result<(), TypeErr> {
+        let arg_types: Vec<_> = self.args.iter().map(|a| a.get_rtype()).collect();
+        if !T::accepts(self.rtype) {
+            Err(TypeErr {
+                msg: format!("{} can't take arguments of type {:?}!!", T::NAME, self.rtype),
+            })
+        } else if Self::takes(arg_types.len()) && arg_types.iter().all(|ty| *ty == self.rtype) {
+            self.args.iter().try_for_each(|a| a.type_check())
+        } else {
+            let arity = if T::VARIADIC {
+                format!("at least {}", T::ARITY)
+            } else {
+                T::ARITY.to_string()
+            };
+            Err(TypeErr {
+                msg: format!(
+                    "{} required {} arguments of type {:#?}; Got {:?}!!",
+                    T::NAME,
+                    arity,
+                    self.rtype,
+                    arg_types
+                ),
+            })
+        }
+    }
+
+    fn get_random_child(
+        &self,
+        probability: f32,
+        depth: usize,
+        params: &mut BuilderParams,
+    ) -> Option<NodeRef> {
+        if params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth) {
+            Some(self.deep_copy())
+        } else {
+            random_child(&self.args, probability, depth, params)
+        }
+    }
+
+    /// variadic nodes may take `new_node` as an additional operand,
+    /// instead of it replacing one
+    fn set_random_child(
+        &self,
+        new_node: NodeRef,
+        probability: f32,
+        depth: usize,
+        params: &mut BuilderParams,
+    ) -> Option<NodeRef> {
+        if depth + new_node.get_max_depth() > params.max_depth {
+            None
+        } else if new_node.get_rtype() == self.rtype
+            && params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth)
+        {
+            Some(new_node)
+        } else if T::VARIADIC
+            && new_node.get_rtype() == self.arg_types[0]
+            && self.args.len() < params.max_arity
+            && depth + 1 + new_node.get_max_depth() <= params.max_depth
+            && params.randomizer.gen::<f32>() < params.get_mut_prob(probability, depth)
+        {
+            let mut args: Vec<_> = self.args.iter().map(|a| a.deep_copy()).collect();
+            args.insert(params.randomizer.gen_range(0..=args.len()), new_node);
+            Some(Self::make(args))
+        } else {
+            let args = set_random_operand(&self.args, new_node, probability, depth, params)?;
+            Some(Self::make(args))
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        T::NAME
+    }
+
+    fn prune(&self) -> NodeRef {
+        let args: Vec<_> = self.args.iter().map(|a| a.prune()).collect();
+        let consts: Option<Vec<_>> = args.iter().map(simplify::const_val).collect();
+        match consts {
+            Some(vals) => Val::make(T::eval(&vals)),
+            None => Self::make(args),
+        }
+    }
+
+    fn get_max_depth(&self) -> usize {
+        self.args.iter().map(|a| a.get_max_depth()).max().unwrap_or(0) + 1
+    }
+
+    fn get_node_count(&self) -> usize {
+        self.args.iter().map(|a| a.get_node_count()).sum::<usize>() + 1
+    }
+
+    fn get_float_consts(&self, consts: &mut Vec<f32>) {
+        for a in self.args.iter() {
+            a.get_float_consts(consts);
+        }
+    }
+
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>) {
+        for a in self.args.iter_mut() {
+            a.set_float_consts(consts);
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let args: Vec<_> = self.args.iter().collect();
+        ser::op_node_to_json(T::NAME, self.rtype, &self.arg_types, &args)
+    }
+
+    fn compile(&self, program: &mut Program) {
+        for a in self.args.iter() {
+            a.compile(program);
+        }
+        program.push(Instr::Nary(Arc::new(T::eval_columns), self.args.len()));
+    }
+
+    fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column {
+        let cols: Vec<_> = self.args.iter().map(|a| a.eval_batch(args, n_rows)).collect();
+        T::eval_columns(&cols)
+    }
+
+    fn get_code_str(&self, lang: Lang) -> String {
+        let args: Vec<_> = self.args.iter().map(|a| a.get_code_str(lang)).collect();
+        T::get_code_str(lang, self.rtype, &args)
+    }
+
+    fn derivative(&self, var_idx: usize) -> NodeRef {
+        T::derivative(&self.args, self.args.iter().map(|a| a.derivative(var_idx)).collect())
+    }
+}
+
+// The helpers below work on the children of nodes that keep them in a `Vec`
+
+/// mutant copies of `children` (see `Node::mutant_copy`), the ones that weren't
+/// mutated being copied as they are; `None` if none of them was mutated
+pub fn mutant_children<'a>(
+    children: &[NodeRef],
+    probability: f32,
+    node_depth: usize,
+    arg_types: &[TypeV],
+    build_table: &'a BuilderTable,
+    params: &'a mut BuilderParams,
+) -> Option<Vec<NodeRef>> {
+    let mutants: Vec<_> = children
+        .iter()
+        .map(|c| c.mutant_copy(probability, node_depth + 1, arg_types, build_table, params))
+        .collect();
+    if mutants.iter().all(|m| m.is_none()) {
+        return None;
+    }
+    Some(
+        mutants
+            .into_iter()
+            .zip(children)
+            .map(|(m, c)| m.unwrap_or_else(|| c.deep_copy()))
+            .collect(),
+    )
+}
+
+/// random node from the subtrees of `children` (see `Node::get_random_child`),
+/// picked evenly among the ones that give one
+pub fn random_child(
+    children: &[NodeRef],
+    probability: f32,
+    depth: usize,
+    params: &mut BuilderParams,
+) -> Option<NodeRef> {
+    let mut picked: Vec<_> = children
+        .iter()
+        .filter_map(|c| c.get_random_child(probability, depth + 1, params))
+        .collect();
+    if picked.is_empty() {
+        return None;
+    }
+    let idx = params.randomizer.gen_range(0..picked.len());
+    Some(picked.swap_remove(idx))
+}
+
+/// copies of `children`, with `new_node` set into the subtree of one of them
+/// picked at random (see `Node::set_random_child`); `None` if it wasn't set
+pub fn set_random_operand(
+    children: &[NodeRef],
+    new_node: NodeRef,
+    probability: f32,
+    depth: usize,
+    params: &mut BuilderParams,
+) -> Option<Vec<NodeRef>> {
+    let idx = params.randomizer.gen_range(0..children.len());
+    let child = children[idx].set_random_child(new_node, probability, depth + 1, params)?;
+    let mut children: Vec<_> = children.iter().map(|c| c.deep_copy()).collect();
+    children[idx] = child;
+    Some(children)
+}
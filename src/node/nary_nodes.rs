@@ -0,0 +1,269 @@
+///This module defines operations taking more than two operands
+use super::base::*;
+use super::binary_node_base::BinOpKind;
+use super::cmp_nodes::{Gte, Lte};
+use super::codegen::{self, Lang};
+use super::column::Column;
+use super::diff;
+use super::nary_node_base as nb;
+use super::op_nodes::{AddEval, MulEval};
+
+fn min2(a: Type, b: Type) -> Type {
+    match (a, b) {
+        (Type::Int(a), Type::Int(b)) => Type::Int(a.min(b)),
+        (Type::Float(a), Type::Float(b)) => Type::Float(a.min(b)),
+        (Type::UInt(a), Type::UInt(b)) => Type::UInt(a.min(b)),
+        _ => unreachable!(),
+    }
+}
+
+fn max2(a: Type, b: Type) -> Type {
+    match (a, b) {
+        (Type::Int(a), Type::Int(b)) => Type::Int(a.max(b)),
+        (Type::Float(a), Type::Float(b)) => Type::Float(a.max(b)),
+        (Type::UInt(a), Type::UInt(b)) => Type::UInt(a.max(b)),
+        _ => unreachable!(),
+    }
+}
+
+/// `min(a, b)` in the source code of `lang`; NaNs are ignored, as with `f32::min`
+fn min_code(lang: Lang, ty: TypeV, a: &str, b: &str) -> String {
+    match (lang, ty) {
+        (_, TypeV::Float) => codegen::float_fn("min", "fminf", &[a, b], lang),
+        (Lang::Rust, _) => format!("{}::min({}, {})", codegen::type_name(ty, lang), a, b),
+        (Lang::C, TypeV::Int) => format!("ga_imin({}, {})", a, b),
+        (Lang::C, _) => format!("ga_umin({}, {})", a, b),
+    }
+}
+
+fn max_code(lang: Lang, ty: TypeV, a: &str, b: &str) -> String {
+    match (lang, ty) {
+        (_, TypeV::Float) => codegen::float_fn("max", "fmaxf", &[a, b], lang),
+        (Lang::Rust, _) => format!("{}::max({}, {})", codegen::type_name(ty, lang), a, b),
+        (Lang::C, TypeV::Int) => format!("ga_imax({}, {})", a, b),
+        (Lang::C, _) => format!("ga_umax({}, {})", a, b),
+    }
+}
+
+/// applies `f` on the operands from left to right
+fn fold(args: &[Type], f: fn(Type, Type) -> Type) -> Type {
+    args[1..].iter().fold(args[0], |acc, a| f(acc, *a))
+}
+
+fn fold_code(args: &[String], f: impl Fn(&str, &str) -> String) -> String {
+    args[1..].iter().fold(args[0].clone(), |acc, a| f(&acc, a))
+}
+
+fn fold_columns(args: &[Column], f: fn(Column, Column) -> Column) -> Column {
+    args[1..].iter().fold(args[0].clone(), |acc, a| f(acc, a.clone()))
+}
+
+/// derivative of the minimum or maximum of `args`; the derivative of the operand
+/// that is picked, as told by `picks(a, b)`, the condition of `a` being picked over `b`
+fn pick_derivative(
+    args: &[NodeRef],
+    mut dargs: Vec<NodeRef>,
+    make: fn(Vec<NodeRef>) -> NodeRef,
+    picks: fn(NodeRef, NodeRef) -> NodeRef,
+) -> NodeRef {
+    if args.len() == 1 {
+        return dargs.remove(0);
+    }
+    let first = dargs.remove(0);
+    let rest = if args.len() == 2 {
+        args[1].deep_copy()
+    } else {
+        make(args[1..].iter().map(|a| a.deep_copy()).collect())
+    };
+    diff::cond(
+        picks(args[0].deep_copy(), rest),
+        first,
+        pick_derivative(&args[1..], dargs, make, picks),
+    )
+}
+
+fn is_gte(a: NodeRef, b: NodeRef) -> NodeRef {
+    Gte::make(b, a)
+}
+
+fn is_lte(a: NodeRef, b: NodeRef) -> NodeRef {
+    Lte::make(b, a)
+}
+
+pub struct SumEval;
+
+impl nb::NaryOpKind for SumEval {
+    const NAME: &'static str = "Sum";
+    const ARITY: usize = 2;
+    const VARIADIC: bool = true;
+
+    fn eval(args: &[Type]) -> Type {
+        fold(args, AddEval::eval)
+    }
+
+    fn get_code_str(lang: Lang, arg_type: TypeV, args: &[String]) -> String {
+        fold_code(args, |a, b| AddEval::get_code_str(lang, arg_type, a, b))
+    }
+
+    fn eval_columns(args: &[Column]) -> Column {
+        fold_columns(args, AddEval::eval_columns)
+    }
+
+    fn derivative(_args: &[NodeRef], dargs: Vec<NodeRef>) -> NodeRef {
+        dargs.into_iter().reduce(diff::add).unwrap()
+    }
+}
+
+/// Sum of two or more operands of the same numeric type
+pub type Sum = nb::NaryOpBase<SumEval>;
+
+pub struct ProductEval;
+
+impl nb::NaryOpKind for ProductEval {
+    const NAME: &'static str = "Product";
+    const ARITY: usize = 2;
+    const VARIADIC: bool = true;
+
+    fn eval(args: &[Type]) -> Type {
+        fold(args, MulEval::eval)
+    }
+
+    fn get_code_str(lang: Lang, arg_type: TypeV, args: &[String]) -> String {
+        fold_code(args, |a, b| MulEval::get_code_str(lang, arg_type, a, b))
+    }
+
+    fn eval_columns(args: &[Column]) -> Column {
+        fold_columns(args, MulEval::eval_columns)
+    }
+
+    /// the derivative of each operand times the rest of the operands, summed up
+    fn derivative(args: &[NodeRef], dargs: Vec<NodeRef>) -> NodeRef {
+        dargs
+            .into_iter()
+            .enumerate()
+            .map(|(i, darg)| {
+                args.iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .fold(darg, |acc, (_, a)| diff::mul(acc, a.deep_copy()))
+            })
+            .reduce(diff::add)
+            .unwrap()
+    }
+}
+
+/// Product of two or more operands of the same numeric type
+pub type Product = nb::NaryOpBase<ProductEval>;
+
+pub struct MinEval;
+
+impl nb::NaryOpKind for MinEval {
+    const NAME: &'static str = "Min";
+    const ARITY: usize = 2;
+    const VARIADIC: bool = true;
+
+    fn eval(args: &[Type]) -> Type {
+        fold(args, min2)
+    }
+
+    fn get_code_str(lang: Lang, arg_type: TypeV, args: &[String]) -> String {
+        fold_code(args, |a, b| min_code(lang, arg_type, a, b))
+    }
+
+    fn derivative(args: &[NodeRef], dargs: Vec<NodeRef>) -> NodeRef {
+        pick_derivative(args, dargs, Min::make, is_lte)
+    }
+}
+
+/// Least of two or more operands of the same numeric type; NaNs are ignored
+pub type Min = nb::NaryOpBase<MinEval>;
+
+pub struct MaxEval;
+
+impl nb::NaryOpKind for MaxEval {
+    const NAME: &'static str = "Max";
+    const ARITY: usize = 2;
+    const VARIADIC: bool = true;
+
+    fn eval(args: &[Type]) -> Type {
+        fold(args, max2)
+    }
+
+    fn get_code_str(lang: Lang, arg_type: TypeV, args: &[String]) -> String {
+        fold_code(args, |a, b| max_code(lang, arg_type, a, b))
+    }
+
+    fn derivative(args: &[NodeRef], dargs: Vec<NodeRef>) -> NodeRef {
+        pick_derivative(args, dargs, Max::make, is_gte)
+    }
+}
+
+/// Greatest of two or more operands of the same numeric type; NaNs are ignored
+pub type Max = nb::NaryOpBase<MaxEval>;
+
+pub struct ClampEval;
+
+impl nb::NaryOpKind for ClampEval {
+    const NAME: &'static str = "Clamp";
+    const ARITY: usize = 3;
+
+    /// `min(max(x, lo), hi)`; unlike `f32::clamp`, doesn't panic if `lo > hi`
+    fn eval(args: &[Type]) -> Type {
+        min2(max2(args[0], args[1]), args[2])
+    }
+
+    fn get_code_str(lang: Lang, arg_type: TypeV, args: &[String]) -> String {
+        let lower = max_code(lang, arg_type, &args[0], &args[1]);
+        min_code(lang, arg_type, &lower, &args[2])
+    }
+
+    fn derivative(args: &[NodeRef], mut dargs: Vec<NodeRef>) -> NodeRef {
+        let dhi = dargs.pop().unwrap();
+        let lower = Max::make(vec![args[0].deep_copy(), args[1].deep_copy()]);
+        let dlower = pick_derivative(&args[..2], dargs, Max::make, is_gte);
+        pick_derivative(&[lower, args[2].deep_copy()], vec![dlower, dhi], Min::make, is_lte)
+    }
+}
+
+/// `Clamp(x, lo, hi)`; `x` limited to the range between `lo` and `hi`
+pub type Clamp = nb::NaryOpBase<ClampEval>;
+
+pub struct LerpEval;
+
+impl nb::NaryOpKind for LerpEval {
+    const NAME: &'static str = "Lerp";
+    const ARITY: usize = 3;
+
+    fn accepts(ty: TypeV) -> bool {
+        ty == TypeV::Float
+    }
+
+    fn eval(args: &[Type]) -> Type {
+        match args {
+            [Type::Float(a), Type::Float(b), Type::Float(t)] => Type::Float(a + (b - a) * t),
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, args: &[String]) -> String {
+        format!("({0} + (({1} - {0}) * {2}))", args[0], args[1], args[2])
+    }
+
+    /// (a + (b - a) * t)' = a' + (b' - a') * t + (b - a) * t'
+    fn derivative(args: &[NodeRef], dargs: Vec<NodeRef>) -> NodeRef {
+        let (a, b, t) = (&args[0], &args[1], &args[2]);
+        let mut dargs = dargs.into_iter();
+        let (da, db, dt) = (
+            dargs.next().unwrap(),
+            dargs.next().unwrap(),
+            dargs.next().unwrap(),
+        );
+        diff::add(
+            diff::add(da.deep_copy(), diff::mul(diff::sub(db, da), t.deep_copy())),
+            diff::mul(diff::sub(b.deep_copy(), a.deep_copy()), dt),
+        )
+    }
+}
+
+/// `Lerp(a, b, t)`; linear interpolation from `a` to `b` by `t`, of floats
+pub type Lerp = nb::NaryOpBase<LerpEval>;
@@ -1,12 +1,70 @@
 ///This module defines basic arithmatic operators
 use super::base::*;
 use super::binary_node_base as bb;
+use super::codegen::{self, Lang};
+use super::column::Column;
+use super::diff;
+use super::simplify::*;
+
+/// applies `$expr` elementwise on two columns of the same numeric type,
+/// writing the result into the lhs column
+macro_rules! zip_columns {
+    ($lhs: expr, $rhs: expr, |$l: ident, $r: ident| $expr: expr) => {
+        match ($lhs, $rhs) {
+            (Column::Int(mut lv), Column::Int(rv)) => {
+                lv.iter_mut().zip(rv).for_each(|(x, $r)| {
+                    let $l = *x;
+                    *x = $expr
+                });
+                Column::Int(lv)
+            }
+            (Column::Float(mut lv), Column::Float(rv)) => {
+                lv.iter_mut().zip(rv).for_each(|(x, $r)| {
+                    let $l = *x;
+                    *x = $expr
+                });
+                Column::Float(lv)
+            }
+            (Column::UInt(mut lv), Column::UInt(rv)) => {
+                lv.iter_mut().zip(rv).for_each(|(x, $r)| {
+                    let $l = *x;
+                    *x = $expr
+                });
+                Column::UInt(lv)
+            }
+            _ => unreachable!(),
+        }
+    };
+}
 
 pub struct AddEval;
 
 impl bb::BinOpKind for AddEval {
     const NAME: &'static str = "+";
 
+    const COMMUTATIVE: bool = true;
+
+    /// x + 0 = x; constants are on the right after reordering
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if is_num(rhs, 0) {
+            Some(lhs.deep_copy())
+        } else {
+            None
+        }
+    }
+
+    fn derivative(_lhs: &NodeRef, _rhs: &NodeRef, dlhs: NodeRef, drhs: NodeRef) -> NodeRef {
+        diff::add(dlhs, drhs)
+    }
+
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} + {})", lhs, rhs)
+    }
+
+    fn eval_columns(lhs: Column, rhs: Column) -> Column {
+        zip_columns!(lhs, rhs, |l, r| l + r)
+    }
+
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Int(ri), Type::Int(li)) => Type::Int(ri + li),
@@ -25,6 +83,29 @@ pub struct SubEval;
 impl bb::BinOpKind for SubEval {
     const NAME: &'static str = "-";
 
+    /// x - 0 = x; x - x = 0
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if is_num(rhs, 0) {
+            Some(lhs.deep_copy())
+        } else if same(lhs, rhs) && cancels(lhs) {
+            Some(num(lhs.get_rtype(), 0))
+        } else {
+            None
+        }
+    }
+
+    fn derivative(_lhs: &NodeRef, _rhs: &NodeRef, dlhs: NodeRef, drhs: NodeRef) -> NodeRef {
+        diff::sub(dlhs, drhs)
+    }
+
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} - {})", lhs, rhs)
+    }
+
+    fn eval_columns(lhs: Column, rhs: Column) -> Column {
+        zip_columns!(lhs, rhs, |l, r| l - r)
+    }
+
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Int(ri), Type::Int(li)) => Type::Int(li - ri),
@@ -43,6 +124,36 @@ pub struct MulEval;
 impl bb::BinOpKind for MulEval {
     const NAME: &'static str = "*";
 
+    const COMMUTATIVE: bool = true;
+
+    /// x * 1 = x; x * 0 = 0 for integers (not for floats, as inf * 0 is NaN);
+    /// constants are on the right after reordering
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if is_num(rhs, 1) {
+            Some(lhs.deep_copy())
+        } else if is_num(rhs, 0) && rhs.get_rtype() != TypeV::Float {
+            Some(num(rhs.get_rtype(), 0))
+        } else {
+            None
+        }
+    }
+
+    /// (u * v)' = u' * v + u * v'
+    fn derivative(lhs: &NodeRef, rhs: &NodeRef, dlhs: NodeRef, drhs: NodeRef) -> NodeRef {
+        diff::add(
+            diff::mul(dlhs, rhs.deep_copy()),
+            diff::mul(lhs.deep_copy(), drhs),
+        )
+    }
+
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} * {})", lhs, rhs)
+    }
+
+    fn eval_columns(lhs: Column, rhs: Column) -> Column {
+        zip_columns!(lhs, rhs, |l, r| l * r)
+    }
+
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Int(ri), Type::Int(li)) => Type::Int(li * ri),
@@ -61,6 +172,36 @@ pub struct DivEval;
 impl bb::BinOpKind for DivEval {
     const NAME: &'static str = "/";
 
+    /// x / 1 = x; x / x = 1
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if is_num(rhs, 1) {
+            Some(lhs.deep_copy())
+        } else if same(lhs, rhs) && cancels(lhs) {
+            Some(num(lhs.get_rtype(), 1))
+        } else {
+            None
+        }
+    }
+
+    /// (u / v)' = u' / v - u * v' / (v * v)
+    fn derivative(lhs: &NodeRef, rhs: &NodeRef, dlhs: NodeRef, drhs: NodeRef) -> NodeRef {
+        diff::sub(
+            diff::div(dlhs, rhs.deep_copy()),
+            diff::div(
+                diff::mul(lhs.deep_copy(), drhs),
+                diff::mul(rhs.deep_copy(), rhs.deep_copy()),
+            ),
+        )
+    }
+
+    fn get_code_str(_lang: Lang, _arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        format!("({} / {})", lhs, rhs)
+    }
+
+    fn eval_columns(lhs: Column, rhs: Column) -> Column {
+        zip_columns!(lhs, rhs, |l, r| l / r)
+    }
+
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Int(ri), Type::Int(li)) => Type::Int(li / ri),
@@ -79,6 +220,41 @@ pub struct PowEval;
 impl bb::BinOpKind for PowEval {
     const NAME: &'static str = "**";
 
+    fn accepts(ty: TypeV) -> bool {
+        ty == TypeV::Float || ty == TypeV::UInt
+    }
+
+    /// x ** 1 = x; x ** 0 = 1
+    fn simplify(lhs: &NodeRef, rhs: &NodeRef) -> Option<NodeRef> {
+        if is_num(rhs, 1) {
+            Some(lhs.deep_copy())
+        } else if is_num(rhs, 0) {
+            Some(num(lhs.get_rtype(), 1))
+        } else {
+            None
+        }
+    }
+
+    /// (u ** v)' = v * u ** (v - 1) * u' + u ** v * ln(u) * v'
+    fn derivative(lhs: &NodeRef, rhs: &NodeRef, dlhs: NodeRef, drhs: NodeRef) -> NodeRef {
+        let (u, v) = (|| lhs.deep_copy(), || rhs.deep_copy());
+        diff::add(
+            diff::mul(
+                diff::mul(v(), diff::pow(u(), diff::sub(v(), diff::num(1.0)))),
+                dlhs,
+            ),
+            diff::mul(diff::mul(diff::pow(u(), v()), diff::ln(u())), drhs),
+        )
+    }
+
+    fn get_code_str(lang: Lang, arg_type: TypeV, lhs: &str, rhs: &str) -> String {
+        match (lang, arg_type) {
+            (Lang::Rust, TypeV::UInt) => format!("u32::pow({}, {})", lhs, rhs),
+            (Lang::C, TypeV::UInt) => format!("ga_upow({}, {})", lhs, rhs),
+            _ => codegen::float_fn("powf", "powf", &[lhs, rhs], lang),
+        }
+    }
+
     fn eval(lhs: Type, rhs: Type) -> Type {
         match (rhs, lhs) {
             (Type::Float(ri), Type::Float(li)) => Type::Float(li.powf(ri)),
@@ -0,0 +1,264 @@
+#![allow(dead_code)]
+//! Parser for the textual form of expression trees, as returned by `Node::get_equation_str`.
+//!
+//! ```text
+//! expr := '(' expr op expr ')'            binary nodes; op is a registered binary name
+//!       | '(' expr '?' expr ':' expr ')'  Cond
+//!       | name '(' expr {',' expr} ')'    unary and other registered nodes
+//!       | 'x' '[' index ']'               Var
+//!       | number ('F' | 'I' | 'U')        Val
+//!       | 'true' | 'false'                Val
+//! ```
+use super::base::*;
+use super::registry::NodeRegistry;
+
+/// Error on malformed input; `pos` is the character offset in the input where parsing failed
+#[derive(Debug)]
+pub struct ParseErr {
+    pub msg: String,
+    pub pos: usize,
+}
+
+/// Parses `input` into a type checked node tree.
+/// `arg_types` are the argument types of the expression, used for typing `x[i]`
+pub fn parse_equation(
+    input: &str,
+    arg_types: &[TypeV],
+    registry: &NodeRegistry,
+) -> Result<NodeRef, ParseErr> {
+    let mut parser = Parser {
+        src: input,
+        pos: 0,
+        arg_types,
+        registry,
+    };
+    let node = parser.parse_expr()?;
+    parser.skip_ws();
+    if parser.pos < input.len() {
+        return Err(parser.err("Unexpected trailing input".to_string()));
+    }
+    Ok(node)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    /// byte offset into `src`
+    pos: usize,
+    arg_types: &'a [TypeV],
+    registry: &'a NodeRegistry,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn err_at(&self, pos: usize, msg: String) -> ParseErr {
+        ParseErr {
+            msg,
+            pos: self.src[..pos].chars().count(),
+        }
+    }
+
+    fn err(&self, msg: String) -> ParseErr {
+        self.err_at(self.pos, msg)
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseErr> {
+        self.skip_ws();
+        match self.peek() {
+            Some(p) if p == c => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(p) => Err(self.err(format!("Expected `{}`; Got `{}`", c, p))),
+            None => Err(self.err(format!("Expected `{}`; Got end of input", c))),
+        }
+    }
+
+    /// longest registered name with given arity that the remaining input starts with
+    fn match_name(&self, arity: usize) -> Option<&'a str> {
+        let rest = self.rest();
+        self.registry
+            .names()
+            .filter(|name| {
+                self.registry.get_arity(name) == Some(arity) && !self.registry.is_variadic(name)
+            })
+            .filter(|name| rest.starts_with(*name))
+            .max_by_key(|name| name.len())
+            .map(|name| &rest[..name.len()])
+    }
+
+    /// builds and type checks the node at `pos`; its children are already checked,
+    /// so a failure is reported at the node itself
+    fn build(&self, pos: usize, name: &str, children: Vec<NodeRef>) -> Result<NodeRef, ParseErr> {
+        let node = self
+            .registry
+            .build(name, children)
+            .map_err(|e| self.err_at(pos, e.msg))?;
+        node.type_check().map_err(|e| self.err_at(pos, e.msg))?;
+        Ok(node)
+    }
+
+    fn parse_expr(&mut self) -> Result<NodeRef, ParseErr> {
+        self.skip_ws();
+        let start = self.pos;
+        match self.peek() {
+            None => Err(self.err("Unexpected end of input".to_string())),
+            Some('(') => {
+                self.pos += 1;
+                let first = self.parse_expr()?;
+                self.skip_ws();
+                if self.peek() == Some('?') {
+                    self.pos += 1;
+                    let iftrue = self.parse_expr()?;
+                    self.expect(':')?;
+                    let iffalse = self.parse_expr()?;
+                    self.expect(')')?;
+                    self.build(start, "Cond", vec![first, iftrue, iffalse])
+                } else {
+                    let op_pos = self.pos;
+                    let op = self.match_name(2).ok_or_else(|| {
+                        self.err("Expected a binary operator or `?`".to_string())
+                    })?;
+                    self.pos += op.len();
+                    let second = self.parse_expr()?;
+                    self.expect(')')?;
+                    self.build(op_pos, op, vec![first, second])
+                }
+            }
+            Some(_) => {
+                if let Some(node) = self.parse_leaf()? {
+                    return Ok(node);
+                }
+                let name = self.parse_name()?;
+                self.expect('(')?;
+                let mut children = vec![self.parse_expr()?];
+                self.skip_ws();
+                while self.peek() == Some(',') {
+                    self.pos += 1;
+                    children.push(self.parse_expr()?);
+                    self.skip_ws();
+                }
+                self.expect(')')?;
+                self.build(start, name, children)
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<&'a str, ParseErr> {
+        let rest = self.rest();
+        let ident_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let name = if ident_len > 0 {
+            &rest[..ident_len]
+        } else {
+            self.match_name(1)
+                .ok_or_else(|| self.err("Expected an expression".to_string()))?
+        };
+        if !self.registry.contains(name) {
+            return Err(self.err(format!("Unknown node `{}`", name)));
+        }
+        self.pos += name.len();
+        Ok(name)
+    }
+
+    /// parses `Var` and `Val` nodes; returns `None` if the input is not a leaf
+    fn parse_leaf(&mut self) -> Result<Option<NodeRef>, ParseErr> {
+        let rest = self.rest();
+        let is_word_end = |len: usize| {
+            !rest[len..]
+                .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '(')
+        };
+        if rest.starts_with("x[") {
+            let start = self.pos;
+            self.pos += 2;
+            let rest = self.rest();
+            let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let idx: usize = rest[..len]
+                .parse()
+                .map_err(|_| self.err("Expected argument index".to_string()))?;
+            self.pos += len;
+            self.expect(']')?;
+            return match self.arg_types.get(idx) {
+                Some(ty) => Ok(Some(Var::make(idx, *ty))),
+                None => Err(self.err_at(
+                    start,
+                    format!("x[{}] is out of range for {} arguments", idx, self.arg_types.len()),
+                )),
+            };
+        }
+        for (word, val) in &[("true", true), ("false", false)] {
+            if rest.starts_with(word) && is_word_end(word.len()) {
+                self.pos += word.len();
+                return Ok(Some(Val::make(Type::Bool(*val))));
+            }
+        }
+        let num_len = number_len(rest);
+        if num_len == 0 {
+            return Ok(None);
+        }
+        let num = &rest[..num_len];
+        let suffix = rest[num_len..].chars().next();
+        let val = match suffix {
+            Some('F') => num.parse().ok().map(Type::Float),
+            Some('I') => num.parse().ok().map(Type::Int),
+            Some('U') => num.parse().ok().map(Type::UInt),
+            _ => {
+                return Err(self.err_at(
+                    self.pos + num_len,
+                    format!("Expected type suffix (`F`, `I` or `U`) after `{}`", num),
+                ))
+            }
+        };
+        let val = val.ok_or_else(|| {
+            self.err(format!("Invalid value `{}` for suffix `{}`", num, suffix.unwrap()))
+        })?;
+        self.pos += num_len + 1;
+        Ok(Some(Val::make(val)))
+    }
+}
+
+/// length of the numeric literal (without type suffix) at the start of `s`; 0 if none
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    if i < bytes.len() && (bytes[i] == b'-' || bytes[i] == b'+') {
+        i += 1;
+    }
+    for special in &["NaN", "inf"] {
+        if s[i..].starts_with(special) && s[i + special.len()..].starts_with('F') {
+            return i + special.len();
+        }
+    }
+    let digits_start = i;
+    while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+        i += 1;
+    }
+    if i == digits_start {
+        return 0;
+    }
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        let mut j = i + 1;
+        if j < bytes.len() && (bytes[j] == b'-' || bytes[j] == b'+') {
+            j += 1;
+        }
+        if j < bytes.len() && bytes[j].is_ascii_digit() {
+            while j < bytes.len() && bytes[j].is_ascii_digit() {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+    i
+}
@@ -0,0 +1,219 @@
+#![allow(dead_code)]
+use super::base::*;
+use super::binary_node_base as bb;
+use super::cmp_nodes as cmp;
+use super::logic_nodes as logic;
+use super::math_nodes as math;
+use super::misc_nodes as misc;
+use super::nary_node_base as nb;
+use super::nary_nodes as nary;
+use super::op_nodes as ops;
+use super::unary_node_base as ub;
+use std::collections::HashMap;
+
+/// Builds the "zero" node of a registered kind, given the return types
+/// of the children it is going to be given
+pub type ZeroFactory = Box<dyn Fn(&[TypeV]) -> NodeRef>;
+
+struct RegistryEntry {
+    arity: usize,
+    /// takes `arity` or more children
+    variadic: bool,
+    zero: ZeroFactory,
+}
+
+/// Registry of node kinds keyed on their name (`Node::get_name()`),
+/// used for rebuilding trees from their stored or textual form.
+/// `Val` and `Var` are leaves carrying data, so they are not registered here
+pub struct NodeRegistry {
+    entries: HashMap<String, RegistryEntry>,
+}
+
+impl NodeRegistry {
+    /// returns a registry with all the predefined nodes registered
+    pub fn new() -> NodeRegistry {
+        let mut registry = NodeRegistry::empty();
+        registry.register_unary::<math::__Sin>();
+        registry.register_unary::<math::__Cos>();
+        registry.register_unary::<math::__Tan>();
+        registry.register_unary::<math::__ASin>();
+        registry.register_unary::<math::__ACos>();
+        registry.register_unary::<math::__ATan>();
+        registry.register_unary::<math::__Sinh>();
+        registry.register_unary::<math::__Cosh>();
+        registry.register_unary::<math::__Tanh>();
+        registry.register_unary::<math::__ASinh>();
+        registry.register_unary::<math::__ACosh>();
+        registry.register_unary::<math::__ATanh>();
+        registry.register_unary::<math::__Exp>();
+        registry.register_unary::<math::__Log>();
+        registry.register_unary::<math::__Abs>();
+        registry.register_unary::<math::__Heaviside>();
+        registry.register_unary::<math::__ReLu>();
+        registry.register_unary::<logic::NotEval>();
+        registry.register_binary::<ops::AddEval>();
+        registry.register_binary::<ops::SubEval>();
+        registry.register_binary::<ops::MulEval>();
+        registry.register_binary::<ops::DivEval>();
+        registry.register_binary::<ops::PowEval>();
+        registry.register_binary::<cmp::EqEval>();
+        registry.register_binary::<cmp::NEqEval>();
+        registry.register_binary::<cmp::GtEval>();
+        registry.register_binary::<cmp::GteEval>();
+        registry.register_binary::<cmp::LtEval>();
+        registry.register_binary::<cmp::LteEval>();
+        registry.register_binary::<logic::AndEval>();
+        registry.register_binary::<logic::OrEval>();
+        registry.register_nary::<nary::ClampEval>();
+        registry.register_nary::<nary::LerpEval>();
+        registry.register_nary::<nary::SumEval>();
+        registry.register_nary::<nary::ProductEval>();
+        registry.register_nary::<nary::MinEval>();
+        registry.register_nary::<nary::MaxEval>();
+        registry.register(
+            "Cond",
+            3,
+            Box::new(|types| {
+                misc::Cond::zero(types[1], vec![TypeV::Bool, types[1], types[1]])
+            }),
+        );
+        registry
+    }
+
+    /// returns a registry with no nodes registered
+    pub fn empty() -> NodeRegistry {
+        NodeRegistry {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// registers a node kind with `arity` children under `name`;
+    /// an existing entry with the same name is replaced
+    pub fn register(&mut self, name: &str, arity: usize, zero: ZeroFactory) {
+        self.insert(name, arity, false, zero);
+    }
+
+    /// registers a node kind taking `min_arity` or more children under `name`
+    pub fn register_variadic(&mut self, name: &str, min_arity: usize, zero: ZeroFactory) {
+        self.insert(name, min_arity, true, zero);
+    }
+
+    fn insert(&mut self, name: &str, arity: usize, variadic: bool, zero: ZeroFactory) {
+        self.entries.insert(
+            name.to_string(),
+            RegistryEntry {
+                arity,
+                variadic,
+                zero,
+            },
+        );
+    }
+
+    pub fn register_unary<T: 'static + ub::UnaryOpKind>(&mut self) {
+        self.register(T::NAME, 1, Box::new(|_| ub::UnaryOpBase::<T>::zero()));
+    }
+
+    /// operands of a type the operation doesn't take get a zero node of a type it does take,
+    /// so that `build` reports the mismatch
+    pub fn register_binary<T: 'static + bb::BinOpKind>(&mut self) {
+        self.register(
+            T::NAME,
+            2,
+            Box::new(|types| {
+                let ty = [types[0], TypeV::Float, TypeV::Int, TypeV::UInt, TypeV::Bool]
+                    .iter()
+                    .copied()
+                    .find(|ty| T::accepts(*ty))
+                    .unwrap_or_else(|| panic!("{} takes no type of operands", T::NAME));
+                bb::BinOpBase::<T>::zero(T::rtype(ty), vec![ty, ty])
+            }),
+        );
+    }
+
+    /// operands of a type the operation doesn't take get a zero node of a type it does take,
+    /// so that `build` reports the mismatch
+    pub fn register_nary<T: 'static + nb::NaryOpKind>(&mut self) {
+        let zero: ZeroFactory = Box::new(|types| {
+            let ty = [types[0], TypeV::Float, TypeV::Int, TypeV::UInt, TypeV::Bool]
+                .iter()
+                .copied()
+                .find(|ty| T::accepts(*ty))
+                .unwrap_or_else(|| panic!("{} takes no type of operands", T::NAME));
+            nb::NaryOpBase::<T>::zero(ty, vec![ty; types.len()])
+        });
+        if T::VARIADIC {
+            self.register_variadic(T::NAME, T::ARITY, zero);
+        } else {
+            self.register(T::NAME, T::ARITY, zero);
+        }
+    }
+
+    /// registers the kind of `node` under its name, with as many children as
+    /// it takes arguments; rebuilt nodes are copies of its zero node.
+    /// Used for nodes made at runtime, like the ones of `custom_nodes`
+    pub fn register_node(&mut self, node: NodeRef) {
+        let arity = node.get_arg_types().len();
+        let name = node.get_name().to_string();
+        self.register(&name, arity, Box::new(move |_| node.get_zero_node()));
+    }
+
+    /// names of all the registered nodes
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.keys().map(|k| k.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// returns number of children the node registered under `name` takes;
+    /// the fewest for variadic nodes
+    pub fn get_arity(&self, name: &str) -> Option<usize> {
+        self.entries.get(name).map(|e| e.arity)
+    }
+
+    pub fn is_variadic(&self, name: &str) -> bool {
+        self.entries.get(name).is_some_and(|e| e.variadic)
+    }
+
+    /// builds the node registered under `name` with the given children;
+    /// the types of the children are checked against the argument types of the node
+    pub fn build(&self, name: &str, children: Vec<NodeRef>) -> Result<NodeRef, TypeErr> {
+        let entry = self.entries.get(name).ok_or_else(|| TypeErr {
+            msg: format!("Unknown node `{}`!!", name),
+        })?;
+        if children.len() < entry.arity || (children.len() > entry.arity && !entry.variadic) {
+            return Err(TypeErr {
+                msg: format!(
+                    "{} requires {}{} arguments; Got {}!!",
+                    name,
+                    if entry.variadic { "at least " } else { "" },
+                    entry.arity,
+                    children.len()
+                ),
+            });
+        }
+        let child_types: Vec<_> = children.iter().map(|c| c.get_rtype()).collect();
+        let mut node = (entry.zero)(&child_types);
+        if node.get_arg_types() != child_types.as_slice() {
+            return Err(TypeErr {
+                msg: format!(
+                    "{} required argument of type {:?}; Got {:?}!!",
+                    name,
+                    node.get_arg_types(),
+                    child_types
+                ),
+            });
+        }
+        for (i, child) in children.into_iter().enumerate() {
+            node.set_child(i, child);
+        }
+        Ok(node)
+    }
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,253 @@
+#![allow(dead_code)]
+//! Helpers for storing expression trees as JSON.
+//!
+//! A node is stored as an object with its `kind` (the name returned by `Node::get_name()`),
+//! its return type and, depending on the kind, either its value (`Val`),
+//! its argument index (`Var`) or its argument types and children (everything else)
+use super::base::*;
+use super::registry::NodeRegistry;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde_json::{json, Value};
+use std::convert::TryFrom;
+
+/// version of the stored format; bumped on incompatible changes
+pub const FORMAT_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub struct SerdeErr {
+    pub msg: String,
+}
+
+impl SerdeErr {
+    pub fn new(msg: String) -> SerdeErr {
+        SerdeErr { msg }
+    }
+}
+
+impl From<TypeErr> for SerdeErr {
+    fn from(e: TypeErr) -> SerdeErr {
+        SerdeErr { msg: e.msg }
+    }
+}
+
+pub fn typev_to_json(ty: TypeV) -> Value {
+    json!(format!("{:?}", ty))
+}
+
+pub fn typev_from_json(val: &Value) -> Result<TypeV, SerdeErr> {
+    match val.as_str() {
+        Some("Int") => Ok(TypeV::Int),
+        Some("Float") => Ok(TypeV::Float),
+        Some("UInt") => Ok(TypeV::UInt),
+        Some("Bool") => Ok(TypeV::Bool),
+        _ => Err(SerdeErr::new(format!("Invalid type {}", val))),
+    }
+}
+
+pub fn typev_list_to_json(types: &[TypeV]) -> Value {
+    Value::Array(types.iter().map(|t| typev_to_json(*t)).collect())
+}
+
+pub fn typev_list_from_json(val: &Value) -> Result<Vec<TypeV>, SerdeErr> {
+    val.as_array()
+        .ok_or_else(|| SerdeErr::new(format!("Expected list of types; Got {}", val)))?
+        .iter()
+        .map(typev_from_json)
+        .collect()
+}
+
+/// Stores the value without its type; the type is stored alongside by the caller.
+/// Non-finite floats are not valid JSON numbers, so they are stored as strings
+pub fn type_to_json(val: Type) -> Value {
+    match val {
+        Type::Int(i) => json!(i),
+        Type::Float(f) if f.is_finite() => json!(f),
+        Type::Float(f) => json!(f.to_string()),
+        Type::UInt(u) => json!(u),
+        Type::Bool(b) => json!(b),
+    }
+}
+
+pub fn type_from_json(ty: TypeV, val: &Value) -> Result<Type, SerdeErr> {
+    let err = || SerdeErr::new(format!("Invalid value {} for type {:?}", val, ty));
+    match ty {
+        TypeV::Int => val
+            .as_i64()
+            .and_then(|i| i32::try_from(i).ok())
+            .map(Type::Int)
+            .ok_or_else(err),
+        TypeV::UInt => val
+            .as_u64()
+            .and_then(|u| u32::try_from(u).ok())
+            .map(Type::UInt)
+            .ok_or_else(err),
+        TypeV::Bool => val.as_bool().map(Type::Bool).ok_or_else(err),
+        TypeV::Float => match val {
+            Value::Number(n) => n.as_f64().map(|f| Type::Float(f as f32)).ok_or_else(err),
+            Value::String(s) => s.parse::<f32>().map(Type::Float).map_err(|_| err()),
+            _ => Err(err()),
+        },
+    }
+}
+
+/// JSON form of the building parameters, with the exact state of the randomizer,
+/// so that the random draws continue where they left off
+pub fn params_to_json(params: &BuilderParams) -> Value {
+    let rng = &params.randomizer;
+    json!({
+        "max_depth": params.max_depth,
+        "min_depth": params.min_depth,
+        "termination_probability": type_to_json(Type::Float(params.termination_probability)),
+        "float_range": [
+            type_to_json(Type::Float(params.float_range.0)),
+            type_to_json(Type::Float(params.float_range.1)),
+        ],
+        "int_range": [params.int_range.0, params.int_range.1],
+        "uint_range": [params.uint_range.0, params.uint_range.1],
+        "max_arity": params.max_arity,
+        "randomizer": {
+            "seed": rng.get_seed().to_vec(),
+            "stream": rng.get_stream(),
+            // u128 doesn't fit in a JSON number
+            "word_pos": rng.get_word_pos().to_string(),
+        },
+    })
+}
+
+/// reads the parameters written by `params_to_json`; a field added since version 1
+/// of the format gets the default of `BuilderParams::new` if it's missing
+pub fn params_from_json(val: &Value) -> Result<BuilderParams, SerdeErr> {
+    let invalid = |key: &str| SerdeErr::new(format!("Invalid field `{}`", key));
+    fn field<'v>(val: &'v Value, key: &str) -> Result<&'v Value, SerdeErr> {
+        val.get(key)
+            .ok_or_else(|| SerdeErr::new(format!("Missing field `{}`", key)))
+    }
+    let count_or = |key: &str, default: usize| match val.get(key) {
+        Some(v) => v.as_u64().map(|n| n as usize).ok_or_else(|| invalid(key)),
+        None => Ok(default),
+    };
+    // ranges are stored as a pair of values
+    let range = |key: &str, ty: TypeV| -> Result<(Type, Type), SerdeErr> {
+        match field(val, key)?.as_array().map(|v| v.as_slice()) {
+            Some([a, b]) => Ok((type_from_json(ty, a)?, type_from_json(ty, b)?)),
+            _ => Err(invalid(key)),
+        }
+    };
+    let rng = field(val, "randomizer")?;
+    let seed: Vec<u8> = field(rng, "seed")?
+        .as_array()
+        .ok_or_else(|| invalid("seed"))?
+        .iter()
+        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+        .collect::<Option<_>>()
+        .ok_or_else(|| invalid("seed"))?;
+    let seed = <[u8; 32]>::try_from(seed).map_err(|_| invalid("seed"))?;
+    let mut randomizer = ChaCha8Rng::from_seed(seed);
+    randomizer.set_stream(field(rng, "stream")?.as_u64().ok_or_else(|| invalid("stream"))?);
+    randomizer.set_word_pos(
+        field(rng, "word_pos")?
+            .as_str()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("word_pos"))?,
+    );
+    let termination_probability =
+        match type_from_json(TypeV::Float, field(val, "termination_probability")?)? {
+            Type::Float(f) => f,
+            _ => unreachable!(),
+        };
+    Ok(BuilderParams {
+        max_depth: field(val, "max_depth")?
+            .as_u64()
+            .ok_or_else(|| invalid("max_depth"))? as usize,
+        // added with the initialisation methods
+        min_depth: count_or("min_depth", 0)?,
+        randomizer,
+        termination_probability,
+        float_range: match range("float_range", TypeV::Float)? {
+            (Type::Float(a), Type::Float(b)) => (a, b),
+            _ => unreachable!(),
+        },
+        int_range: match range("int_range", TypeV::Int)? {
+            (Type::Int(a), Type::Int(b)) => (a, b),
+            _ => unreachable!(),
+        },
+        uint_range: match range("uint_range", TypeV::UInt)? {
+            (Type::UInt(a), Type::UInt(b)) => (a, b),
+            _ => unreachable!(),
+        },
+        // added with the n-ary nodes
+        max_arity: count_or("max_arity", 4)?,
+    })
+}
+
+/// JSON form of a node that has children
+pub fn op_node_to_json(name: &str, rtype: TypeV, arg_types: &[TypeV], children: &[&NodeRef]) -> Value {
+    json!({
+        "kind": name,
+        "rtype": typev_to_json(rtype),
+        "arg_types": typev_list_to_json(arg_types),
+        "children": children.iter().map(|c| c.to_json()).collect::<Vec<_>>(),
+    })
+}
+
+fn get_field<'a>(val: &'a Value, key: &str) -> Result<&'a Value, SerdeErr> {
+    val.get(key)
+        .ok_or_else(|| SerdeErr::new(format!("Missing field `{}` in {}", key, val)))
+}
+
+/// Rebuilds a node tree from its JSON form.
+/// `arg_types` are the argument types of the expression the node belongs to,
+/// and are used for validating `Var` nodes
+pub fn node_from_json(
+    val: &Value,
+    arg_types: &[TypeV],
+    registry: &NodeRegistry,
+) -> Result<NodeRef, SerdeErr> {
+    let kind = get_field(val, "kind")?
+        .as_str()
+        .ok_or_else(|| SerdeErr::new(format!("Invalid node kind in {}", val)))?;
+    let rtype = typev_from_json(get_field(val, "rtype")?)?;
+    match kind {
+        "Val" => Ok(Val::make(type_from_json(rtype, get_field(val, "value")?)?)),
+        "Var" => {
+            let idx = get_field(val, "idx")?
+                .as_u64()
+                .ok_or_else(|| SerdeErr::new(format!("Invalid index in {}", val)))?
+                as usize;
+            match arg_types.get(idx) {
+                Some(ty) if *ty == rtype => Ok(Var::make(idx, rtype)),
+                Some(ty) => Err(SerdeErr::new(format!(
+                    "x[{}] is of type {:?}; Got {:?}",
+                    idx, ty, rtype
+                ))),
+                None => Err(SerdeErr::new(format!(
+                    "x[{}] is out of range for {} arguments",
+                    idx,
+                    arg_types.len()
+                ))),
+            }
+        }
+        _ => {
+            let children = get_field(val, "children")?
+                .as_array()
+                .ok_or_else(|| SerdeErr::new(format!("Invalid children in {}", val)))?
+                .iter()
+                .map(|c| node_from_json(c, arg_types, registry))
+                .collect::<Result<Vec<_>, _>>()?;
+            let node = registry.build(kind, children)?;
+            let node_arg_types = typev_list_from_json(get_field(val, "arg_types")?)?;
+            if node.get_rtype() != rtype || node.get_arg_types() != node_arg_types.as_slice() {
+                return Err(SerdeErr::new(format!(
+                    "{} has signature {:?} -> {:?}; Stored {:?} -> {:?}",
+                    kind,
+                    node.get_arg_types(),
+                    node.get_rtype(),
+                    node_arg_types,
+                    rtype
+                )));
+            }
+            Ok(node)
+        }
+    }
+}
@@ -0,0 +1,65 @@
+#![allow(dead_code)]
+//! Helpers for the algebraic simplification done by `Node::prune`.
+//!
+//! Every node prunes its children first and then
+//! - folds itself into a single `Val` if all of its children are constants,
+//! - puts the operands in canonical order if it is commutative (`BinOpKind::COMMUTATIVE`),
+//! - applies the identities of its kind (`BinOpKind::simplify`, `UnaryOpKind::simplify`).
+//!
+//! The rewrites don't change the result of `Node::eval`, other than the sign of a zero;
+//! so `x - x` and `x / x` aren't rewritten on floats, as they don't hold for infinities,
+//! NaNs and zeros (see `cancels`)
+use super::base::*;
+
+/// value of the node if it is a constant
+pub fn const_val(node: &NodeRef) -> Option<Type> {
+    if node.get_name() == "Val" {
+        Some(node.eval(&[]))
+    } else {
+        None
+    }
+}
+
+/// checks if the node is a numeric constant equal to `val`
+pub fn is_num(node: &NodeRef, val: i32) -> bool {
+    match const_val(node) {
+        Some(Type::Int(i)) => i == val,
+        Some(Type::UInt(u)) => val >= 0 && u == val as u32,
+        Some(Type::Float(f)) => f == val as f32,
+        _ => false,
+    }
+}
+
+/// checks if the node is the boolean constant `val`
+pub fn is_bool(node: &NodeRef, val: bool) -> bool {
+    matches!(const_val(node), Some(Type::Bool(b)) if b == val)
+}
+
+/// numeric constant `val` of type `rtype`
+pub fn num(rtype: TypeV, val: i32) -> NodeRef {
+    Val::make(match rtype {
+        TypeV::Int => Type::Int(val),
+        TypeV::Float => Type::Float(val as f32),
+        TypeV::UInt => Type::UInt(val as u32),
+        TypeV::Bool => unreachable!(),
+    })
+}
+
+/// checks if both the trees are the same
+pub fn same(a: &NodeRef, b: &NodeRef) -> bool {
+    a.get_equation_str() == b.get_equation_str()
+}
+
+/// checks if `x - x == 0` and `x / x == 1` can be used for `x`;
+/// integers are fine (other than integer division by zero, which panics anyway),
+/// floats never are, as `x` may be zero, infinite or NaN
+pub fn cancels(x: &NodeRef) -> bool {
+    x.get_rtype() != TypeV::Float
+}
+
+/// checks if operands of a commutative node are out of canonical order;
+/// constants go to the right, the rest is ordered on the equation string
+pub fn out_of_order(lhs: &NodeRef, rhs: &NodeRef) -> bool {
+    (const_val(lhs).is_some(), lhs.get_equation_str())
+        > (const_val(rhs).is_some(), rhs.get_equation_str())
+}
@@ -1,4 +1,9 @@
 use super::base::*;
+use super::bytecode::{Instr, Program};
+use super::codegen::Lang;
+use super::column::Column;
+use super::serialize as ser;
+use super::simplify;
 use rand::Rng;
 use std::marker::PhantomData;
 
@@ -9,6 +14,27 @@ pub trait UnaryOpKind {
     const ARG_TYPE: TypeV;
     const RTYPE: TypeV;
     fn eval(input: Type) -> Type;
+
+    /// returns the operation applied on `arg` in the source code of `lang`
+    fn get_code_str(lang: Lang, arg: &str) -> String;
+
+    /// returns the simpler equivalent of the operation on `arg` if there is one;
+    /// called from `Node::prune` on already pruned argument
+    fn simplify(_arg: &NodeRef) -> Option<NodeRef> {
+        None
+    }
+
+    /// returns the derivative of the operation on `arg`, given the derivative
+    /// `darg` of the argument; panics if the operation can't be differentiated
+    fn derivative(_arg: &NodeRef, _darg: NodeRef) -> NodeRef {
+        panic!("{} cannot be differentiated!!", Self::NAME)
+    }
+
+    /// evaluates the operation over a whole column;
+    /// by default `eval` is applied on every value
+    fn eval_column(input: Column) -> Column {
+        Column::collect(Self::RTYPE, input.iter().map(Self::eval))
+    }
 }
 
 /// UnaryOpKind<Op> is used to specialize for
@@ -17,7 +43,7 @@ pub struct UnaryOpBase<T: UnaryOpKind> {
     pub rtype: TypeV,
     pub arg_types: Vec<TypeV>,
     pub arg: NodeRef,
-    phantom: PhantomData<T>,
+    phantom: PhantomData<fn() -> T>,
 }
 
 impl<T: 'static + UnaryOpKind> UnaryOpBase<T> {
@@ -26,7 +52,7 @@ impl<T: 'static + UnaryOpKind> UnaryOpBase<T> {
             rtype: T::RTYPE,
             arg_types: vec![T::ARG_TYPE],
             arg: child,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         })
     }
     pub fn zero() -> NodeRef {
@@ -34,7 +60,7 @@ impl<T: 'static + UnaryOpKind> UnaryOpBase<T> {
             arg: Null::zero(T::RTYPE),
             arg_types: vec![T::ARG_TYPE],
             rtype: T::RTYPE,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         })
     }
 }
@@ -46,7 +72,7 @@ impl<T: 'static + UnaryOpKind> Node for UnaryOpBase<T> {
     }
 
     fn get_rtype(&self) -> TypeV {
-        T::RTYPE
+        self.rtype
     }
 
     fn get_tree_str(&self, indent: usize) -> String {
@@ -174,30 +200,48 @@ impl<T: 'static + UnaryOpKind> Node for UnaryOpBase<T> {
         T::NAME
     }
 
-    /// This is probably an unsatisfactory way to do it, I'll do better when I find a way
-    /// to put this in main class
     fn prune(&self) -> NodeRef {
-        match (T::NAME, self.arg.get_name()) {
-            ("Sin", "ASin")
-            | ("ASin", "Sin")
-            | ("Cos", "ACos")
-            | ("ACos", "Cos")
-            | ("Tan", "ATan")
-            | ("ATan", "Tan")
-            | ("Sinh", "ASinh")
-            | ("ASinh", "Sinh")
-            | ("Cosh", "ACosh")
-            | ("ACosh", "Cosh")
-            | ("Tanh", "ATanh")
-            | ("ATanh", "Tanh")
-            | ("Log", "Exp")
-            | ("Exp", "Log") => self.arg.get_child(0).prune(),
-            ("Abs", "Abs") => self.arg.prune(),
-            _ => Self::make(self.arg.prune()),
+        let arg = self.arg.prune();
+        if let Some(v) = simplify::const_val(&arg) {
+            return Val::make(T::eval(v));
         }
+        T::simplify(&arg).unwrap_or_else(|| Self::make(arg))
     }
 
     fn get_max_depth(&self) -> usize {
         self.arg.get_max_depth() + 1
     }
+
+    fn get_node_count(&self) -> usize {
+        self.arg.get_node_count() + 1
+    }
+
+    fn get_float_consts(&self, consts: &mut Vec<f32>) {
+        self.arg.get_float_consts(consts);
+    }
+
+    fn set_float_consts(&mut self, consts: &mut dyn Iterator<Item = f32>) {
+        self.arg.set_float_consts(consts);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        ser::op_node_to_json(T::NAME, self.rtype, &self.arg_types, &[&self.arg])
+    }
+
+    fn compile(&self, program: &mut Program) {
+        self.arg.compile(program);
+        program.push(Instr::Unary(T::eval_column));
+    }
+
+    fn eval_batch(&self, args: &[Column], n_rows: usize) -> Column {
+        T::eval_column(self.arg.eval_batch(args, n_rows))
+    }
+
+    fn get_code_str(&self, lang: Lang) -> String {
+        T::get_code_str(lang, &self.arg.get_code_str(lang))
+    }
+
+    fn derivative(&self, var_idx: usize) -> NodeRef {
+        T::derivative(&self.arg, self.arg.derivative(var_idx))
+    }
 }
@@ -0,0 +1,173 @@
+//! Checks the migration topologies of `Archipelago`, that training on threads
+//! gives the same results as training sequentially, and which training arguments
+//! carry over to the islands
+use genetic_algo::algorithm::archipelago::{Archipelago, Topology};
+use genetic_algo::algorithm::expr_tree::{self as et, Error};
+use genetic_algo::algorithm::metrics::{MetricsFormat, MetricsSink};
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::registry::NodeRegistry;
+use serde_json::Value;
+
+mod common;
+
+/// island `k` holds `x[0]` with errors k, k + 0.1, k + 0.2, ...
+fn archipelago(n_islands: usize, n_subs: usize) -> Archipelago {
+    let registry = NodeRegistry::new();
+    let islands = (0..n_islands)
+        .map(|k| {
+            let mut island = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+            for i in 0..n_subs {
+                let mut p = et::Expr::parse("x[0]", vec![nb::TypeV::Float], &registry).unwrap();
+                p.error = Error::Err {
+                    real: k as f32 + i as f32 / 10.0,
+                    nan: 0.0,
+                };
+                island.p.push(p);
+            }
+            island
+        })
+        .collect();
+    Archipelago::new(islands).n_migrants(2).seed(3)
+}
+
+/// islands the migrants in island `k` came from
+fn sources(arch: &Archipelago, k: usize, n_subs: usize) -> Vec<usize> {
+    arch.islands[k].p[n_subs..]
+        .iter()
+        .map(|p| match p.error {
+            Error::Err { real, .. } => real.floor() as usize,
+            Error::Uncalculated => unreachable!(),
+        })
+        .collect()
+}
+
+#[test]
+fn migration_topologies() {
+    let mut ring = archipelago(4, 5).topology(Topology::Ring);
+    ring.migrate();
+    for k in 0..4 {
+        let src = (k + 3) % 4;
+        assert_eq!(sources(&ring, k, 5), vec![src, src]);
+        // the fittest ones
+        assert!(
+            matches!(ring.islands[k].p[6].error, Error::Err { real, .. } if real == src as f32 + 0.1)
+        );
+    }
+
+    let mut full = archipelago(4, 5).topology(Topology::FullyConnected);
+    full.migrate();
+    for k in 0..4 {
+        let expected: Vec<_> = (0..4).filter(|j| *j != k).flat_map(|j| [j, j]).collect();
+        assert_eq!(sources(&full, k, 5), expected);
+    }
+
+    let mut random = archipelago(4, 5).topology(Topology::Random);
+    random.migrate();
+    let mut n_received = 0;
+    for k in 0..4 {
+        let src = sources(&random, k, 5);
+        assert!(!src.contains(&k));
+        n_received += src.len();
+    }
+    assert_eq!(n_received, 8);
+
+    // migrants are taken from before the uncalculated subjects
+    let mut short = archipelago(2, 1);
+    let new_sub = short.islands[0].p[0].clone();
+    short.islands[0]
+        .p
+        .push(et::Expr::new(new_sub.root, vec![nb::TypeV::Float]));
+    short.migrate();
+    assert_eq!(sources(&short, 1, 1), vec![0]);
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    let train_x: Vec<_> = (0..64)
+        .map(|i| vec![nb::Type::Float(i as f32 / 16.0 - 2.0)])
+        .collect();
+    let train_y: Vec<_> = train_x
+        .iter()
+        .map(|x| match x[0] {
+            nb::Type::Float(x) => nb::Type::Float(x * x - 3.0 * x),
+            _ => unreachable!(),
+        })
+        .collect();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(50)
+        .n_iter(25)
+        .max_population(300)
+        .n_threads(1)
+        .compile();
+    let run = |parallel: bool| {
+        let islands = (0..3)
+            .map(|k| {
+                let mut island = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+                island.set_build_table(FloatFnTable::new().table);
+                // islands with different parameters
+                island.set_params(nb::BuilderParams::new().max_depth(3 + k).seed(k as u64));
+                island
+            })
+            .collect();
+        let mut arch = Archipelago::new(islands)
+            .topology(Topology::Random)
+            .migration_period(4)
+            .parallel(parallel)
+            .seed(5);
+        let best = arch.train(&args);
+        for island in arch.islands.iter() {
+            assert!(best.error.cmp(&island.p[0].error).is_le());
+        }
+        let tops: Vec<_> = arch
+            .islands
+            .iter()
+            .map(|i| i.p[0].to_equation_str())
+            .collect();
+        (best.to_equation_str(), tops)
+    };
+    assert_eq!(run(false), run(true));
+}
+
+#[test]
+fn metrics_carry_the_island() {
+    let path = std::env::temp_dir().join(format!("genetic_algo_islands_{}", std::process::id()));
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(20)
+        .n_iter(4)
+        .n_threads(1)
+        .metrics(MetricsSink::create(&path, MetricsFormat::JsonLines).unwrap())
+        .compile();
+    let islands = (0..3).map(|k| common::population(k as u64)).collect();
+    Archipelago::new(islands).migration_period(2).seed(1).train(&args);
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let records: Vec<Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records.len(), 12);
+    for k in 0..3 {
+        let iters: Vec<_> = records
+            .iter()
+            .filter(|r| r["island"] == k)
+            .map(|r| r["iter"].as_u64().unwrap())
+            .collect();
+        assert_eq!(iters, vec![0, 1, 2, 3], "island {}", k);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Archipelago doesn't support checkpoint")]
+fn checkpoint_is_rejected() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .checkpoint(std::env::temp_dir().join("genetic_algo_islands.json"), 1)
+        .compile();
+    Archipelago::new(vec![common::population(1)]).train(&args);
+}
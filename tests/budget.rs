@@ -0,0 +1,98 @@
+//! Checks that training stops cleanly on running out of time or evaluations,
+//! and on being cancelled
+use genetic_algo::algorithm::cancel::CancelToken;
+use genetic_algo::algorithm::expr_tree::Error;
+use genetic_algo::algorithm::observer::Observer;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::algorithm::report::StopReason;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn max_evaluations() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(1000)
+        .max_evaluations(200)
+        .compile();
+    let report = common::population(17).train(&args);
+    assert_eq!(report.stop_reason, StopReason::MaxEvaluations);
+    assert!(report.n_evaluations >= 200);
+    assert!(report.n_iter < 1000);
+}
+
+#[test]
+fn max_duration() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(usize::MAX)
+        .max_duration(Duration::from_millis(50))
+        .compile();
+    let report = common::population(17).train(&args);
+    assert_eq!(report.stop_reason, StopReason::MaxDuration);
+    assert!(report.elapsed >= Duration::from_millis(50));
+}
+
+/// cancels the token at the end of generation `iter`
+struct CancelAt {
+    token: CancelToken,
+    iter: usize,
+}
+
+impl Observer for CancelAt {
+    fn on_generation_end(&self, iter: usize, _popln: &ap::Population) {
+        if iter == self.iter {
+            self.token.cancel();
+        }
+    }
+}
+
+#[test]
+fn cancelled_between_generations() {
+    let (train_x, train_y) = common::data();
+    let token = CancelToken::new();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(100)
+        .cancel_token(token.clone())
+        .observer(Box::new(CancelAt { token, iter: 2 }))
+        .compile();
+    let report = common::population(17).train(&args);
+    assert_eq!(report.stop_reason, StopReason::Cancelled);
+    assert_eq!(report.n_iter, 3);
+    assert!(!matches!(report.best.error, Error::Uncalculated));
+}
+
+#[test]
+fn cancelled_from_another_thread() {
+    let token = CancelToken::new();
+    let handle = {
+        let token = token.clone();
+        thread::spawn(move || {
+            let (train_x, train_y) = common::data();
+            let args = ap::TrainingArgs::new()
+                .train_x(&train_x)
+                .train_y(&train_y)
+                .n_subs(30)
+                .n_iter(usize::MAX)
+                .cancel_token(token)
+                .compile();
+            common::population(17).train(&args)
+        })
+    };
+    thread::sleep(Duration::from_millis(20));
+    token.cancel();
+    let report = handle.join().unwrap();
+    assert_eq!(report.stop_reason, StopReason::Cancelled);
+    assert!(!matches!(report.best.error, Error::Uncalculated));
+}
@@ -0,0 +1,173 @@
+//! Checks that a training resumed from a checkpoint ends up exactly where
+//! the uninterrupted training does
+use genetic_algo::algorithm::expr_tree::Error;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::registry::NodeRegistry;
+use rand::Rng;
+use serde_json::Value;
+
+mod common;
+
+fn checkpoint_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("genetic_algo_{}_{}.json", name, std::process::id()))
+}
+
+#[test]
+fn resume_matches_uninterrupted() {
+    let (train_x, train_y) = common::data();
+    let (valid_x, valid_y) = (&train_x[..10], &train_y[..10]);
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .valid_x(valid_x)
+        .valid_y(valid_y)
+        .n_subs(40)
+        .n_iter(20)
+        .mass_extinction_th(4)
+        .compile();
+    let expected = common::population(3).train(&args);
+
+    let path = checkpoint_path("resume");
+    let mut popln = common::population(3);
+    popln.init_population(args.n_subs);
+    for _ in 0..10 {
+        assert!(popln.step(&args, None).is_none());
+    }
+    popln.save_checkpoint(&path).unwrap();
+    drop(popln);
+
+    let mut popln = ap::Population::load_checkpoint(&path, &NodeRegistry::new()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(popln.state.iter, 10);
+    popln.set_build_table(FloatFnTable::new().table);
+    let report = popln.resume(&args);
+    let (best, expected_best) = (&report.best, &expected.best);
+    assert_eq!(best.to_equation_str(), expected_best.to_equation_str());
+    assert!(best.error.cmp(&expected_best.error).is_eq());
+    assert!(best.valid_error.cmp(&expected_best.valid_error).is_eq());
+    assert_eq!(report.n_iter, expected.n_iter);
+    assert_eq!(report.error_history.len(), expected.error_history.len());
+    assert_eq!(report.extinctions, expected.extinctions);
+    assert_eq!(report.n_evaluations, expected.n_evaluations);
+}
+
+#[test]
+fn checkpoint_round_trip() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(20)
+        .n_iter(3)
+        .compile();
+    let path = checkpoint_path("round_trip");
+    let mut popln = common::population(3);
+    popln.train(&args);
+    // leaves some subjects uncalculated
+    popln.init_population(5);
+    popln.save_checkpoint(&path).unwrap();
+    let mut loaded = ap::Population::load_checkpoint(&path, &NodeRegistry::new()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.p.len(), popln.p.len());
+    for (a, b) in loaded.p.iter().zip(popln.p.iter()) {
+        assert_eq!(a.to_equation_str(), b.to_equation_str());
+        assert_eq!(a.case_errors, b.case_errors);
+        match (a.error, b.error) {
+            (Error::Uncalculated, Error::Uncalculated) => {}
+            (a, b) => assert!(a.cmp(&b).is_eq()),
+        }
+    }
+    assert_eq!(loaded.state.iter, popln.state.iter);
+    assert_eq!(loaded.state.stagnant_cycles, popln.state.stagnant_cycles);
+    assert_eq!(loaded.params.max_depth, 5);
+    let draws: Vec<u32> = (0..8).map(|_| loaded.params.randomizer.gen()).collect();
+    let expected: Vec<u32> = (0..8).map(|_| popln.params.randomizer.gen()).collect();
+    assert_eq!(draws, expected);
+}
+
+#[test]
+fn checkpoints_while_training() {
+    let (train_x, train_y) = common::data();
+    let path = checkpoint_path("periodic");
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(20)
+        .n_iter(7)
+        .checkpoint(&path, 3)
+        .compile();
+    common::population(3).train(&args);
+    let loaded = ap::Population::load_checkpoint(&path, &NodeRegistry::new()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.state.iter, 6);
+}
+
+#[test]
+fn loads_state_of_older_checkpoints() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(20)
+        .n_iter(3)
+        .compile();
+    let path = checkpoint_path("older_state");
+    let mut popln = common::population(3);
+    popln.train(&args);
+    popln.save_checkpoint(&path).unwrap();
+    // state as written before the observers and the report were added
+    let text = std::fs::read_to_string(&path).unwrap();
+    let mut val: Value = serde_json::from_str(&text).unwrap();
+    let state = val["state"].as_object_mut().unwrap();
+    for key in ["best_error", "error_history", "extinctions", "n_evaluations", "elapsed_secs"] {
+        state.remove(key).unwrap();
+    }
+    std::fs::write(&path, val.to_string()).unwrap();
+
+    let mut loaded = ap::Population::load_checkpoint(&path, &NodeRegistry::new()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.state.iter, 3);
+    assert!(loaded.state.best_error.cmp(&popln.p[0].error).is_eq());
+    assert!(loaded.state.error_history.is_empty());
+    assert!(loaded.state.extinctions.is_empty());
+    assert_eq!(loaded.state.n_evaluations, 0);
+
+    // resumes with the history of the generations run since
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(20)
+        .n_iter(5)
+        .compile();
+    loaded.set_build_table(FloatFnTable::new().table);
+    let report = loaded.resume(&args);
+    assert_eq!(report.n_iter, 5);
+    assert_eq!(report.error_history.len(), 2);
+    assert!(report.n_evaluations > 0);
+}
+
+#[test]
+fn loads_params_of_older_checkpoints() {
+    let path = checkpoint_path("older");
+    let mut popln = common::population(3);
+    popln.params.set_min_depth(2);
+    popln.params.set_max_arity(6);
+    popln.init_population(5);
+    popln.save_checkpoint(&path).unwrap();
+    // params as written before `min_depth` and `max_arity` were added
+    let text = std::fs::read_to_string(&path).unwrap();
+    let mut val: Value = serde_json::from_str(&text).unwrap();
+    for key in ["min_depth", "max_arity"].iter() {
+        val["params"].as_object_mut().unwrap().remove(*key);
+    }
+    std::fs::write(&path, val.to_string()).unwrap();
+
+    let loaded = ap::Population::load_checkpoint(&path, &NodeRegistry::new()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.params.min_depth, 0);
+    assert_eq!(loaded.params.max_arity, 4);
+    assert_eq!(loaded.params.max_depth, 5);
+    assert_eq!(loaded.p.len(), 5);
+}
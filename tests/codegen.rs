@@ -0,0 +1,176 @@
+//! Compiles the Rust code generated from expression trees with `rustc`
+//! and checks its output against `Node::eval` on random inputs
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::codegen::{self, Lang};
+use genetic_algo::node::registry::NodeRegistry;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+const N_ROWS: usize = 64;
+
+/// value as printed by the generated program; NaNs of any payload compare equal
+fn fmt_val(val: nb::Type) -> String {
+    match val {
+        nb::Type::Float(f) if f.is_nan() => "nan".to_string(),
+        nb::Type::Float(f) => format!("{}", f.to_bits()),
+        nb::Type::Int(i) => format!("{}", i),
+        nb::Type::UInt(u) => format!("{}", u),
+        nb::Type::Bool(b) => format!("{}", b),
+    }
+}
+
+/// expression printing its value the same way as `fmt_val`
+fn fmt_code(expr: &str, rtype: nb::TypeV) -> String {
+    match rtype {
+        nb::TypeV::Float => format!(
+            "{{ let v = {}; if v.is_nan() {{ \"nan\".to_string() }} else {{ v.to_bits().to_string() }} }}",
+            expr
+        ),
+        _ => format!("{}.to_string()", expr),
+    }
+}
+
+/// compiles and runs `src`, returning its stdout
+fn run_rust(src: &str) -> String {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let src_path = dir.join("codegen_test.rs");
+    let bin_path = dir.join("codegen_test");
+    std::fs::write(&src_path, src).unwrap();
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let out = Command::new(rustc)
+        .args(["--edition", "2018", "-A", "warnings", "-o"])
+        .arg(&bin_path)
+        .arg(&src_path)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "generated code failed to compile:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let out = Command::new(&bin_path).output().unwrap();
+    assert!(out.status.success());
+    String::from_utf8(out.stdout).unwrap()
+}
+
+/// generates a program calling every expression on every row,
+/// and compares its output with `Node::eval`
+fn check_exprs(exprs: &[et::Expr], rows: &[Vec<nb::Type>]) {
+    let mut src = String::new();
+    let mut main = String::from("fn main() {\n");
+    let mut expected = String::new();
+    for (i, expr) in exprs.iter().enumerate() {
+        let name = format!("f{}", i);
+        src.push_str(&expr.to_code(&name, Lang::Rust));
+        for row in rows.iter() {
+            let args: Vec<_> = row.iter().map(|v| codegen::literal(*v, Lang::Rust)).collect();
+            let call = format!("{}({})", name, args.join(", "));
+            writeln!(main, "    println!(\"{{}}\", {});", fmt_code(&call, expr.root.get_rtype())).unwrap();
+            writeln!(expected, "{}", fmt_val(expr.root.eval(row))).unwrap();
+        }
+    }
+    main.push_str("}\n");
+    src.push_str(&main);
+
+    let got = run_rust(&src);
+    for (i, (e, g)) in expected.lines().zip(got.lines()).enumerate() {
+        let expr = &exprs[i / rows.len()];
+        assert_eq!(
+            e,
+            g,
+            "mismatch for {} on {:?}",
+            expr.to_equation_str(),
+            rows[i % rows.len()]
+        );
+    }
+    assert_eq!(expected.lines().count(), got.lines().count());
+}
+
+#[test]
+fn generated_rust_matches_eval_on_float_trees() {
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new()
+        .max_depth(5)
+        .float_range(-2.0, 2.0)
+        .seed(7);
+    let exprs: Vec<_> = (0..200)
+        .map(|_| et::Expr::random(vec![nb::TypeV::Float], nb::TypeV::Float, &table, &mut params))
+        .collect();
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+    let rows: Vec<_> = (0..N_ROWS)
+        .map(|_| vec![nb::Type::Float(rng.gen_range(-4.0..=4.0))])
+        .collect();
+    check_exprs(&exprs, &rows);
+}
+
+#[test]
+fn generated_rust_matches_eval_on_typed_trees() {
+    let registry = NodeRegistry::new();
+    let arg_types = vec![
+        nb::TypeV::Float,
+        nb::TypeV::Int,
+        nb::TypeV::UInt,
+        nb::TypeV::Bool,
+    ];
+    let equations = [
+        "((x[0] > 0.5F) ? Sin(x[0]) : (x[0] * -1.5F))",
+        "((x[1] <= -3I) | (x[3] & (x[2] != 7U)))",
+        "((x[1] >= x[1]) ? (x[1] - 2I) : (x[1] / 3I))",
+        "(~(x[3]) == (x[2] < 4U))",
+        "((x[2] & 5U) | (x[2] ** 2U))",
+        "((x[3] > false) ? Heaviside(x[0]) : ReLu((x[0] ** 2F)))",
+        "(((x[0] / 0F) == infF) ? -infF : NaNF)",
+        "((x[1] & 12I) == (x[1] | -4I))",
+    ];
+    let exprs: Vec<_> = equations
+        .iter()
+        .map(|eq| et::Expr::parse(eq, arg_types.clone(), &registry).unwrap())
+        .collect();
+    let mut rng = ChaCha8Rng::seed_from_u64(13);
+    let rows: Vec<_> = (0..N_ROWS)
+        .map(|_| {
+            vec![
+                nb::Type::Float(rng.gen_range(-2.0..=2.0)),
+                nb::Type::Int(rng.gen_range(-8..=8)),
+                nb::Type::UInt(rng.gen_range(0..=10)),
+                nb::Type::Bool(rng.gen()),
+            ]
+        })
+        .collect();
+    check_exprs(&exprs, &rows);
+}
+
+#[test]
+fn mutants_and_children_keep_the_signature() {
+    // the root of a tree takes different arguments than the expression
+    let arg_types = vec![nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::Float];
+    let signature = |code: &str| code.lines().find(|l| l.contains("fn ")).unwrap().to_string();
+    let expected =
+        signature(&codegen::function("f", &arg_types, nb::TypeV::Float, "", Lang::Rust));
+    let (train_x, train_y): (Vec<_>, Vec<_>) = (0..10)
+        .map(|i| {
+            let x = i as f32 / 2.0;
+            let row = vec![nb::Type::Float(x), nb::Type::Int(i), nb::Type::Float(1.0)];
+            (row, nb::Type::Float(x * x))
+        })
+        .unzip();
+    let mut popln = ap::Population::new(arg_types, nb::TypeV::Float);
+    popln.set_build_table(FloatFnTable::new().table);
+    popln.set_params(nb::BuilderParams::new().max_depth(4).seed(3));
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(20)
+        .n_iter(3)
+        .compile();
+    popln.train(&args);
+    for p in popln.p.iter() {
+        assert_eq!(signature(&p.to_code("f", Lang::Rust)), expected, "{}", p.to_equation_str());
+    }
+}
@@ -0,0 +1,36 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+
+/// argument types of two floats
+pub fn floats() -> Vec<nb::TypeV> {
+    vec![nb::TypeV::Float, nb::TypeV::Float]
+}
+
+/// 20 points of `x^3 - 2x + 1`, for `x` from -2 in steps of 0.25
+pub fn data() -> (Vec<Vec<nb::Type>>, Vec<nb::Type>) {
+    (0..20)
+        .map(|i| {
+            let x = i as f32 / 4.0 - 2.0;
+            (vec![nb::Type::Float(x)], nb::Type::Float(x * x * x - 2.0 * x + 1.0))
+        })
+        .unzip()
+}
+
+/// population of float trees of a float argument, with a randomizer seeded by `seed`
+pub fn population(seed: u64) -> ap::Population {
+    let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+    popln.set_build_table(FloatFnTable::new().table);
+    popln.set_params(nb::BuilderParams::new().max_depth(5).seed(seed));
+    popln
+}
+
+/// the value of a float; panics on other types
+pub fn float(val: nb::Type) -> f32 {
+    match val {
+        nb::Type::Float(f) => f,
+        v => panic!("Expected float; Got {:?}", v),
+    }
+}
@@ -0,0 +1,126 @@
+//! Checks the Nelder–Mead minimiser and the tuning of float constants of trees
+use genetic_algo::algorithm::const_opt::nelder_mead;
+use genetic_algo::algorithm::expr_tree::{self as et, Error};
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::column::Column;
+use genetic_algo::node::registry::NodeRegistry;
+
+fn abs_err(y: nb::Type, p: nb::Type) -> f32 {
+    match (y, p) {
+        (nb::Type::Float(y), nb::Type::Float(p)) => (y - p).abs(),
+        _ => unreachable!(),
+    }
+}
+
+fn linear_data(a: f32, b: f32) -> (Vec<Vec<nb::Type>>, Vec<nb::Type>) {
+    let xs: Vec<f32> = (0..40).map(|i| i as f32 / 8.0 - 2.5).collect();
+    (
+        xs.iter().map(|x| vec![nb::Type::Float(*x)]).collect(),
+        xs.iter().map(|x| nb::Type::Float(a * x + b)).collect(),
+    )
+}
+
+#[test]
+fn nelder_mead_finds_minimum() {
+    let rosenbrock = |x: &[f32]| (1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2);
+    let (x, fx) = nelder_mead(rosenbrock, f32::total_cmp, &[-1.2, 1.0], 400);
+    assert!(fx < 1e-4, "{:?} {}", x, fx);
+    assert!(
+        (x[0] - 1.0).abs() < 1e-2 && (x[1] - 1.0).abs() < 2e-2,
+        "{:?}",
+        x
+    );
+
+    // never worse than the start
+    let (x, fx) = nelder_mead(|x: &[f32]| x[0].abs(), f32::total_cmp, &[0.0], 5);
+    assert_eq!((x, fx), (vec![0.0], 0.0));
+}
+
+#[test]
+fn constants_are_tuned() {
+    let registry = NodeRegistry::new();
+    let (train_x, train_y) = linear_data(2.5, -1.3);
+    let train_x = Column::from_rows(&train_x);
+    let mut expr = et::Expr::parse(
+        "((x[0] * 1F) + ((3F > 2F) ? 0.5F : 1F))",
+        vec![nb::TypeV::Float],
+        &registry,
+    )
+    .unwrap();
+    let mut consts = vec![];
+    expr.root.get_float_consts(&mut consts);
+    assert_eq!(consts, vec![1.0, 3.0, 2.0, 0.5, 1.0]);
+
+    expr.optimize_consts(&train_x, &train_y, &abs_err, 300);
+    let mut tuned = vec![];
+    expr.root.get_float_consts(&mut tuned);
+    match expr.error {
+        Error::Err { real, nan } => {
+            assert!(real < 1e-3 && nan == 0.0, "{:?} {:?}", expr.error, tuned)
+        }
+        Error::Uncalculated => unreachable!(),
+    }
+    assert!((tuned[0] - 2.5).abs() < 1e-2, "{:?}", tuned);
+    // the branch taken gets the offset
+    let offset = if tuned[1] > tuned[2] {
+        tuned[3]
+    } else {
+        tuned[4]
+    };
+    assert!((offset + 1.3).abs() < 1e-2, "{:?}", tuned);
+
+    // the error matches the tuned tree
+    let error = expr.error;
+    expr.calc_err_columns(&train_x, &train_y, &abs_err);
+    assert!(error.cmp(&expr.error).is_eq());
+}
+
+#[test]
+fn top_subjects_are_tuned() {
+    let registry = NodeRegistry::new();
+    let (train_x, train_y) = linear_data(1.7, 0.3);
+    let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+    for eq in ["((x[0] * 1F) + 1F)", "(x[0] * 2F)", "(x[0] + 5F)"] {
+        popln
+            .p
+            .push(et::Expr::parse(eq, vec![nb::TypeV::Float], &registry).unwrap());
+    }
+    popln.calc_err(&train_x, &train_y, &abs_err, 1);
+    popln.sort_population(false);
+    let before: Vec<_> = popln.p.iter().map(|p| p.to_equation_str()).collect();
+    popln.optimize_consts(2, 200, &train_x, &train_y, &abs_err, 2);
+    popln.sort_population(false);
+    let mut consts = vec![];
+    popln.p[0].root.get_float_consts(&mut consts);
+    assert!(
+        (consts[0] - 1.7).abs() < 1e-2 && (consts[1] - 0.3).abs() < 1e-2,
+        "{:?}",
+        consts
+    );
+    assert!(matches!(popln.p[0].error, Error::Err { real, .. } if real < 1e-3));
+    // only the top two are tuned
+    assert_eq!(popln.p[2].to_equation_str(), before[2]);
+    assert_ne!(popln.p[1].to_equation_str(), before[1]);
+}
+
+#[test]
+fn training_keeps_errors_consistent() {
+    let (train_x, train_y) = linear_data(1.7, 0.3);
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(60)
+        .n_iter(10)
+        .err_fn(Box::new(abs_err))
+        .const_opt(3, 40)
+        .compile();
+    let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+    popln.set_build_table(FloatFnTable::new().table);
+    popln.set_params(nb::BuilderParams::new().max_depth(4).seed(2));
+    let best = popln.train(&args).best;
+    let mut check = best.clone();
+    check.calc_err(&train_x, &train_y, &abs_err);
+    assert!(best.error.cmp(&check.error).is_eq());
+}
@@ -0,0 +1,125 @@
+//! Checks nodes defined at runtime from closures through building, evaluation,
+//! printing, parsing, serialisation and training
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::codegen::Lang;
+use genetic_algo::node::column::Column;
+use genetic_algo::node::custom_nodes::CustomNode;
+use genetic_algo::node::registry::NodeRegistry;
+
+mod common;
+
+fn sigmoid() -> nb::NodeRef {
+    CustomNode::unary("sigmoid", nb::TypeV::Float, nb::TypeV::Float, |x| {
+        nb::Type::Float(1.0 / (1.0 + (-x.as_f32()).exp()))
+    })
+}
+
+/// `clip(x, lo, hi)`, with `lo` and `hi` being integers
+fn clip() -> nb::NodeRef {
+    let arg_types = vec![nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::Int];
+    CustomNode::nary("clip", arg_types, nb::TypeV::Float, |args| match args {
+        [nb::Type::Float(x), nb::Type::Int(lo), nb::Type::Int(hi)] => {
+            nb::Type::Float(x.max(*lo as f32).min(*hi as f32))
+        }
+        _ => unreachable!(),
+    })
+}
+
+fn floor() -> nb::NodeRef {
+    CustomNode::unary("floor", nb::TypeV::Float, nb::TypeV::Int, |x| {
+        nb::Type::Int(x.as_f32().floor() as i32)
+    })
+}
+
+fn registry() -> NodeRegistry {
+    let mut registry = NodeRegistry::new();
+    registry.register_node(sigmoid());
+    registry.register_node(clip());
+    registry.register_node(floor());
+    registry
+}
+
+#[test]
+fn evaluates_and_prints() {
+    let registry = registry();
+    let eq = "clip((sigmoid(x[0]) * 4F ), 1I, 3I)";
+    let expr = et::Expr::parse(eq, common::floats(), &registry).unwrap();
+    assert_eq!(expr.to_equation_str(), eq);
+    assert!(expr.type_check().is_ok());
+    for x in [-5.0f32, 0.0, 0.5, 5.0].iter() {
+        let expected = (4.0 / (1.0 + (-x).exp())).clamp(1.0, 3.0);
+        let args = [nb::Type::Float(*x), nb::Type::Float(0.0)];
+        assert_eq!(common::float(expr.root.eval(&args)), expected);
+        assert_eq!(common::float(expr.compile().eval(&args)), expected);
+        let cols = Column::from_rows(&[args]);
+        assert_eq!(common::float(expr.eval_batch(&cols, 1).get(0)), expected);
+    }
+    assert!(expr.to_str().contains("sigmoid"));
+    // custom nodes are called by name in generated code
+    let code = expr.to_code("f", Lang::Rust);
+    assert!(code.contains("clip(") && code.contains("sigmoid(x0)"), "{}", code);
+}
+
+#[test]
+fn checks_types() {
+    let registry = registry();
+    let err = et::Expr::parse("clip(x[0], 1I, 2F)", common::floats(), &registry).err().unwrap();
+    assert!(err.msg.contains("clip"), "{}", err.msg);
+    assert!(et::Expr::parse("sigmoid(x[0], x[1])", common::floats(), &registry).is_err());
+
+    let mut node = sigmoid();
+    node.set_child(0, nb::Val::make(nb::Type::Int(1)));
+    assert!(node.type_check().is_err());
+}
+
+#[test]
+fn serialises_and_prunes() {
+    let registry = registry();
+    let eq = "(sigmoid(0F) + clip(x[1], 0I, 1I))";
+    let expr = et::Expr::parse(eq, common::floats(), &registry).unwrap();
+    let loaded = et::Expr::from_json(&expr.to_json(), &registry).unwrap();
+    assert_eq!(loaded.to_equation_str(), expr.to_equation_str());
+    assert!(et::Expr::from_json(&expr.to_json(), &NodeRegistry::new()).is_err());
+
+    let mut pruned = loaded;
+    pruned.prune();
+    assert_eq!(pruned.to_equation_str(), "(clip(x[1], 0I, 1I) + 0.5F )");
+}
+
+#[test]
+fn trains_with_custom_nodes() {
+    let mut table = FloatFnTable::new().table;
+    table.push(nb::TypeV::Float, sigmoid());
+    table.push(nb::TypeV::Float, clip());
+    table.push(nb::TypeV::Int, floor());
+    // no argument of type Int; the integer arguments of clip end in constants
+    let arg_types = vec![nb::TypeV::Float];
+    let (train_x, train_y): (Vec<_>, Vec<_>) = (0..20)
+        .map(|i| {
+            let x = i as f32 / 2.0 - 5.0;
+            let y = 1.0 / (1.0 + (-x).exp());
+            (vec![nb::Type::Float(x)], nb::Type::Float(y))
+        })
+        .unzip();
+    let mut popln = ap::Population::new(arg_types.clone(), nb::TypeV::Float);
+    popln.set_build_table(table);
+    popln.set_params(nb::BuilderParams::new().max_depth(5).seed(7));
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(40)
+        .n_iter(20)
+        .compile();
+    let report = popln.train(&args);
+    assert!(report.best.type_check().is_ok());
+    let names: Vec<_> = popln.p.iter().map(|p| p.to_equation_str()).collect();
+    assert!(names.iter().any(|eq| eq.contains("sigmoid(") || eq.contains("clip(")));
+    for p in popln.p.iter() {
+        assert!(p.type_check().is_ok(), "{}", p.to_equation_str());
+        let loaded = et::Expr::parse(&p.to_equation_str(), arg_types.clone(), &registry()).unwrap();
+        assert_eq!(loaded.to_equation_str(), p.to_equation_str());
+    }
+}
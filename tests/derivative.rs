@@ -0,0 +1,104 @@
+//! Checks `Expr::derivative` on a few known derivatives,
+//! and against finite differences on random trees
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::registry::NodeRegistry;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+fn eval(node: &nb::NodeRef, args: &[f32]) -> f32 {
+    let args: Vec<_> = args.iter().map(|a| nb::Type::Float(*a)).collect();
+    match node.eval(&args) {
+        nb::Type::Float(f) => f,
+        v => panic!("Expected float; Got {:?}", v),
+    }
+}
+
+/// central difference of `node` along `x[var_idx]` at `args`
+fn finite_diff(node: &nb::NodeRef, args: &[f32], var_idx: usize, h: f32) -> f32 {
+    let (mut lo, mut hi) = (args.to_vec(), args.to_vec());
+    lo[var_idx] -= h;
+    hi[var_idx] += h;
+    (eval(node, &hi) - eval(node, &lo)) / (2.0 * h)
+}
+
+#[test]
+fn known_derivatives() {
+    let registry = NodeRegistry::new();
+    let arg_types = vec![nb::TypeV::Float, nb::TypeV::Float];
+    let cases = [
+        ("Sin(x[0])", 0, "Cos(x[0])"),
+        ("Exp(x[1])", 0, "0F"),
+        ("(x[0] * x[1])", 1, "x[0]"),
+        ("(3F * x[0])", 0, "3F"),
+        ("(x[0] ** 3F)", 0, "((x[0] ** 2F) * 3F)"),
+        ("(x[0] / 2F)", 0, "(1F / 2F)"),
+        ("Log(x[0])", 0, "(1F / x[0])"),
+        ("Heaviside(x[0])", 0, "0F"),
+        ("ReLu(x[0])", 0, "Heaviside(x[0])"),
+        (
+            "((x[0] > 0F) ? Sin(x[0]) : (x[0] * 2F))",
+            0,
+            "((x[0] > 0F) ? Cos(x[0]) : 2F)",
+        ),
+        ("((x[0] > 0F) ? x[1] : 2F)", 0, "0F"),
+    ];
+    for (input, var_idx, expected) in cases.iter() {
+        let expr = et::Expr::parse(input, arg_types.clone(), &registry).unwrap();
+        let expected = et::Expr::parse(expected, arg_types.clone(), &registry).unwrap();
+        assert_eq!(
+            expr.derivative(*var_idx).get_equation_str(),
+            expected.root.prune().get_equation_str(),
+            "d/dx[{}] {}",
+            var_idx,
+            input
+        );
+    }
+}
+
+#[test]
+fn derivative_matches_finite_differences() {
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new()
+        .max_depth(5)
+        .float_range(-2.0, 2.0)
+        .seed(17);
+    let mut rng = ChaCha8Rng::seed_from_u64(23);
+    let arg_types = vec![nb::TypeV::Float, nb::TypeV::Float];
+    let (mut n_checked, mut n_finite) = (0, 0);
+    for _ in 0..500 {
+        let expr = et::Expr::random(arg_types.clone(), nb::TypeV::Float, &table, &mut params);
+        let derivs = [expr.derivative(0), expr.derivative(1)];
+        for _ in 0..16 {
+            let args = [rng.gen_range(-2.0..=2.0), rng.gen_range(-2.0..=2.0)];
+            for (var_idx, deriv) in derivs.iter().enumerate() {
+                let d = eval(deriv, &args);
+                let f = eval(&expr.root, &args);
+                let fd = finite_diff(&expr.root, &args, var_idx, 1e-2);
+                let fd_half = finite_diff(&expr.root, &args, var_idx, 5e-3);
+                if !(d.is_finite() && f.is_finite() && fd.is_finite() && fd_half.is_finite()) {
+                    continue;
+                }
+                n_finite += 1;
+                let scale = 1f32.max(fd_half.abs()).max(f.abs());
+                // the function isn't smooth around the point (kinks, steps, poles)
+                if (fd - fd_half).abs() > 1e-2 * scale {
+                    continue;
+                }
+                n_checked += 1;
+                assert!(
+                    (d - fd_half).abs() <= 2e-2 * scale,
+                    "d/dx[{}] {} at {:?}: {} = {}; finite difference = {}",
+                    var_idx,
+                    expr.to_equation_str(),
+                    args,
+                    deriv.get_equation_str(),
+                    d,
+                    fd_half
+                );
+            }
+        }
+    }
+    assert!(n_checked * 2 > n_finite, "only {} of {} points checked", n_checked, n_finite);
+}
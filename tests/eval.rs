@@ -0,0 +1,105 @@
+//! Checks that the compiled program and the batch evaluation compute what walking
+//! the tree row by row does, on random trees of every type
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::cmp_nodes as cmp;
+use genetic_algo::node::column::Column;
+use genetic_algo::node::logic_nodes as logic;
+use genetic_algo::node::misc_nodes as misc;
+use genetic_algo::node::nary_nodes as nary;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const N_ROWS: usize = 32;
+
+fn arg_types() -> Vec<nb::TypeV> {
+    vec![nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::UInt, nb::TypeV::Bool]
+}
+
+/// nodes of every type; random integer constants take any value,
+/// so the integer nodes are ones that can't overflow
+fn table() -> nb::BuilderTable {
+    use nb::TypeV::{Bool, Float, Int, UInt};
+    let mut table = FloatFnTable::new().table;
+    table.push(Float, misc::Cond::zero(Float, vec![Bool, Float, Float]));
+    for ty in [Int, UInt].iter().copied() {
+        table.push(ty, nary::Min::zero(ty, vec![ty, ty, ty]));
+        table.push(ty, logic::And::zero(ty, vec![ty, ty]));
+        table.push(ty, logic::Or::zero(ty, vec![ty, ty]));
+        table.push(ty, nary::Max::zero(ty, vec![ty, ty]));
+        table.push(ty, misc::Cond::zero(ty, vec![Bool, ty, ty]));
+    }
+    table.push(Bool, cmp::Gt::zero(Bool, vec![Float, Float]));
+    table.push(Bool, cmp::Lte::zero(Bool, vec![Int, Int]));
+    table.push(Bool, cmp::Eq::zero(Bool, vec![UInt, UInt]));
+    table.push(Bool, logic::And::zero(Bool, vec![Bool, Bool]));
+    table.push(Bool, logic::Or::zero(Bool, vec![Bool, Bool]));
+    table.push(Bool, logic::Not::zero());
+    table
+}
+
+/// random trees returning `rtype`, with the rows of data they are evaluated on
+fn cases(rtype: nb::TypeV, seed: u64) -> (Vec<et::Expr>, Vec<Vec<nb::Type>>) {
+    let table = table();
+    let mut params = nb::BuilderParams::new()
+        .max_depth(5)
+        .float_range(-2.0, 2.0)
+        .int_range(-10, 10)
+        .uint_range(0, 10)
+        .seed(seed);
+    let exprs = (0..100)
+        .map(|_| et::Expr::random(arg_types(), rtype, &table, &mut params))
+        .collect();
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let rows = (0..N_ROWS)
+        .map(|_| {
+            vec![
+                nb::Type::Float(rng.gen_range(-4.0..=4.0)),
+                nb::Type::Int(rng.gen_range(-10..=10)),
+                nb::Type::UInt(rng.gen_range(0..=10)),
+                nb::Type::Bool(rng.gen()),
+            ]
+        })
+        .collect();
+    (exprs, rows)
+}
+
+/// checks `got` against `Node::eval` on every row; NaNs of any payload compare equal
+fn check_same(expr: &et::Expr, rows: &[Vec<nb::Type>], got: &Column) {
+    assert_eq!(got.len(), rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let (expected, got) = (expr.root.eval(row), got.get(i));
+        let same = match (expected, got) {
+            (nb::Type::Float(e), nb::Type::Float(g)) => {
+                e.to_bits() == g.to_bits() || (e.is_nan() && g.is_nan())
+            }
+            (e, g) => format!("{:?}", e) == format!("{:?}", g),
+        };
+        assert!(same, "{} on {:?}: {:?} != {:?}", expr.to_equation_str(), row, got, expected);
+    }
+}
+
+#[test]
+fn compiled_matches_tree_walk() {
+    let types = [nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::UInt, nb::TypeV::Bool];
+    for (i, rtype) in types.iter().enumerate() {
+        let (exprs, rows) = cases(*rtype, i as u64);
+        let cols = Column::from_rows(&rows);
+        for expr in exprs.iter() {
+            check_same(expr, &rows, &expr.compile().eval_columns(&cols, rows.len()));
+        }
+    }
+}
+
+#[test]
+fn batch_matches_tree_walk() {
+    let types = [nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::UInt, nb::TypeV::Bool];
+    for (i, rtype) in types.iter().enumerate() {
+        let (exprs, rows) = cases(*rtype, 10 + i as u64);
+        let cols = Column::from_rows(&rows);
+        for expr in exprs.iter() {
+            check_same(expr, &rows, &expr.eval_batch(&cols, rows.len()));
+        }
+    }
+}
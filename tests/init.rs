@@ -0,0 +1,100 @@
+//! Checks the shapes of the trees built by the initialisation strategies
+use genetic_algo::algorithm::init::InitMethod;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+
+fn population(params: nb::BuilderParams) -> ap::Population {
+    let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+    popln.set_build_table(FloatFnTable::new().table);
+    popln.set_params(params.seed(19));
+    popln
+}
+
+/// number of levels down to the shallowest terminal, counted like `get_max_depth`
+fn min_depth(node: &nb::NodeRef) -> usize {
+    let n_args = node.get_arg_types().len();
+    if n_args == 0 {
+        node.get_max_depth()
+    } else {
+        1 + (0..n_args).map(|i| min_depth(node.get_child(i))).min().unwrap()
+    }
+}
+
+#[test]
+fn full() {
+    let mut popln = population(nb::BuilderParams::new().max_depth(4));
+    popln.init_population_with(50, InitMethod::Full);
+    for p in popln.p.iter() {
+        assert_eq!(p.root.get_max_depth(), 5);
+        assert_eq!(min_depth(&p.root), 5);
+    }
+}
+
+#[test]
+fn grow_with_min_depth() {
+    let params = nb::BuilderParams::new()
+        .max_depth(6)
+        .min_depth(2)
+        .termination_probability(0.5);
+    let mut popln = population(params);
+    popln.init_population_with(50, InitMethod::Grow);
+    for p in popln.p.iter() {
+        assert!(min_depth(&p.root) >= 3);
+        assert!(p.root.get_max_depth() <= 7);
+    }
+    // not every branch goes all the way down
+    assert!(popln.p.iter().any(|p| min_depth(&p.root) < p.root.get_max_depth()));
+    assert_eq!((popln.params.min_depth, popln.params.max_depth), (2, 6));
+}
+
+#[test]
+fn ramped_half_and_half() {
+    let params = nb::BuilderParams::new().max_depth(8).termination_probability(0.5);
+    let mut popln = population(params);
+    let method = InitMethod::RampedHalfAndHalf {
+        min_depth: 2,
+        max_depth: 5,
+    };
+    popln.init_population_with(40, method);
+    for (i, p) in popln.p.iter().enumerate() {
+        let depth = 2 + (i / 2) % 4;
+        if i.is_multiple_of(2) {
+            assert!(p.root.get_max_depth() <= depth + 1);
+        } else {
+            assert_eq!(p.root.get_max_depth(), depth + 1);
+            assert_eq!(min_depth(&p.root), depth + 1);
+        }
+    }
+    let depths: Vec<usize> = popln.p.iter().map(|p| p.root.get_max_depth()).collect();
+    for depth in 3..=6 {
+        assert!(depths.contains(&depth));
+    }
+    assert_eq!(popln.params.max_depth, 8);
+}
+
+#[test]
+fn training_with_init_methods() {
+    let (train_x, train_y): (Vec<_>, Vec<_>) = (0..20)
+        .map(|i| {
+            let x = i as f32 / 4.0;
+            (vec![nb::Type::Float(x)], nb::Type::Float(x * x))
+        })
+        .unzip();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(10)
+        .init_method(InitMethod::RampedHalfAndHalf {
+            min_depth: 1,
+            max_depth: 4,
+        })
+        .immigrant_init_method(InitMethod::Full)
+        // x * x may be found; all the generations are run anyway
+        .max_allowed_err(-1.0)
+        .compile();
+    let mut popln = population(nb::BuilderParams::new().max_depth(5));
+    let report = popln.train(&args);
+    assert_eq!(report.n_iter, 10);
+}
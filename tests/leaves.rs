@@ -0,0 +1,46 @@
+//! Checks the mutation of constants of every type, and that crossover only puts
+//! trees of the right type in place of a leaf
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+
+#[test]
+fn constants_of_every_type_mutate() {
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new().max_depth(4).seed(1);
+    let consts = [
+        nb::Type::Float(0.5),
+        nb::Type::Int(3),
+        nb::Type::UInt(3),
+        nb::Type::Bool(true),
+    ];
+    for val in consts.iter() {
+        let leaf = nb::Val::make(*val);
+        let mut n_mutants = 0;
+        for _ in 0..100 {
+            let mutant = leaf.mutant_copy(1.0, 0, &[nb::TypeV::Float], &table, &mut params);
+            if let Some(m) = mutant {
+                assert_eq!(m.get_rtype(), val.get_type(), "{}", m.get_equation_str());
+                assert_eq!(m.get_name(), "Val");
+                n_mutants += 1;
+            }
+        }
+        assert!(n_mutants > 0, "{:?}", val);
+    }
+}
+
+#[test]
+fn leaves_take_donors_of_their_type() {
+    let mut params = nb::BuilderParams::new().max_depth(4).seed(2);
+    let leaves = [nb::Val::make(nb::Type::Int(1)), nb::Var::make(0, nb::TypeV::Int)];
+    for leaf in leaves.iter() {
+        let (mut n_int, mut n_float) = (0, 0);
+        for _ in 0..100 {
+            let int_donor = nb::Val::make(nb::Type::Int(7));
+            n_int += leaf.set_random_child(int_donor, 1.0, 0, &mut params).is_some() as usize;
+            let float_donor = nb::Val::make(nb::Type::Float(7.0));
+            n_float += leaf.set_random_child(float_donor, 1.0, 0, &mut params).is_some() as usize;
+        }
+        assert!(n_int > 0, "{}", leaf.get_equation_str());
+        assert_eq!(n_float, 0, "{}", leaf.get_equation_str());
+    }
+}
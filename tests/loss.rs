@@ -0,0 +1,198 @@
+//! Checks the built-in losses on known values, and their use by `Expr::calc_err`
+use genetic_algo::algorithm::expr_tree::{self as et, Error};
+use genetic_algo::algorithm::loss::*;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::logic_nodes as logic;
+use genetic_algo::node::registry::NodeRegistry;
+
+mod common;
+
+fn floats(v: &[f32]) -> Vec<nb::Type> {
+    v.iter().map(|f| nb::Type::Float(*f)).collect()
+}
+
+/// loss of `predicted` vs `actual` over the data set
+fn score(loss: &dyn Loss, actual: &[nb::Type], predicted: &[nb::Type]) -> f32 {
+    let errs: Vec<_> = actual
+        .iter()
+        .zip(predicted)
+        .map(|(y, p)| loss.case_err(*y, *p))
+        .collect();
+    loss.aggregate(&errs, actual)
+}
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+}
+
+#[test]
+fn regression_losses() {
+    let y = floats(&[1.0, 2.0, 3.0, 4.0]);
+    let p = floats(&[1.0, 2.5, 2.0, 7.0]);
+    // errors 0, 0.5, -1, 3
+    assert_close(score(&Mse, &y, &p), (0.25 + 1.0 + 9.0) / 4.0);
+    assert_close(score(&Rmse, &y, &p), ((0.25 + 1.0 + 9.0) / 4.0f32).sqrt());
+    assert_close(score(&Mae, &y, &p), 4.5 / 4.0);
+    assert_close(score(&Huber::new(1.0), &y, &p), (0.125 + 0.5 + 2.5) / 4.0);
+    // variance of y is 1.25 per case
+    assert_close(score(&RSquared, &y, &p), 10.25 / 5.0);
+    assert_close(score(&RSquared, &y, &y), 0.0);
+    assert_close(score(&Relative, &y, &p), (0.25 + 1.0 / 3.0 + 0.75) / 4.0);
+    assert_close(score(&PredRelative, &y, &p), (0.2 + 0.5 + 3.0 / 7.0) / 4.0);
+    // integers are taken as numbers
+    let yi = [nb::Type::Int(2), nb::Type::UInt(3)];
+    assert_close(score(&Mse, &yi, &floats(&[1.0, 5.0])), 2.5);
+}
+
+#[test]
+fn classification_losses() {
+    let y = [
+        nb::Type::Bool(true),
+        nb::Type::Bool(false),
+        nb::Type::Bool(true),
+    ];
+    let p = floats(&[0.9, 0.2, 0.0]);
+    let expected = -(0.9f32.ln() + 0.8f32.ln() + 1e-7f32.ln()) / 3.0;
+    assert_close(score(&LogLoss, &y, &p), expected);
+    assert!(LogLoss
+        .case_err(nb::Type::Float(1.0), nb::Type::Float(1.0))
+        .is_finite());
+
+    let scores = floats(&[2.0, 0.5, -0.5]);
+    // margins 2, -0.5, -0.5
+    assert_close(score(&Hinge, &y, &scores), (0.0 + 1.5 + 1.5) / 3.0);
+    let y_pm = floats(&[1.0, -1.0, 1.0]);
+    assert_close(score(&Hinge, &y_pm, &scores), 1.0);
+
+    let pred = [
+        nb::Type::Bool(true),
+        nb::Type::Bool(true),
+        nb::Type::Bool(true),
+    ];
+    assert_close(score(&ZeroOne, &y, &pred), 1.0 / 3.0);
+    let yi = [nb::Type::Int(3), nb::Type::Int(-1)];
+    assert_close(
+        score(&ZeroOne, &yi, &[nb::Type::Int(3), nb::Type::Int(1)]),
+        0.5,
+    );
+}
+
+#[test]
+fn calc_err_aggregates_finite_cases() {
+    let registry = NodeRegistry::new();
+    let mut expr = et::Expr::parse("(1F / x[0])", vec![nb::TypeV::Float], &registry).unwrap();
+    let train_x: Vec<_> = [1.0, 0.0, 0.5, 0.25]
+        .iter()
+        .map(|x| vec![nb::Type::Float(*x)])
+        .collect();
+    // predictions 1, inf, 2, 4
+    let train_y = floats(&[2.0, 2.0, 2.0, 2.0]);
+    expr.calc_err(&train_x, &train_y, &Rmse);
+    assert_eq!(expr.case_errors, vec![1.0, f32::INFINITY, 0.0, 4.0]);
+    match expr.error {
+        Error::Err { real, nan } => {
+            assert_close(real, (5.0f32 / 3.0).sqrt());
+            assert_close(nan, 0.25);
+        }
+        Error::Uncalculated => unreachable!(),
+    }
+    // the finite errors of closures are summed over the number of cases
+    expr.calc_err(&train_x, &train_y, &|y: nb::Type, p: nb::Type| {
+        (y.as_f32() - p.as_f32()).abs()
+    });
+    assert!(matches!(expr.error, Error::Err { real, .. } if real == 0.75));
+}
+
+/// error as calculated with the default `err_fn` before losses were added
+fn baseline_error(expr: &et::Expr, train_x: &[Vec<nb::Type>], train_y: &[nb::Type]) -> (f32, f32) {
+    let err_fn = |act_val, pred_val| match (act_val, pred_val) {
+        (nb::Type::Float(pred_y_dat), nb::Type::Float(train_y_dat)) => {
+            if train_y_dat != 0.0 {
+                ((pred_y_dat - train_y_dat) / train_y_dat).abs()
+            } else {
+                pred_y_dat.abs()
+            }
+        }
+        (_, _) => unimplemented!(),
+    };
+    let (mut err_real, mut err_nan) = (0.0f32, 0.0f32);
+    for i in 0..train_x.len() {
+        let e = err_fn(train_y[i], expr.root.eval(&train_x[i]));
+        if e.is_finite() {
+            err_real += e;
+        } else {
+            err_nan += 1.0;
+        }
+    }
+    let n = train_y.len() as f32;
+    (err_real / n, err_nan / n)
+}
+
+#[test]
+fn default_loss_is_the_baseline_err_fn() {
+    let (mut train_x, mut train_y) = common::data();
+    // a case predicted as 0 by x[0]
+    train_x.push(vec![nb::Type::Float(0.0)]);
+    train_y.push(nb::Type::Float(3.0));
+    let args = ap::TrainingArgs::new();
+    let mut popln = common::population(7);
+    popln.init_population(200);
+    let mut n_partial = 0;
+    for p in popln.p.iter_mut() {
+        let (real, nan) = baseline_error(p, &train_x, &train_y);
+        p.calc_err(&train_x, &train_y, &*args.loss);
+        match p.error {
+            Error::Err { real: r, nan: n } => {
+                assert_eq!(n, nan, "{}", p.to_equation_str());
+                assert!((r - real).abs() <= 1e-5 * real.abs(), "{}", p.to_equation_str());
+            }
+            Error::Uncalculated => unreachable!(),
+        }
+        n_partial += (nan > 0.0 && nan < 1.0) as usize;
+    }
+    // some subjects have both finite and non-finite cases
+    assert!(n_partial > 0);
+    let x = et::Expr::parse("x[0]", vec![nb::TypeV::Float], &NodeRegistry::new()).unwrap();
+    assert_eq!(baseline_error(&x, &train_x[20..], &train_y[20..]), (3.0, 0.0));
+}
+
+#[test]
+fn train_bool_classifier() {
+    // x[0] & !x[1]
+    let train_x: Vec<_> = (0..4)
+        .map(|i| vec![nb::Type::Bool(i & 1 == 1), nb::Type::Bool(i & 2 == 2)])
+        .collect();
+    let train_y: Vec<_> = train_x
+        .iter()
+        .map(|x| match (x[0], x[1]) {
+            (nb::Type::Bool(a), nb::Type::Bool(b)) => nb::Type::Bool(a && !b),
+            _ => unreachable!(),
+        })
+        .collect();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(50)
+        .n_iter(100)
+        .loss(Box::new(ZeroOne))
+        .compile();
+    let mut popln = ap::Population::new(vec![nb::TypeV::Bool, nb::TypeV::Bool], nb::TypeV::Bool);
+    let mut table = nb::BuilderTable::new();
+    let bools = vec![nb::TypeV::Bool, nb::TypeV::Bool];
+    table.push(
+        nb::TypeV::Bool,
+        logic::And::zero(nb::TypeV::Bool, bools.clone()),
+    );
+    table.push(nb::TypeV::Bool, logic::Or::zero(nb::TypeV::Bool, bools));
+    table.push(nb::TypeV::Bool, logic::Not::zero());
+    popln.set_build_table(table);
+    popln.set_params(nb::BuilderParams::new().max_depth(4).seed(5));
+    let best = popln.train(&args).best;
+    assert!(
+        matches!(best.error, Error::Err { real, nan } if real == 0.0 && nan == 0.0),
+        "{} {:?}",
+        best.to_equation_str(),
+        best.error
+    );
+}
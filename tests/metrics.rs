@@ -0,0 +1,69 @@
+//! Checks the per-generation records written while training
+use genetic_algo::algorithm::metrics::{MetricsFormat, MetricsSink, PHASES};
+use genetic_algo::algorithm::population as ap;
+use serde_json::Value;
+
+mod common;
+
+fn train(format: MetricsFormat, name: &str, n_iter: usize) -> String {
+    let path = std::env::temp_dir().join(format!("genetic_algo_{}_{}", name, std::process::id()));
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(n_iter)
+        .metrics(MetricsSink::create(&path, format).unwrap())
+        .compile();
+    common::population(11).train(&args);
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    text
+}
+
+#[test]
+fn json_lines() {
+    let text = train(MetricsFormat::JsonLines, "metrics.jsonl", 6);
+    let records: Vec<Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(records.len(), 6);
+    for (i, r) in records.iter().enumerate() {
+        assert_eq!(r["iter"], i);
+        assert!(r["island"].is_null());
+        assert!(r["population"].as_u64().unwrap() > 0);
+        // errors are ordered by the fraction of nans first
+        let err = |key: &str| {
+            let e = &r[key];
+            (e["nan"].as_f64().unwrap(), e["real"].as_f64().unwrap())
+        };
+        assert!(err("best_error") <= err("median_error"));
+        assert!(r["mean_depth"].as_f64().unwrap() >= 1.0);
+        assert!(r["mean_node_count"].as_f64().unwrap() >= 1.0);
+        assert!(r["timings_secs"]["calc_err"].as_f64().unwrap() > 0.0);
+        assert_eq!(r["timings_secs"]["sort_pareto"], 0.0);
+        assert!(r["mutants_added"].as_u64() <= r["mutants_tried"].as_u64());
+        assert!(r["crossovers_added"].as_u64() <= r["crossovers_tried"].as_u64());
+    }
+    // nothing is bred on the last generation
+    assert!(records[0]["mutants_tried"].as_u64().unwrap() > 0);
+    assert_eq!(records[5]["mutants_tried"], 0);
+}
+
+#[test]
+fn csv() {
+    let text = train(MetricsFormat::Csv, "metrics.csv", 4);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 5);
+    let header: Vec<&str> = lines[0].split(',').collect();
+    assert_eq!(header[..2], ["iter", "island"]);
+    assert_eq!(header.len(), 15 + PHASES.len());
+    for (i, line) in lines[1..].iter().enumerate() {
+        let cols: Vec<&str> = line.split(',').collect();
+        assert_eq!(cols.len(), header.len());
+        assert_eq!(cols[0], i.to_string());
+        assert_eq!(cols[1], "");
+        let err = |col: usize| -> (f32, f32) {
+            (cols[col + 1].parse().unwrap(), cols[col].parse().unwrap())
+        };
+        assert!(err(3) <= err(7));
+    }
+}
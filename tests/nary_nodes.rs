@@ -0,0 +1,232 @@
+//! Checks the nodes taking more than two operands, and the mutation and crossover
+//! of variadic nodes adding and removing operands
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::codegen::Lang;
+use genetic_algo::node::column::Column;
+use genetic_algo::node::nary_nodes as nary;
+use genetic_algo::node::registry::NodeRegistry;
+
+mod common;
+
+/// expression and the function it is expected to compute
+type Case = (&'static str, fn(f32, f32) -> f32);
+
+fn parse(eq: &str) -> et::Expr {
+    et::Expr::parse(eq, common::floats(), &NodeRegistry::new()).unwrap()
+}
+
+/// number of operands of every variadic node in the tree
+fn operand_counts(node: &nb::NodeRef, counts: &mut Vec<usize>) {
+    let n = node.get_arg_types().len();
+    if ["Sum", "Product", "Min", "Max"].contains(&node.get_name()) {
+        counts.push(n);
+    }
+    for i in 0..n {
+        operand_counts(node.get_child(i), counts);
+    }
+}
+
+#[test]
+fn evaluates() {
+    let cases: [Case; 6] = [
+        ("Sum(x[0], x[1], 2F)", |a, b| a + b + 2.0),
+        ("Product(x[0], x[1], x[0], 0.5F)", |a, b| a * b * a * 0.5),
+        ("Min(x[0], x[1], 0.5F)", |a, b| a.min(b).min(0.5)),
+        ("Max(x[0], x[1])", |a, b| a.max(b)),
+        ("Clamp(x[0], -1F, x[1])", |a, b| a.max(-1.0).min(b)),
+        ("Lerp(x[0], x[1], 0.25F)", |a, b| a + (b - a) * 0.25),
+    ];
+    let points = [(0.3f32, 2.0f32), (-4.0, 1.5), (2.0, -3.0)];
+    for (eq, f) in cases.iter() {
+        let expr = parse(eq);
+        assert_eq!(expr.to_equation_str(), *eq);
+        assert!(expr.type_check().is_ok());
+        let rows: Vec<_> = points
+            .iter()
+            .map(|(a, b)| vec![nb::Type::Float(*a), nb::Type::Float(*b)])
+            .collect();
+        let batch = expr.eval_batch(&Column::from_rows(&rows), rows.len());
+        let compiled = expr.compile().eval_rows(&rows);
+        for (i, (a, b)) in points.iter().enumerate() {
+            let expected = f(*a, *b);
+            assert_eq!(common::float(expr.root.eval(&rows[i])), expected, "{}", eq);
+            assert_eq!(common::float(batch.get(i)), expected, "{}", eq);
+            assert_eq!(common::float(compiled[i]), expected, "{}", eq);
+        }
+        let loaded = et::Expr::from_json(&expr.to_json(), &NodeRegistry::new()).unwrap();
+        assert_eq!(loaded.to_equation_str(), *eq);
+    }
+    let registry = NodeRegistry::new();
+    let ints = et::Expr::parse("Max(x[0], 3I, Sum(x[0], x[0]))", vec![nb::TypeV::Int], &registry);
+    let ints = ints.unwrap();
+    assert_eq!(ints.root.eval(&[nb::Type::Int(2)]).as_f32(), 4.0);
+}
+
+#[test]
+fn checks_arity() {
+    let registry = NodeRegistry::new();
+    for eq in ["Sum(x[0])", "Clamp(x[0], 1F)", "Lerp(x[0], x[1], 1F, 1F)"].iter() {
+        assert!(et::Expr::parse(eq, common::floats(), &registry).is_err(), "{}", eq);
+    }
+    // variadic nodes take any number of operands beyond the least
+    let eq = "Sum(x[0], x[1], x[0], x[1], x[0], x[1])";
+    assert!(et::Expr::parse(eq, common::floats(), &registry).is_ok());
+
+    let mut node = nary::Clamp::zero(nb::TypeV::Float, vec![nb::TypeV::Float; 3]);
+    node.set_child(0, nb::Val::make(nb::Type::Float(1.0)));
+    node.set_child(1, nb::Val::make(nb::Type::Int(1)));
+    node.set_child(2, nb::Val::make(nb::Type::Float(1.0)));
+    assert!(node.type_check().is_err());
+}
+
+#[test]
+fn checks_operand_types() {
+    let registry = NodeRegistry::new();
+    let cases = [
+        ("Lerp(x[0], x[0], x[0])", nb::TypeV::Int),
+        ("Lerp(x[0], x[0], x[0])", nb::TypeV::UInt),
+        ("Min(x[0], x[0])", nb::TypeV::Bool),
+        ("Clamp(x[0], x[0], x[0])", nb::TypeV::Bool),
+        ("Sum(x[0], x[0], x[0])", nb::TypeV::Bool),
+    ];
+    for (eq, ty) in cases.iter() {
+        let err = et::Expr::parse(eq, vec![*ty], &registry).err().unwrap();
+        let name = &eq[..eq.find('(').unwrap()];
+        assert!(err.msg.contains(name), "{}", err.msg);
+    }
+
+    // a stored tree with operands of a type the operation doesn't take
+    let expr = et::Expr::parse("Sum(x[0], x[0], x[0])", vec![nb::TypeV::Int], &registry).unwrap();
+    let mut json = expr.to_json();
+    json["root"]["kind"] = "Lerp".into();
+    assert!(et::Expr::from_json(&json, &registry).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Lerp can't take operands of type Int")]
+fn lerp_takes_floats_only() {
+    nary::Lerp::zero(nb::TypeV::Int, vec![nb::TypeV::Int; 3]);
+}
+
+#[test]
+fn generated_c_has_each_operand_once() {
+    let registry = NodeRegistry::new();
+    let eq = "Clamp(Min(x[0], Max(x[1], x[2], x[3]), x[4], x[5]), x[6], Max(x[7], x[8]))";
+    for ty in [nb::TypeV::Int, nb::TypeV::UInt].iter() {
+        let expr = et::Expr::parse(eq, vec![*ty; 9], &registry).unwrap();
+        let code = expr.to_code("f", Lang::C);
+        // the expression is returned last, after the helpers
+        let body = code.lines().rev().find(|l| l.trim_start().starts_with("return")).unwrap();
+        for i in 0..9 {
+            assert_eq!(body.matches(&format!("x{}", i)).count(), 1, "{}", body);
+        }
+    }
+}
+
+#[test]
+fn differentiates() {
+    let cases = [
+        "Sum(x[0], Sin(x[0]), x[1])",
+        "Product(x[0], x[1], Exp(x[0]))",
+        "Lerp(x[0], x[1], Sin(x[0]))",
+        "Clamp(x[0], -1F, x[1])",
+        "Min(x[0], x[1], 0.5F)",
+        "Max(x[0], Sin(x[0]), x[1])",
+    ];
+    let h = 1e-3;
+    for eq in cases.iter() {
+        let expr = parse(eq);
+        let deriv = expr.derivative(0);
+        for (a, b) in [(0.3f32, 2.0f32), (-4.0, 1.5), (2.0, -3.0), (0.2, 0.1)].iter() {
+            let at = |a: f32| common::float(expr.root.eval(&[nb::Type::Float(a), nb::Type::Float(*b)]));
+            let expected = (at(a + h) - at(a - h)) / (2.0 * h);
+            let actual = common::float(deriv.eval(&[nb::Type::Float(*a), nb::Type::Float(*b)]));
+            let msg = format!("{} at {}: {} != {}", eq, a, actual, expected);
+            assert!((expected - actual).abs() < 1e-2, "{}", msg);
+        }
+    }
+}
+
+#[test]
+fn mutation_adds_and_removes_operands() {
+    let eq = "Sum(x[0], x[1], 1F, 2F)";
+    let expr = parse(eq);
+    let mut table = nb::BuilderTable::new();
+    table.push(nb::TypeV::Float, nary::Sum::zero(nb::TypeV::Float, common::floats()));
+    let mut params = nb::BuilderParams::new().max_depth(3).max_arity(5).seed(3);
+    let (mut added, mut removed) = (false, false);
+    for _ in 0..2000 {
+        let mutant = match expr.root.mutant_copy(0.2, 0, &common::floats(), &table, &mut params) {
+            Some(m) => m,
+            None => continue,
+        };
+        let n = mutant.get_arg_types().len();
+        let operands: Vec<_> = (0..n).map(|i| mutant.get_child(i).get_equation_str()).collect();
+        // the operands kept stay in order
+        let is_sub = |short: &[String], long: &[String]| {
+            (0..long.len()).any(|i| [&long[..i], &long[i + 1..]].concat() == short)
+        };
+        let original: Vec<_> = ["x[0]", "x[1]", "1F", "2F"].map(String::from).to_vec();
+        added |= n == 5 && is_sub(&original, &operands);
+        removed |= n == 3 && is_sub(&operands, &original);
+        assert!(n <= 5);
+    }
+    assert!(added && removed);
+}
+
+#[test]
+fn crossover_adds_operands() {
+    let expr = parse("Sum(x[0], x[1])");
+    let mut params = nb::BuilderParams::new().max_depth(3).max_arity(3).seed(5);
+    let mut added = false;
+    for _ in 0..500 {
+        let donor = nb::Val::make(nb::Type::Float(7.0));
+        if let Some(child) = expr.root.set_random_child(donor, 0.2, 0, &mut params) {
+            assert!(child.type_check().is_ok());
+            let eq = child.get_equation_str();
+            assert!(child.get_arg_types().len() <= 3, "{}", eq);
+            added |= eq.starts_with("Sum(") && child.get_arg_types().len() == 3;
+        }
+    }
+    assert!(added);
+}
+
+#[test]
+fn trains_with_nary_nodes() {
+    let mut table = FloatFnTable::new().table;
+    table.push(nb::TypeV::Float, nary::Sum::zero(nb::TypeV::Float, common::floats()));
+    table.push(nb::TypeV::Float, nary::Max::zero(nb::TypeV::Float, common::floats()));
+    let ternary = vec![nb::TypeV::Float; 3];
+    table.push(nb::TypeV::Float, nary::Clamp::zero(nb::TypeV::Float, ternary.clone()));
+    table.push(nb::TypeV::Float, nary::Lerp::zero(nb::TypeV::Float, ternary));
+    let (train_x, train_y): (Vec<_>, Vec<_>) = (0..20)
+        .map(|i| {
+            let x = i as f32 / 4.0 - 2.0;
+            let y = x + x.max(0.0);
+            (vec![nb::Type::Float(x), nb::Type::Float(1.0)], nb::Type::Float(y))
+        })
+        .unzip();
+    let mut popln = ap::Population::new(common::floats(), nb::TypeV::Float);
+    popln.set_build_table(table);
+    popln.set_params(nb::BuilderParams::new().max_depth(5).max_arity(4).seed(11));
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(40)
+        .n_iter(20)
+        .compile();
+    popln.train(&args);
+    let registry = NodeRegistry::new();
+    let mut counts = vec![];
+    for p in popln.p.iter() {
+        assert!(p.type_check().is_ok(), "{}", p.to_equation_str());
+        operand_counts(&p.root, &mut counts);
+        let loaded = et::Expr::parse(&p.to_equation_str(), common::floats(), &registry).unwrap();
+        assert_eq!(loaded.to_equation_str(), p.to_equation_str());
+    }
+    assert!(!counts.is_empty());
+    assert!(counts.iter().all(|n| (2..=4).contains(n)), "{:?}", counts);
+}
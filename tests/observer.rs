@@ -0,0 +1,97 @@
+//! Checks that the observers are called as the training goes, and can stop it
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::algorithm::observer::Observer;
+use genetic_algo::algorithm::population as ap;
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+#[derive(Default)]
+struct Events {
+    generations: Vec<(usize, usize)>,
+    bests: Vec<(usize, et::Error)>,
+    extinctions: Vec<usize>,
+}
+
+/// records the calls; asks to stop after `stop_after` generations
+#[derive(Clone)]
+struct Recorder {
+    events: Arc<Mutex<Events>>,
+    stop_after: usize,
+}
+
+impl Recorder {
+    fn new(stop_after: usize) -> Recorder {
+        Recorder {
+            events: Arc::new(Mutex::new(Events::default())),
+            stop_after,
+        }
+    }
+}
+
+impl Observer for Recorder {
+    fn on_generation_end(&self, iter: usize, popln: &ap::Population) {
+        self.events.lock().unwrap().generations.push((iter, popln.p.len()));
+    }
+
+    fn on_new_best(&self, iter: usize, best: &et::Expr) {
+        self.events.lock().unwrap().bests.push((iter, best.error));
+    }
+
+    fn on_mass_extinction(&self, iter: usize, _popln: &ap::Population) {
+        self.events.lock().unwrap().extinctions.push(iter);
+    }
+
+    fn should_stop(&self) -> bool {
+        self.events.lock().unwrap().generations.len() >= self.stop_after
+    }
+}
+
+#[test]
+fn hooks_are_called() {
+    let (train_x, train_y) = common::data();
+    let recorder = Recorder::new(usize::MAX);
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(25)
+        .mass_extinction_th(3)
+        .observer(Box::new(recorder.clone()))
+        .compile();
+    let mut popln = common::population(5);
+    let best = popln.train(&args).best;
+
+    let events = recorder.events.lock().unwrap();
+    let iters: Vec<usize> = events.generations.iter().map(|(i, _)| *i).collect();
+    assert_eq!(iters, (0..25).collect::<Vec<_>>());
+    assert_eq!(events.generations[24].1, popln.p.len());
+
+    assert_eq!(events.bests[0].0, 0);
+    for pair in events.bests.windows(2) {
+        assert!(pair[0].0 < pair[1].0);
+        assert!(pair[1].1.cmp(&pair[0].1).is_lt());
+    }
+    let (_, last_best) = events.bests.last().unwrap();
+    assert!(last_best.cmp(&best.error).is_eq());
+
+    assert!(!events.extinctions.is_empty());
+}
+
+#[test]
+fn observer_stops_training() {
+    let (train_x, train_y) = common::data();
+    let recorder = Recorder::new(4);
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(100)
+        .observer(Box::new(Recorder::new(usize::MAX)))
+        .observer(Box::new(recorder.clone()))
+        .compile();
+    let mut popln = common::population(5);
+    popln.train(&args);
+    assert_eq!(popln.state.iter, 4);
+    assert_eq!(recorder.events.lock().unwrap().generations.len(), 4);
+}
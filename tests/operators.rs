@@ -0,0 +1,92 @@
+//! Checks that the comparison and logic operators compute `(lhs OP rhs)`,
+//! as their equations are printed, on every path of evaluation
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::column::Column;
+use genetic_algo::node::registry::NodeRegistry;
+
+/// value of `(x[0] OP x[1] )` for every pair of `vals`, through `Node::eval`,
+/// the compiled program and the batch evaluation, which must all agree
+fn eval_pairs(op: &str, ty: nb::TypeV, vals: &[nb::Type]) -> Vec<nb::Type> {
+    let eq = format!("(x[0] {} x[1] )", op);
+    let expr = et::Expr::parse(&eq, vec![ty, ty], &NodeRegistry::new()).unwrap();
+    let rows: Vec<_> = vals
+        .iter()
+        .flat_map(|a| vals.iter().map(move |b| vec![*a, *b]))
+        .collect();
+    let walked: Vec<_> = rows.iter().map(|r| expr.root.eval(r)).collect();
+    let compiled = expr.compile().eval_rows(&rows);
+    let batch = expr.eval_batch(&Column::from_rows(&rows), rows.len());
+    for (i, w) in walked.iter().enumerate() {
+        assert_eq!(format!("{:?}", compiled[i]), format!("{:?}", w), "{}", eq);
+        assert_eq!(format!("{:?}", batch.get(i)), format!("{:?}", w), "{}", eq);
+    }
+    walked
+}
+
+fn as_bool(val: nb::Type) -> bool {
+    match val {
+        nb::Type::Bool(b) => b,
+        v => panic!("Expected bool; Got {:?}", v),
+    }
+}
+
+fn bools(vals: Vec<nb::Type>) -> Vec<bool> {
+    vals.into_iter().map(as_bool).collect()
+}
+
+/// `f(a, b)` for every pair of `vals`, in the order of `eval_pairs`
+fn pairs<T: Copy>(vals: &[T], f: impl Fn(T, T) -> bool) -> Vec<bool> {
+    vals.iter()
+        .flat_map(|a| vals.iter().map(|b| f(*a, *b)).collect::<Vec<_>>())
+        .collect()
+}
+
+/// operator and the function it is expected to compute
+type Case = (&'static str, fn(f64, f64) -> bool);
+
+#[test]
+fn comparisons_take_lhs_first() {
+    let ints = [-2, 0, 3];
+    let uints = [0u32, 1, 7];
+    let floats = [-1.5f32, 0.0, 2.5];
+    let cases: [Case; 4] = [
+        (">", |a, b| a > b),
+        (">=", |a, b| a >= b),
+        ("<", |a, b| a < b),
+        ("<=", |a, b| a <= b),
+    ];
+    for (op, f) in cases.iter() {
+        let vals: Vec<_> = ints.iter().map(|i| nb::Type::Int(*i)).collect();
+        let got = eval_pairs(op, nb::TypeV::Int, &vals);
+        assert_eq!(bools(got), pairs(&ints.map(f64::from), *f), "{} on Int", op);
+
+        let vals: Vec<_> = uints.iter().map(|u| nb::Type::UInt(*u)).collect();
+        let got = eval_pairs(op, nb::TypeV::UInt, &vals);
+        assert_eq!(bools(got), pairs(&uints.map(f64::from), *f), "{} on UInt", op);
+
+        let vals: Vec<_> = floats.iter().map(|x| nb::Type::Float(*x)).collect();
+        let got = eval_pairs(op, nb::TypeV::Float, &vals);
+        assert_eq!(bools(got), pairs(&floats.map(f64::from), *f), "{} on Float", op);
+    }
+    // 3 > 2, not 2 > 3
+    let args = [nb::Type::Int(3), nb::Type::Int(2)];
+    let expr = et::Expr::parse("(x[0] > x[1] )", vec![nb::TypeV::Int; 2], &NodeRegistry::new());
+    assert!(as_bool(expr.unwrap().root.eval(&args)));
+}
+
+#[test]
+fn or_is_disjunction() {
+    let truth = [false, true];
+    let vals: Vec<_> = truth.iter().map(|b| nb::Type::Bool(*b)).collect();
+    let got = eval_pairs("|", nb::TypeV::Bool, &vals);
+    assert_eq!(bools(got), pairs(&truth, |a, b| a || b));
+
+    let vals = [nb::Type::Int(0b0101), nb::Type::Int(0b0011)];
+    let got = eval_pairs("|", nb::TypeV::Int, &vals);
+    assert_eq!(format!("{:?}", got[1]), format!("{:?}", nb::Type::Int(0b0111)));
+
+    let vals = [nb::Type::UInt(0b1100), nb::Type::UInt(0b1010)];
+    let got = eval_pairs("|", nb::TypeV::UInt, &vals);
+    assert_eq!(format!("{:?}", got[1]), format!("{:?}", nb::Type::UInt(0b1110)));
+}
@@ -0,0 +1,123 @@
+//! Checks the non-dominated sorting and crowding distance of `pareto`,
+//! and that `Population::train_pareto` returns a Pareto front
+use genetic_algo::algorithm::expr_tree::{self as et, Error};
+use genetic_algo::algorithm::pareto::{self, Complexity};
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::registry::NodeRegistry;
+
+/// subjects parsed from `(equation, error)`
+fn population(subjects: &[(&str, f32)]) -> Vec<et::Expr> {
+    let registry = NodeRegistry::new();
+    subjects
+        .iter()
+        .map(|(eq, real)| {
+            let mut p = et::Expr::parse(eq, vec![nb::TypeV::Float], &registry).unwrap();
+            p.error = Error::Err {
+                real: *real,
+                nan: 0.0,
+            };
+            p
+        })
+        .collect()
+}
+
+#[test]
+fn node_count() {
+    let p = population(&[
+        ("x[0]", 0.0),
+        ("Sin((x[0] * 2F))", 0.0),
+        ("((x[0] > 0F) ? x[0] : (x[0] + 1F))", 0.0),
+    ]);
+    let counts: Vec<_> = p.iter().map(|p| Complexity::NodeCount.of(p)).collect();
+    let depths: Vec<_> = p.iter().map(|p| Complexity::Depth.of(p)).collect();
+    assert_eq!(counts, vec![1, 4, 8]);
+    assert_eq!(depths, vec![1, 3, 3]);
+}
+
+#[test]
+fn fronts_and_crowding() {
+    let p = population(&[
+        // node count 1
+        ("x[0]", 4.0),
+        ("1F", 9.0),
+        // node count 3
+        ("(x[0] * 2F)", 2.0),
+        ("(x[0] + 1F)", 3.0),
+        ("(x[0] + 1F)", 2.0),
+        // node count 5
+        ("((x[0] * 2F) + 1F)", 1.0),
+        ("((x[0] * x[0]) + 1F)", 3.0),
+        // node count 7
+        ("(((x[0] * 2F) + 1F) * x[0])", 0.5),
+    ]);
+    let refs: Vec<_> = p.iter().collect();
+    let fronts = pareto::non_dominated_fronts(&refs, Complexity::NodeCount);
+    // duplicates of the objectives share the front
+    assert_eq!(fronts, vec![vec![7, 5, 2, 4, 0], vec![3, 1], vec![6]]);
+    for (k, front) in fronts.iter().enumerate() {
+        for i in front.iter() {
+            let dominated_by_earlier = fronts[..k].iter().flatten().any(|j| {
+                pareto::dominates(
+                    (&p[*j].error, Complexity::NodeCount.of(&p[*j])),
+                    (&p[*i].error, Complexity::NodeCount.of(&p[*i])),
+                )
+            });
+            assert_eq!(dominated_by_earlier, k > 0);
+        }
+    }
+
+    let dist = pareto::crowding_distance(&refs, &fronts[0], Complexity::NodeCount);
+    assert_eq!(dist[0], f32::INFINITY);
+    assert_eq!(dist[4], f32::INFINITY);
+    // errors 0.5, 1, 2, 2, 4 over a range of 3.5; node counts 7, 5, 3, 3, 1 over 6
+    let expected = [1.5 / 3.5 + 4.0 / 6.0, 1.0 / 3.5 + 2.0 / 6.0, 2.0 / 3.5 + 2.0 / 6.0];
+    for (d, e) in dist[1..4].iter().zip(expected.iter()) {
+        assert!((d - e).abs() < 1e-6, "{:?}", dist);
+    }
+}
+
+#[test]
+fn train_returns_pareto_front() {
+    let train_x: Vec<_> = (0..64)
+        .map(|i| vec![nb::Type::Float(i as f32 / 16.0 - 2.0)])
+        .collect();
+    let train_y: Vec<_> = train_x
+        .iter()
+        .map(|x| match x[0] {
+            nb::Type::Float(x) => nb::Type::Float(x * x * x - x + 1.0),
+            _ => unreachable!(),
+        })
+        .collect();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(100)
+        .n_iter(30)
+        .purge_period(1)
+        .max_population(500)
+        .n_threads(1)
+        .compile();
+    for complexity in [Complexity::NodeCount, Complexity::Depth] {
+        let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+        popln.set_build_table(FloatFnTable::new().table);
+        popln.set_params(nb::BuilderParams::new().max_depth(5).seed(11));
+        let front = popln.train_pareto(&args, complexity);
+        assert!(front.len() > 1);
+        // from the simplest to the most accurate; so none dominates another
+        for w in front.windows(2) {
+            assert!(complexity.of(&w[0]) < complexity.of(&w[1]));
+            assert!(w[0].error.cmp(&w[1].error).is_gt());
+        }
+        // and none of the evaluated subjects dominates the front
+        for p in popln.p.iter().filter(|p| !matches!(p.error, Error::Uncalculated)) {
+            for f in front.iter() {
+                assert!(!pareto::dominates(
+                    (&p.error, complexity.of(p)),
+                    (&f.error, complexity.of(f))
+                ));
+            }
+        }
+    }
+}
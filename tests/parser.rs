@@ -0,0 +1,112 @@
+//! Checks that printed equations parse back to the same tree,
+//! and where the parser reports malformed equations
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::misc_nodes as misc;
+use genetic_algo::node::registry::NodeRegistry;
+
+mod common;
+
+fn arg_types() -> Vec<nb::TypeV> {
+    vec![nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::UInt, nb::TypeV::Bool]
+}
+
+/// message and character offset of the error on parsing `eq`
+fn parse_err(eq: &str, registry: &NodeRegistry) -> (String, usize) {
+    let err = et::Expr::parse(eq, arg_types(), registry).err().unwrap();
+    (err.msg, err.pos)
+}
+
+#[test]
+fn equations_round_trip() {
+    let registry = NodeRegistry::new();
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new().max_depth(6).seed(3);
+    for _ in 0..200 {
+        let expr = et::Expr::random(common::floats(), nb::TypeV::Float, &table, &mut params);
+        let eq = expr.to_equation_str();
+        let parsed = et::Expr::parse(&eq, common::floats(), &registry).unwrap();
+        assert_eq!(parsed.to_equation_str(), eq);
+        assert_eq!(parsed.to_json(), expr.to_json(), "{}", eq);
+    }
+    let eqs = [
+        "((x[3] == false) ? Abs(x[0]) : (x[0] ** 0.5F))",
+        "(((x[1] * -5I) + 7I) / (x[1] - 2I))",
+        "((((x[2] | 7U) & 3U) > x[2]) & ~(x[3]))",
+        "(((x[0] / 0F) == infF) ? -infF : NaNF)",
+        "(x[0] * 1.5e-7F)",
+    ];
+    for eq in eqs.iter() {
+        let expr = et::Expr::parse(eq, arg_types(), &registry).unwrap();
+        let again = et::Expr::parse(&expr.to_equation_str(), arg_types(), &registry).unwrap();
+        assert_eq!(again.to_equation_str(), expr.to_equation_str(), "{}", eq);
+    }
+}
+
+#[test]
+fn reports_unknown_names() {
+    let registry = NodeRegistry::new();
+    let (msg, pos) = parse_err("(x[0] + Sine(x[0]))", &registry);
+    assert!(msg.contains("Unknown node `Sine`"), "{}", msg);
+    assert_eq!(pos, 8);
+
+    let (msg, pos) = parse_err("(x[0] % x[0])", &registry);
+    assert!(msg.contains("binary operator"), "{}", msg);
+    assert_eq!(pos, 6);
+}
+
+#[test]
+fn reports_trailing_input() {
+    let registry = NodeRegistry::new();
+    let (msg, pos) = parse_err("(x[0] + 1F) x[0]", &registry);
+    assert!(msg.contains("trailing input"), "{}", msg);
+    assert_eq!(pos, 12);
+
+    let (msg, pos) = parse_err("Sin(x[0]))", &registry);
+    assert!(msg.contains("trailing input"), "{}", msg);
+    assert_eq!(pos, 9);
+}
+
+#[test]
+fn reports_arguments_out_of_range() {
+    let registry = NodeRegistry::new();
+    let (msg, pos) = parse_err("(x[0] + Sin(x[4]))", &registry);
+    assert!(msg.contains("x[4] is out of range for 4 arguments"), "{}", msg);
+    assert_eq!(pos, 12);
+}
+
+#[test]
+fn reports_type_errors_at_the_call() {
+    let mut registry = NodeRegistry::new();
+    let (msg, pos) = parse_err("(x[0] + Sin((x[0] + x[1])))", &registry);
+    assert!(msg.contains("required argument of type"), "{}", msg);
+    assert_eq!(pos, 18);
+
+    // takes its arguments as given, so only the node's own type check rejects it
+    registry.register("Pick", 3, Box::new(|types| misc::Cond::zero(types[1], types.to_vec())));
+    let (msg, pos) = parse_err("(x[0] + Sin(Pick(x[0], x[0], x[0])))", &registry);
+    assert!(msg.contains("Cond required argument of type"), "{}", msg);
+    assert_eq!(pos, 12);
+}
+
+#[test]
+fn reports_operand_types_an_operator_doesnt_take() {
+    let registry = NodeRegistry::new();
+    let (msg, pos) = parse_err("(x[0] + (x[1] ** 2I))", &registry);
+    assert!(msg.contains("** required argument of type"), "{}", msg);
+    assert_eq!(pos, 14);
+
+    for op in ["+", "-", "*", "/", "**"].iter() {
+        let (msg, pos) = parse_err(&format!("(x[3] {} true)", op), &registry);
+        assert!(msg.contains("required argument of type"), "{}", msg);
+        assert_eq!(pos, 6);
+    }
+    let (msg, pos) = parse_err("(x[0] & x[0])", &registry);
+    assert!(msg.contains("& required argument of type"), "{}", msg);
+    assert_eq!(pos, 6);
+
+    for eq in ["(x[0] ** 2F)", "(x[2] ** 2U)", "(x[1] & 3I)", "(x[3] | (x[3] == true))"].iter() {
+        assert!(et::Expr::parse(eq, arg_types(), &registry).is_ok(), "{}", eq);
+    }
+}
@@ -0,0 +1,72 @@
+//! Checks the report of a training run
+use genetic_algo::algorithm::observer::Observer;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::algorithm::report::StopReason;
+
+mod common;
+
+#[test]
+fn runs_all_iterations() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(30)
+        .mass_extinction_th(3)
+        .const_opt(2, 5)
+        .compile();
+    let report = common::population(13).train(&args);
+    assert_eq!(report.stop_reason, StopReason::MaxIter);
+    assert_eq!(report.n_iter, 30);
+    assert_eq!(report.error_history.len(), 30);
+    // the fittest subject always survives
+    for pair in report.error_history.windows(2) {
+        assert!(!pair[1].cmp(&pair[0]).is_gt());
+    }
+    assert!(report.error_history[29].cmp(&report.best.error).is_eq());
+    assert!(!report.extinctions.is_empty());
+    assert!(report.extinctions.windows(2).all(|w| w[0] < w[1]));
+    assert!(report.n_evaluations > 30 * 30);
+    assert!(report.elapsed.as_nanos() > 0);
+}
+
+#[test]
+fn stops_on_target_error() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(30)
+        .max_allowed_err(f32::MAX)
+        .compile();
+    let report = common::population(13).train(&args);
+    assert_eq!(report.stop_reason, StopReason::TargetError);
+    assert_eq!(report.n_iter, 1);
+    assert_eq!(report.error_history.len(), 1);
+    assert_eq!(report.n_evaluations, 30);
+}
+
+struct StopNow;
+
+impl Observer for StopNow {
+    fn should_stop(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn stops_on_observer() {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(30)
+        .observer(Box::new(StopNow))
+        .compile();
+    let report = common::population(13).train(&args);
+    assert_eq!(report.stop_reason, StopReason::Observer);
+    assert_eq!(report.n_iter, 1);
+}
@@ -0,0 +1,57 @@
+//! Checks that a training gives the same result for the same seed,
+//! and for any number of threads
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+
+mod common;
+
+/// errors of `subjects` calculated on `n_threads` threads
+fn errors(subjects: &[et::Expr], n_threads: usize) -> Vec<String> {
+    let (train_x, train_y) = common::data();
+    let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+    popln.p = subjects.to_vec();
+    let args = ap::TrainingArgs::new();
+    popln.calc_err(&train_x, &train_y, &*args.loss, n_threads);
+    popln.p.iter().map(|p| format!("{:?}", p.error)).collect()
+}
+
+/// equation and error of the best subject found with `n_threads`
+fn train(n_threads: usize, seed: u64) -> (String, String) {
+    let (train_x, train_y) = common::data();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(40)
+        .n_iter(15)
+        .n_threads(n_threads)
+        .const_opt(2, 5)
+        .compile();
+    let report = common::population(seed).train(&args);
+    (report.best.to_equation_str(), format!("{:?}", report.best.error))
+}
+
+#[test]
+fn errors_same_for_any_number_of_threads() {
+    let mut popln = common::population(23);
+    popln.init_population(101);
+    let expected = errors(&popln.p, 1);
+    assert!(expected.iter().all(|e| e != "Uncalculated"));
+    assert_eq!(errors(&popln.p, 4), expected);
+    assert_eq!(errors(&popln.p, 0), expected);
+    assert_eq!(errors(&popln.p, 200), expected);
+}
+
+#[test]
+fn same_for_any_number_of_threads() {
+    let expected = train(1, 29);
+    assert_eq!(train(4, 29), expected);
+    assert_eq!(train(0, 29), expected);
+}
+
+#[test]
+fn same_for_the_same_seed() {
+    let expected = train(1, 37);
+    assert_eq!(train(1, 37), expected);
+    assert_ne!(train(1, 38), expected);
+}
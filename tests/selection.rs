@@ -0,0 +1,157 @@
+//! Checks the selection strategies pick from the expected part of a sorted population
+use genetic_algo::algorithm::expr_tree::{self as et, Error};
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::algorithm::selection::*;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::registry::NodeRegistry;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const N_PICKS: usize = 20000;
+
+/// sorted population of `n` subjects with errors 0, 1, 2, ...,
+/// followed by `n_new` subjects with uncalculated errors
+fn population(n: usize, n_new: usize) -> Vec<et::Expr> {
+    let registry = NodeRegistry::new();
+    let expr = et::Expr::parse("x[0]", vec![nb::TypeV::Float], &registry).unwrap();
+    (0..n + n_new)
+        .map(|i| {
+            let mut p = expr.clone();
+            if i < n {
+                p.error = Error::Err {
+                    real: i as f32,
+                    nan: 0.0,
+                };
+            }
+            p
+        })
+        .collect()
+}
+
+/// number of times each subject is picked
+fn histogram(selection: &dyn Selection, population: &[et::Expr]) -> Vec<usize> {
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    let mut counts = vec![0; population.len()];
+    let picks = selection.select(population, N_PICKS, &mut rng);
+    assert_eq!(picks.len(), N_PICKS);
+    for i in picks {
+        counts[i] += 1;
+    }
+    counts
+}
+
+/// fraction of the picks from the fitter half of the population
+fn top_half(counts: &[usize]) -> f32 {
+    counts[..counts.len() / 2].iter().sum::<usize>() as f32 / N_PICKS as f32
+}
+
+#[test]
+fn selection_pressure() {
+    let p = population(100, 0);
+
+    let uniform = top_half(&histogram(&Tournament::new(1), &p));
+    assert!((uniform - 0.5).abs() < 0.03, "{}", uniform);
+    let uniform = top_half(&histogram(&Uniform, &p));
+    assert!((uniform - 0.5).abs() < 0.03, "{}", uniform);
+    let tour_2 = top_half(&histogram(&Tournament::new(2), &p));
+    let tour_7 = top_half(&histogram(&Tournament::new(7), &p));
+    assert!(uniform < tour_2 && tour_2 < tour_7, "{} {} {}", uniform, tour_2, tour_7);
+
+    let linear_flat = top_half(&histogram(&LinearRank::new(1.0), &p));
+    assert!((linear_flat - 0.5).abs() < 0.03, "{}", linear_flat);
+    let linear = histogram(&LinearRank::new(2.0), &p);
+    assert_eq!(linear[99], 0);
+    assert!(top_half(&linear) > 0.7);
+
+    let exp = histogram(&ExponentialRank::new(0.9), &p);
+    assert!(exp[0] > exp[10] && exp[10] > exp[30]);
+
+    let trunc = histogram(&Truncation::new(0.1), &p);
+    assert!(trunc[..10].iter().all(|c| *c > 0));
+    assert!(trunc[10..].iter().all(|c| *c == 0));
+
+    let fit = histogram(&FitnessProportional, &p);
+    // fitness of the first is twice that of the second; 1/(1+0) vs 1/(1+1)
+    let ratio = fit[0] as f32 / fit[1] as f32;
+    assert!((ratio - 2.0).abs() < 0.3, "{}", ratio);
+}
+
+#[test]
+fn default_parents_as_in_baseline() {
+    // the father used to be picked with weights n - 1, ..., 1, 0 by rank,
+    // the mother uniformly
+    let n = 50;
+    let p = population(n, 0);
+    let args = ap::TrainingArgs::new();
+    let total = (n * (n - 1) / 2) as f32;
+    let fathers = histogram(args.selection.as_ref(), &p);
+    let mothers = histogram(args.mother_selection.as_ref(), &p);
+    for i in 0..n {
+        let expected = (n - 1 - i) as f32 / total;
+        let actual = fathers[i] as f32 / N_PICKS as f32;
+        assert!((actual - expected).abs() < 0.01, "father {}: {}", i, actual);
+        let actual = mothers[i] as f32 / N_PICKS as f32;
+        assert!((actual - 1.0 / n as f32).abs() < 0.01, "mother {}: {}", i, actual);
+    }
+}
+
+#[test]
+fn uncalculated_subjects() {
+    let p = population(20, 20);
+    let fit = histogram(&FitnessProportional, &p);
+    assert!(fit[20..].iter().all(|c| *c == 0));
+    // rank based strategies treat the new subjects as the least fit
+    let linear = histogram(&LinearRank::default(), &p);
+    assert_eq!(linear[39], 0);
+    assert!(top_half(&linear) > 0.7);
+}
+
+#[test]
+fn lexicase_picks_specialists() {
+    // subject 0 is best on case 0, subject 1 on case 1, subject 2 has the
+    // lowest average error but is the best on neither; the rest are far off
+    let mut p = population(12, 1);
+    let case_errors = [[0.0, 10.0], [10.0, 0.0], [4.0, 4.0]];
+    for (i, s) in p.iter_mut().enumerate() {
+        s.case_errors = match i {
+            0..=2 => case_errors[i].to_vec(),
+            3 => vec![100.0, f32::INFINITY],
+            12 => vec![],
+            _ => vec![100.0, 100.0],
+        };
+    }
+    let counts = histogram(&EpsilonLexicase, &p);
+    assert_eq!(counts[0] + counts[1], N_PICKS);
+    assert!((counts[0] as f32 / N_PICKS as f32 - 0.5).abs() < 0.03);
+}
+
+#[test]
+fn lexicase_epsilon_is_mad() {
+    let mut p = population(5, 0);
+    for (s, e) in p.iter_mut().zip([1.0, 2.0, 4.0, 8.0, f32::INFINITY]) {
+        s.case_errors = vec![e];
+    }
+    // median of 1, 2, 4, 8 is 4; deviations 3, 2, 0, 4 have median 3
+    let refs: Vec<_> = p.iter().collect();
+    assert_eq!(EpsilonLexicase::epsilons(&refs, 1), vec![3.0]);
+    // everything within 1 + 3 of the best
+    let counts = histogram(&EpsilonLexicase, &p);
+    assert!(counts[..3].iter().all(|c| *c > 0));
+    assert!(counts[3..].iter().all(|c| *c == 0));
+}
+
+#[test]
+fn calc_err_keeps_case_errors() {
+    let registry = NodeRegistry::new();
+    let mut expr = et::Expr::parse("(1F / x[0])", vec![nb::TypeV::Float], &registry).unwrap();
+    let train_x: Vec<_> = [1.0, 0.0, 2.0]
+        .iter()
+        .map(|x| vec![nb::Type::Float(*x)])
+        .collect();
+    let train_y = vec![nb::Type::Float(2.0); 3];
+    expr.calc_err(&train_x, &train_y, &|y, p| match (y, p) {
+        (nb::Type::Float(y), nb::Type::Float(p)) => (y - p).abs(),
+        _ => unreachable!(),
+    });
+    assert_eq!(expr.case_errors, vec![1.0, f32::INFINITY, 1.5]);
+}
@@ -0,0 +1,253 @@
+//! Checks that expressions come back unchanged from their JSON form and from files,
+//! and that malformed JSON is rejected
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::cmp_nodes as cmp;
+use genetic_algo::node::logic_nodes as logic;
+use genetic_algo::node::math_nodes as math;
+use genetic_algo::node::misc_nodes as misc;
+use genetic_algo::node::nary_nodes as nary;
+use genetic_algo::node::op_nodes as ops;
+use genetic_algo::node::registry::NodeRegistry;
+use genetic_algo::node::serialize as ser;
+use serde_json::{json, Value};
+
+fn arg_types() -> Vec<nb::TypeV> {
+    vec![nb::TypeV::Float, nb::TypeV::Int, nb::TypeV::UInt, nb::TypeV::Bool]
+}
+
+/// `zero` with `lhs` and `rhs` as its children
+fn bin(mut zero: nb::NodeRef, lhs: nb::NodeRef, rhs: nb::NodeRef) -> nb::NodeRef {
+    zero.set_child(0, lhs);
+    zero.set_child(1, rhs);
+    zero
+}
+
+fn unary(mut zero: nb::NodeRef, child: nb::NodeRef) -> nb::NodeRef {
+    zero.set_child(0, child);
+    zero
+}
+
+fn var(idx: usize) -> nb::NodeRef {
+    nb::Var::make(idx, arg_types()[idx])
+}
+
+fn val(val: nb::Type) -> nb::NodeRef {
+    nb::Val::make(val)
+}
+
+/// trees over `arg_types()` using every kind of node in the default registry,
+/// with constants of every type
+fn trees() -> Vec<nb::NodeRef> {
+    use nb::Type::{Bool, Float, Int, UInt};
+    use nb::TypeV as T;
+    let f = || vec![T::Float, T::Float];
+    let i = || vec![T::Int, T::Int];
+    let u = || vec![T::UInt, T::UInt];
+    let b = || vec![T::Bool, T::Bool];
+    vec![
+        bin(
+            ops::Add::zero(T::Float, f()),
+            unary(math::Sin::zero(), unary(math::Cos::zero(), unary(math::Tan::zero(), var(0)))),
+            unary(math::ASin::zero(), unary(math::ACos::zero(), unary(math::ATan::zero(), val(Float(-0.25))))),
+        ),
+        bin(
+            ops::Sub::zero(T::Float, f()),
+            unary(math::Sinh::zero(), unary(math::Cosh::zero(), unary(math::Tanh::zero(), var(0)))),
+            unary(math::ASinh::zero(), unary(math::ACosh::zero(), unary(math::ATanh::zero(), val(Float(0.5))))),
+        ),
+        bin(
+            ops::Mul::zero(T::Float, f()),
+            unary(math::Exp::zero(), unary(math::Log::zero(), unary(math::Abs::zero(), var(0)))),
+            bin(
+                ops::Div::zero(T::Float, f()),
+                unary(math::Heaviside::zero(), var(0)),
+                bin(ops::Pow::zero(T::Float, f()), unary(math::ReLu::zero(), var(0)), val(Float(2.0))),
+            ),
+        ),
+        bin(
+            ops::Sub::zero(T::Int, i()),
+            bin(ops::Add::zero(T::Int, i()), var(1), val(Int(3))),
+            bin(
+                ops::Div::zero(T::Int, i()),
+                bin(ops::Mul::zero(T::Int, i()), var(1), val(Int(-2))),
+                val(Int(7)),
+            ),
+        ),
+        bin(
+            ops::Sub::zero(T::UInt, u()),
+            bin(ops::Pow::zero(T::UInt, u()), var(2), val(UInt(2))),
+            bin(ops::Add::zero(T::UInt, u()), var(2), val(UInt(3))),
+        ),
+        bin(
+            logic::Or::zero(T::Bool, b()),
+            bin(
+                logic::And::zero(T::Bool, b()),
+                bin(cmp::Eq::zero(T::Bool, f()), var(0), val(Float(1.0))),
+                bin(cmp::NEq::zero(T::Bool, i()), var(1), val(Int(-1))),
+            ),
+            bin(
+                logic::And::zero(T::Bool, b()),
+                bin(cmp::Gt::zero(T::Bool, u()), var(2), val(UInt(1))),
+                bin(cmp::Gte::zero(T::Bool, f()), var(0), val(Float(0.5))),
+            ),
+        ),
+        bin(
+            logic::And::zero(T::Bool, b()),
+            bin(
+                logic::Or::zero(T::Bool, b()),
+                bin(cmp::Lt::zero(T::Bool, i()), var(1), val(Int(4))),
+                bin(cmp::Lte::zero(T::Bool, u()), var(2), val(UInt(5))),
+            ),
+            unary(logic::Not::zero(), bin(logic::Or::zero(T::Bool, b()), var(3), val(Bool(true)))),
+        ),
+        misc::Cond::make(
+            bin(cmp::Eq::zero(T::Bool, b()), var(3), val(Bool(false))),
+            bin(logic::And::zero(T::Int, i()), var(1), val(Int(12))),
+            bin(logic::Or::zero(T::Int, i()), var(1), val(Int(-4))),
+        ),
+        misc::Cond::make(
+            bin(cmp::Eq::zero(T::Bool, b()), var(3), val(Bool(false))),
+            nary::Clamp::make(vec![var(0), val(Float(-1.0)), val(Float(1.0))]),
+            nary::Lerp::make(vec![var(0), val(Float(2.0)), val(Float(0.5))]),
+        ),
+        nary::Sum::make(vec![
+            nary::Product::make(vec![var(1), val(Int(2)), var(1)]),
+            nary::Min::make(vec![var(1), val(Int(-5))]),
+            nary::Max::make(vec![var(1), val(Int(0)), val(Int(7))]),
+        ]),
+        nary::Sum::make(vec![
+            nary::Product::make(vec![var(2), val(UInt(2))]),
+            nary::Min::make(vec![var(2), val(UInt(5)), val(UInt(1))]),
+            nary::Max::make(vec![var(2), val(UInt(0))]),
+        ]),
+        misc::Cond::make(
+            bin(cmp::Eq::zero(T::Bool, f()), var(0), val(Float(f32::INFINITY))),
+            val(Float(f32::NEG_INFINITY)),
+            val(Float(f32::NAN)),
+        ),
+    ]
+}
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("genetic_algo_{}_{}.json", name, std::process::id()))
+}
+
+fn check_same(expr: &et::Expr, loaded: &et::Expr) {
+    let eq = expr.root.get_equation_str();
+    assert_eq!(loaded.root.get_equation_str(), eq);
+    assert_eq!(loaded.root.get_rtype(), expr.root.get_rtype(), "{}", eq);
+    assert_eq!(format!("{:?}", loaded.error), format!("{:?}", expr.error), "{}", eq);
+    // the argument types, the return type and every node's kind, type and value
+    assert_eq!(loaded.to_json(), expr.to_json(), "{}", eq);
+}
+
+#[test]
+fn every_node_kind_round_trips() {
+    let registry = NodeRegistry::new();
+    let args = [
+        nb::Type::Float(0.75),
+        nb::Type::Int(-3),
+        nb::Type::UInt(4),
+        nb::Type::Bool(true),
+    ];
+    for tree in trees().iter() {
+        let eq = tree.get_equation_str();
+        assert!(tree.type_check().is_ok(), "{}", eq);
+        // not valid JSON numbers, so non-finite floats must survive being written as text
+        let text = serde_json::to_string(&tree.to_json()).unwrap();
+        let val: Value = serde_json::from_str(&text).unwrap();
+        let loaded = ser::node_from_json(&val, &arg_types(), &registry).unwrap();
+        assert_eq!(loaded.get_equation_str(), eq);
+        assert_eq!(loaded.to_json(), tree.to_json(), "{}", eq);
+        let (got, expected) = (loaded.eval(&args), tree.eval(&args));
+        assert_eq!(format!("{:?}", got), format!("{:?}", expected), "{}", eq);
+        // comparisons keep their `Bool` return type when copied
+        assert_eq!(tree.deep_copy().to_json(), tree.to_json(), "{}", eq);
+    }
+}
+
+#[test]
+fn random_expressions_round_trip() {
+    let registry = NodeRegistry::new();
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new().max_depth(6).seed(1);
+    for i in 0..50 {
+        let mut expr = et::Expr::random(vec![nb::TypeV::Float], nb::TypeV::Float, &table, &mut params);
+        expr.error = et::Error::Err { real: 0.25 * i as f32, nan: 0.1 };
+        let loaded = et::Expr::from_json(&expr.to_json(), &registry).unwrap();
+        check_same(&expr, &loaded);
+
+        let path = temp_path(&format!("serialize_{}", i));
+        expr.save(&path).unwrap();
+        let loaded = et::Expr::load(&path, &registry);
+        std::fs::remove_file(&path).unwrap();
+        check_same(&expr, &loaded.unwrap());
+    }
+}
+
+#[test]
+fn non_finite_errors_round_trip() {
+    let registry = NodeRegistry::new();
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new().max_depth(4).seed(2);
+    let mut expr = et::Expr::random(vec![nb::TypeV::Float], nb::TypeV::Float, &table, &mut params);
+    expr.error = et::Error::Err { real: f32::INFINITY, nan: f32::NAN };
+
+    let path = temp_path("serialize_non_finite");
+    expr.save(&path).unwrap();
+    let loaded = et::Expr::load(&path, &registry);
+    std::fs::remove_file(&path).unwrap();
+    match loaded.unwrap().error {
+        et::Error::Err { real, nan } => assert!(real == f32::INFINITY && nan.is_nan()),
+        e => panic!("Expected error; Got {:?}", e),
+    }
+}
+
+/// JSON of an expression of `arg_types()` with `root` as its root
+fn expr_json(root: &nb::NodeRef) -> Value {
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new().max_depth(2).seed(3);
+    let mut val = et::Expr::random(arg_types(), root.get_rtype(), &table, &mut params).to_json();
+    val["root"] = root.to_json();
+    val
+}
+
+#[test]
+fn rejects_other_versions() {
+    let registry = NodeRegistry::new();
+    let mut val = expr_json(&var(0));
+    assert!(et::Expr::from_json(&val, &registry).is_ok());
+    val["version"] = json!(2);
+    let err = et::Expr::from_json(&val, &registry).err().unwrap();
+    assert!(err.msg.contains("Unsupported format version"), "{}", err.msg);
+
+    val.as_object_mut().unwrap().remove("version");
+    assert!(et::Expr::from_json(&val, &registry).is_err());
+}
+
+#[test]
+fn rejects_vars_of_the_wrong_type() {
+    let registry = NodeRegistry::new();
+    let mut val = expr_json(&var(0));
+    val["rtype"] = json!("Int");
+    val["root"]["rtype"] = json!("Int");
+    let err = et::Expr::from_json(&val, &registry).err().unwrap();
+    assert!(err.msg.contains("x[0] is of type Float"), "{}", err.msg);
+
+    let mut val = expr_json(&var(0));
+    val["root"]["idx"] = json!(4);
+    let err = et::Expr::from_json(&val, &registry).err().unwrap();
+    assert!(err.msg.contains("out of range"), "{}", err.msg);
+}
+
+#[test]
+fn rejects_unknown_nodes() {
+    let mut val = expr_json(&unary(math::Sin::zero(), var(0)));
+    assert!(et::Expr::from_json(&val, &NodeRegistry::new()).is_ok());
+    assert!(et::Expr::from_json(&val, &NodeRegistry::empty()).is_err());
+    val["root"]["kind"] = json!("Sine");
+    let err = et::Expr::from_json(&val, &NodeRegistry::new()).err().unwrap();
+    assert!(err.msg.contains("Unknown node `Sine`"), "{}", err.msg);
+}
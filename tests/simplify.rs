@@ -0,0 +1,156 @@
+//! Checks the rewrites done by `Expr::prune` and that they keep
+//! the results of `Node::eval` on random inputs
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::registry::NodeRegistry;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+mod common;
+
+fn same_val(a: nb::Type, b: nb::Type) -> bool {
+    match (a, b) {
+        (nb::Type::Float(a), nb::Type::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+        (nb::Type::Int(a), nb::Type::Int(b)) => a == b,
+        (nb::Type::UInt(a), nb::Type::UInt(b)) => a == b,
+        (nb::Type::Bool(a), nb::Type::Bool(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn check_preserved(expr: &et::Expr, rows: &[Vec<nb::Type>]) {
+    let mut pruned = expr.clone();
+    pruned.prune();
+    pruned.type_check().unwrap();
+    assert!(pruned.root.get_max_depth() <= expr.root.get_max_depth());
+    for row in rows.iter() {
+        let (e, p) = (expr.root.eval(row), pruned.root.eval(row));
+        assert!(
+            same_val(e, p),
+            "{} = {:?} but {} = {:?} on {:?}",
+            expr.to_equation_str(),
+            e,
+            pruned.to_equation_str(),
+            p,
+            row
+        );
+    }
+}
+
+/// floats for which the usual identities may not hold
+const SPECIAL: [f32; 5] = [0.0, -0.0, f32::INFINITY, f32::NEG_INFINITY, f32::NAN];
+
+fn typed_rows() -> Vec<Vec<nb::Type>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(5);
+    let mut floats: Vec<f32> = (0..64).map(|_| rng.gen_range(-3.0..=3.0)).collect();
+    floats.extend(SPECIAL.iter());
+    floats
+        .into_iter()
+        .map(|f| {
+            vec![
+                nb::Type::Float(f),
+                nb::Type::Int(rng.gen_range(-8..=8)),
+                nb::Type::UInt(rng.gen_range(1..=10)),
+                nb::Type::Bool(rng.gen()),
+            ]
+        })
+        .collect()
+}
+
+#[test]
+fn prune_preserves_eval_on_random_trees() {
+    let table = FloatFnTable::new().table;
+    let mut params = nb::BuilderParams::new()
+        .max_depth(7)
+        .float_range(-2.0, 2.0)
+        .seed(3);
+    let mut rng = ChaCha8Rng::seed_from_u64(9);
+    let rows: Vec<_> = (0..64)
+        .map(|_| rng.gen_range(-5.0..=5.0))
+        .chain(SPECIAL.iter().copied())
+        .map(|f| vec![nb::Type::Float(f)])
+        .collect();
+    for _ in 0..1000 {
+        let expr = et::Expr::random(vec![nb::TypeV::Float], nb::TypeV::Float, &table, &mut params);
+        check_preserved(&expr, &rows);
+    }
+}
+
+#[test]
+fn prune_applies_rules() {
+    let registry = NodeRegistry::new();
+    let arg_types = vec![
+        nb::TypeV::Float,
+        nb::TypeV::Int,
+        nb::TypeV::UInt,
+        nb::TypeV::Bool,
+    ];
+    let cases = [
+        // constant folding
+        ("(Sin(1.5F) * (2F + 3F))", "4.987475F"),
+        ("((3I - 5I) * x[1])", "(x[1] * -2I)"),
+        // identities
+        ("(x[0] + 0F)", "x[0]"),
+        ("(0U + x[2])", "x[2]"),
+        ("(x[1] - x[1])", "0I"),
+        ("(x[0] - x[0])", "(x[0] - x[0])"),
+        ("(x[0] / x[0])", "(x[0] / x[0])"),
+        ("(Sin(x[0]) - Sin(x[0]))", "(Sin(x[0]) - Sin(x[0]))"),
+        ("(x[0] * 1F)", "x[0]"),
+        ("(x[1] * 0I)", "0I"),
+        ("(x[0] * 0F)", "(x[0] * 0F)"),
+        ("(x[2] / x[2])", "1U"),
+        ("(x[0] / 1F)", "x[0]"),
+        ("(x[0] ** 1F)", "x[0]"),
+        ("(Exp(x[0]) ** 0F)", "1F"),
+        ("Abs(Abs(x[0]))", "Abs(x[0])"),
+        ("ReLu(ReLu(x[0]))", "ReLu(x[0])"),
+        ("Sin(ASin(x[0]))", "Sin(ASin(x[0]))"),
+        ("~(~(x[3]))", "x[3]"),
+        ("(x[3] & x[3])", "x[3]"),
+        ("(x[3] | true)", "true"),
+        ("(false & x[3])", "false"),
+        ("(x[1] >= x[1])", "true"),
+        ("(x[0] == x[0])", "(x[0] == x[0])"),
+        // Cond
+        ("((1F > 2F) ? x[0] : Cos(x[0]))", "Cos(x[0])"),
+        ("((x[3] | true) ? x[1] : 4I)", "x[1]"),
+        ("(x[3] ? (x[1] + 1I) : (1I + x[1]))", "(x[1] + 1I)"),
+        // canonical order of commutative operands
+        ("(x[0] + Exp(x[0]))", "(Exp(x[0]) + x[0])"),
+        ("(2F * x[0])", "(x[0] * 2F)"),
+        ("((x[1] + (x[1] * 3I)) - ((3I * x[1]) + x[1]))", "0I"),
+        ("((x[1] + x[1]) == (x[1] + x[1]))", "true"),
+    ];
+    let rows = typed_rows();
+    for (input, expected) in cases.iter() {
+        let expr = et::Expr::parse(input, arg_types.clone(), &registry).unwrap();
+        let expected = et::Expr::parse(expected, arg_types.clone(), &registry).unwrap();
+        let mut pruned = expr.clone();
+        pruned.prune();
+        assert_eq!(pruned.to_equation_str(), expected.to_equation_str(), "pruning {}", input);
+        check_preserved(&expr, &rows);
+    }
+}
+
+#[test]
+fn prune_resets_error_of_changed_trees() {
+    let registry = NodeRegistry::new();
+    let (train_x, train_y) = common::data();
+    let err_fn = |y, p| match (y, p) {
+        (nb::Type::Float(y), nb::Type::Float(p)) => (y - p) * (y - p),
+        _ => unreachable!(),
+    };
+    for (eq, changes) in [("(x[0] + 0F)", true), ("(x[0] - x[0])", false)].iter() {
+        let mut expr = et::Expr::parse(eq, vec![nb::TypeV::Float], &registry).unwrap();
+        expr.calc_err(&train_x, &train_y, &err_fn);
+        let error = format!("{:?}", expr.error);
+        expr.prune();
+        assert_eq!(matches!(expr.error, et::Error::Uncalculated), *changes, "{}", eq);
+        assert_eq!(expr.case_errors.is_empty(), *changes, "{}", eq);
+        if !changes {
+            assert_eq!(format!("{:?}", expr.error), error);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Checks that training picks the subject with the least validation error,
+//! and stops once the validation error runs out of patience
+use genetic_algo::algorithm::expr_tree::{self as et, Error};
+use genetic_algo::algorithm::loss::Mae;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::algorithm::report::StopReason;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::registry::NodeRegistry;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+mod common;
+
+/// noisy samples of `x² - x` on [-2, 2]
+fn data(n: usize, seed: u64) -> (Vec<Vec<nb::Type>>, Vec<nb::Type>) {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    (0..n)
+        .map(|_| {
+            let x: f32 = rng.gen_range(-2.0..=2.0);
+            let y = x * x - x + rng.gen_range(-0.3..=0.3);
+            (vec![nb::Type::Float(x)], nb::Type::Float(y))
+        })
+        .unzip()
+}
+
+#[test]
+fn valid_err_keeps_training_err() {
+    let registry = NodeRegistry::new();
+    let (train_x, train_y) = data(20, 1);
+    let (valid_x, valid_y) = data(10, 2);
+    let mut expr = et::Expr::parse("(x[0] * x[0])", vec![nb::TypeV::Float], &registry).unwrap();
+    expr.calc_err(&train_x, &train_y, &Mae);
+    let (error, case_errors) = (expr.error, expr.case_errors.clone());
+    expr.calc_valid_err(&valid_x, &valid_y, &Mae);
+    assert!(expr.error.cmp(&error).is_eq());
+    assert_eq!(expr.case_errors, case_errors);
+
+    let mut check = expr.clone();
+    check.calc_err(&valid_x, &valid_y, &Mae);
+    assert!(expr.valid_error.cmp(&check.error).is_eq());
+}
+
+#[test]
+fn train_returns_best_on_validation() {
+    let (train_x, train_y) = data(40, 3);
+    let (valid_x, valid_y) = data(40, 4);
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .valid_x(&valid_x)
+        .valid_y(&valid_y)
+        .loss(Box::new(Mae))
+        .n_subs(60)
+        .n_iter(30)
+        .compile();
+    let mut popln = common::population(5);
+    let best = popln.train(&args).best;
+
+    let mut check = best.clone();
+    check.calc_err(&train_x, &train_y, &Mae);
+    assert!(best.error.cmp(&check.error).is_eq());
+    check.calc_err(&valid_x, &valid_y, &Mae);
+    assert!(best.valid_error.cmp(&check.error).is_eq());
+
+    // no worse on validation than the best on the training data at the end
+    let mut last = popln.p[0].clone();
+    last.calc_valid_err(&valid_x, &valid_y, &Mae);
+    assert!(best.valid_error.cmp(&last.valid_error).is_le());
+}
+
+#[test]
+fn early_stopping() {
+    let (train_x, train_y) = data(40, 6);
+    let (valid_x, valid_y) = data(40, 7);
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .valid_x(&valid_x)
+        .valid_y(&valid_y)
+        .loss(Box::new(Mae))
+        .n_subs(40)
+        .n_iter(100000)
+        .patience(5)
+        .compile();
+    let mut popln = common::population(8);
+    popln.init_population(args.n_subs);
+    let reason = loop {
+        if let Some(reason) = popln.step(&args, None) {
+            break reason;
+        }
+    };
+    assert_eq!(reason, StopReason::Patience);
+    assert!(popln.state.iter < args.n_iter);
+    assert_eq!(popln.state.valid_stagnant_cycles, 5);
+    let best = popln.state.best_valid.unwrap();
+    assert!(matches!(best.valid_error, Error::Err { .. }));
+}
+
+#[test]
+#[should_panic(expected = "Required: both valid_x and valid_y")]
+fn validation_needs_outputs() {
+    let (train_x, train_y) = data(10, 9);
+    ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .valid_x(&train_x)
+        .compile();
+}
@@ -0,0 +1,113 @@
+//! Checks that the nodes of a build table are picked by their weights,
+//! and that the weights adapt to the trees they're given
+use genetic_algo::algorithm::expr_tree as et;
+use genetic_algo::algorithm::population as ap;
+use genetic_algo::node::base as nb;
+use genetic_algo::node::btables::FloatFnTable;
+use genetic_algo::node::math_nodes as math;
+use genetic_algo::node::op_nodes as ops;
+use genetic_algo::node::registry::NodeRegistry;
+
+mod common;
+
+/// `Add` with weight `w_add` and `Sin` with weight `w_sin`
+fn table(w_add: f32, w_sin: f32) -> nb::BuilderTable {
+    let mut table = nb::BuilderTable::new();
+    table.push_weighted(nb::TypeV::Float, ops::Add::zero(nb::TypeV::Float, common::floats()), w_add);
+    table.push_weighted(nb::TypeV::Float, math::Sin::zero(), w_sin);
+    table
+}
+
+/// names picked for the root of `n` trees
+fn pick(table: &nb::BuilderTable, n: usize) -> Vec<String> {
+    let mut params = nb::BuilderParams::new().termination_probability(0.0).seed(23);
+    (0..n)
+        .map(|_| table.get_rand_node(0, nb::TypeV::Float, &mut params).get_name().to_string())
+        .collect()
+}
+
+#[test]
+fn picks_by_weight() {
+    let names = pick(&table(3.0, 1.0), 4000);
+    let n_add = names.iter().filter(|n| **n == "+").count();
+    assert!((2800..3200).contains(&n_add), "{}", n_add);
+
+    let names = pick(&table(0.0, 1.0), 100);
+    assert!(names.iter().all(|n| *n == "Sin"));
+}
+
+#[test]
+fn zero_weights_give_terminals() {
+    let mut table = table(0.0, 0.0);
+    table.set_terminal_weights(0.0, 1.0);
+    assert!(pick(&table, 100).iter().all(|n| *n == "Var"));
+    table.set_terminal_weights(1.0, 0.0);
+    assert!(pick(&table, 100).iter().all(|n| *n == "Val"));
+}
+
+#[test]
+fn adapts_to_trees() {
+    let mut table = table(1.0, 1.0);
+    let registry = NodeRegistry::new();
+    let tree = et::Expr::parse("((x[0] + x[1]) + (x[0] + 1F))", common::floats(), &registry).unwrap();
+    for _ in 0..20 {
+        table.adapt_weights(&[&tree.root], 0.5);
+    }
+    let weights = table.get_weights(nb::TypeV::Float);
+    assert!(weights[0] > weights[1] && weights[1] > 0.0);
+    // the total weight is kept
+    assert!((weights[0] + weights[1] - 2.0).abs() < 1e-4);
+    let (val, var) = table.get_terminal_weights();
+    assert!(var > val && val > 0.0);
+}
+
+#[test]
+fn adapting_keeps_zero_weights() {
+    let mut table = table(1.0, 0.0);
+    table.set_terminal_weights(0.0, 1.0);
+    let registry = NodeRegistry::new();
+    let tree = et::Expr::parse("Sin((x[0] + 1F))", common::floats(), &registry).unwrap();
+    for _ in 0..20 {
+        table.adapt_weights(&[&tree.root], 0.5);
+    }
+    let weights = table.get_weights(nb::TypeV::Float);
+    assert_eq!(weights[1], 0.0);
+    assert!((weights[0] - 1.0).abs() < 1e-4);
+    assert_eq!(table.get_terminal_weights(), (0.0, 1.0));
+}
+
+#[test]
+fn training_adapts_weights() {
+    let (train_x, train_y): (Vec<_>, Vec<_>) = (0..20)
+        .map(|i| {
+            let x = i as f32 / 4.0;
+            (vec![nb::Type::Float(x)], nb::Type::Float(x * x))
+        })
+        .unzip();
+    let args = ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .n_subs(30)
+        .n_iter(10)
+        .adapt_weights(5, 0.2)
+        .compile();
+    let mut popln = ap::Population::new(vec![nb::TypeV::Float], nb::TypeV::Float);
+    popln.set_build_table(FloatFnTable::new().table);
+    popln.set_params(nb::BuilderParams::new().max_depth(5).seed(31));
+    popln.train(&args);
+    let weights = popln.builder_table.get_weights(nb::TypeV::Float);
+    assert!(weights.iter().all(|w| *w > 0.0));
+    assert!(weights.iter().any(|w| (w - 1.0).abs() > 1e-3));
+}
+
+#[test]
+#[should_panic(expected = "adapt_weights can't be used with checkpoint")]
+fn adapting_is_not_checkpointed() {
+    let (train_x, train_y) = (vec![vec![nb::Type::Float(1.0)]], vec![nb::Type::Float(1.0)]);
+    ap::TrainingArgs::new()
+        .train_x(&train_x)
+        .train_y(&train_y)
+        .adapt_weights(5, 0.2)
+        .checkpoint(std::env::temp_dir().join("genetic_algo_adapt.json"), 1)
+        .compile();
+}